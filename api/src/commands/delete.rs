@@ -0,0 +1,98 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use database::Database;
+
+use crate::config::AppConfig;
+
+/// Deletes a project and all of its data from the configured database
+///
+/// Unless `yes` is set the name of the project has to be typed again to confirm, anything else
+/// aborts without deleting.
+pub async fn run(config_path: PathBuf, project: String, yes: bool) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load(config_path)?;
+    let database = Database::with_options(
+        config.get_database_url()?.get_as_str(),
+        config.get_pool_config()?,
+    )
+    .await?;
+
+    if !yes {
+        print!("Type the project name to confirm: ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().lock().read_line(&mut input)?;
+        if !confirmed(&project, &input) {
+            return Err("Project name did not match, nothing was deleted".into());
+        }
+    }
+
+    let datapoints = delete(&database, &project).await?;
+    println!(
+        "Deleted project '{}' and {} datapoints",
+        project, datapoints
+    );
+
+    Ok(())
+}
+
+/// Whether the typed confirmation is the project name, ignoring the line ending
+fn confirmed(project: &str, input: &str) -> bool {
+    input.trim_end_matches(['\r', '\n']) == project
+}
+
+/// Deletes the project, archived or not
+///
+/// # Returns
+/// The number of datapoints that were deleted with the project
+pub async fn delete(database: &Database, project: &str) -> Result<i64, Box<dyn Error>> {
+    let datapoints = match database.find_project(project, true).await? {
+        Some(project) => project.datapoint_count().await?,
+        None => return Err(format!("Project '{}' does not exist", project).into()),
+    };
+
+    database.delete_project(project).await?;
+    Ok(datapoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use database::Database;
+
+    use super::{confirmed, delete};
+
+    #[test]
+    fn confirmation() {
+        assert!(confirmed("foo", "foo\n"));
+        assert!(confirmed("foo", "foo\r\n"));
+        assert!(confirmed("foo bar", "foo bar\n"));
+        assert!(!confirmed("foo", "fo\n"));
+        assert!(!confirmed("foo", "\n"));
+        assert!(!confirmed("foo", " foo\n"));
+    }
+
+    #[tokio::test]
+    async fn delete_project() {
+        let database = Database::new("sqlite:file:delete_command?mode=memory&cache=shared")
+            .await
+            .unwrap();
+
+        let foo = database.create_project("foo").await.unwrap();
+        for value in ["1", "2", "3"] {
+            let data = HashMap::from([("a".to_string(), value.to_string())]);
+            foo.add_datapoint(data).await.unwrap();
+        }
+
+        assert_eq!(delete(&database, "foo").await.unwrap(), 3);
+        assert!(database.get_project("foo").await.unwrap().is_none());
+
+        let error = delete(&database, "foo").await.unwrap_err();
+        assert!(error.to_string().contains("foo"));
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use clap::ValueEnum;
+use database::{csv::CsvOptions, Database};
+use serde_json::Value;
+
+use crate::{config::AppConfig, routes::data::datapoint_to_json};
+
+/// Format a project is exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+    Parquet,
+}
+
+/// Writes all datapoints of a project in the configured database to a file, or stdout if no
+/// file is given
+pub async fn run(
+    config_path: PathBuf,
+    project: String,
+    format: Format,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load(config_path)?;
+    let database = Database::with_options(
+        config.get_database_url()?.get_as_str(),
+        config.get_pool_config()?,
+    )
+    .await?;
+
+    let exported = export(&database, &project, format).await?;
+    match output {
+        Some(path) => std::fs::write(path, exported)?,
+        None => io::stdout().write_all(&exported)?,
+    }
+
+    Ok(())
+}
+
+/// Exports a project the same way as the `/:project/csv` and `/:project` endpoints, except
+/// that the json holds every datapoint instead of a single page
+pub async fn export(
+    database: &Database,
+    project: &str,
+    format: Format,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let project = database
+        .get_project(project)
+        .await?
+        .ok_or_else(|| format!("Project '{}' does not exist", project))?;
+
+    Ok(match format {
+        Format::Csv => project.to_csv(&CsvOptions::default()).await?.into_bytes(),
+        Format::Json => {
+            let datapoints = project
+                .get_data_with_timestamps()
+                .await?
+                .into_iter()
+                .map(datapoint_to_json)
+                .collect();
+            format!("{}\n", Value::Array(datapoints)).into_bytes()
+        }
+        Format::Parquet => {
+            let mut parquet = Vec::new();
+            project.to_parquet(&mut parquet).await?;
+            parquet
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use database::Database;
+
+    use super::{export, Format};
+
+    #[tokio::test]
+    async fn export_project() {
+        let database = Database::new("sqlite:file:export_command?mode=memory&cache=shared")
+            .await
+            .unwrap();
+
+        let foo = database.create_project("foo").await.unwrap();
+        for value in ["1", "2"] {
+            let data = HashMap::from([("a".to_string(), value.to_string())]);
+            foo.add_datapoint(data).await.unwrap();
+        }
+
+        let csv = export(&database, "foo", Format::Csv).await.unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines: Vec<&str> = csv.lines().collect();
+        lines[1..].sort();
+        assert_eq!(lines, ["a", "1", "2"]);
+
+        let json = export(&database, "foo", Format::Json).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let datapoints = json.as_array().unwrap();
+        assert_eq!(datapoints.len(), 2);
+        assert!(datapoints.iter().any(|d| d["a"] == "1"));
+        assert!(datapoints[0]["__timestamp__"].is_string());
+
+        // Parquet files start and end with their magic bytes
+        let parquet = export(&database, "foo", Format::Parquet).await.unwrap();
+        assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+
+        let error = export(&database, "missing", Format::Csv).await.unwrap_err();
+        assert!(error.to_string().contains("missing"));
+    }
+}
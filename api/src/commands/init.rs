@@ -0,0 +1,81 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::config;
+
+/// Creates a config file with the default settings, leaving an existing one alone unless
+/// `force` is set
+pub fn run(path: &Path, force: bool) -> Result<(), Box<dyn Error>> {
+    let contents = config::generate_default_config()?;
+
+    match write_config(path, &contents, force)? {
+        true => println!("Created config file {}", path.display()),
+        false => println!(
+            "Config file {} already exists, use --force to overwrite it",
+            path.display()
+        ),
+    }
+
+    Ok(())
+}
+
+/// Writes the config file unless it exists and `force` is not set
+///
+/// The contents are written to a temporary file next to it first, which is then renamed over
+/// the config file. A failed write never leaves a truncated config behind.
+///
+/// # Returns
+/// Whether the file was written
+fn write_config(path: &Path, contents: &str, force: bool) -> io::Result<bool> {
+    if path.exists() && !force {
+        return Ok(false);
+    }
+
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| fs::rename(&temp_path, path)) {
+        fs::remove_file(&temp_path).ok();
+        return Err(e);
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::write_config;
+
+    #[test]
+    fn init_twice() {
+        let dir = std::env::temp_dir().join("fkit_init_twice");
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fkit.toml");
+
+        assert!(write_config(&path, "first", false).unwrap());
+        assert!(!write_config(&path, "second", false).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+
+        assert!(write_config(&path, "third", true).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+
+        // Only the config itself is left, no temporary file
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,5 @@
+pub mod delete;
+pub mod export;
+pub mod init;
+pub mod stats;
+pub mod token;
@@ -0,0 +1,154 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::ValueEnum;
+use database::{stats::Stats, Database};
+use serde_json::json;
+
+use crate::config::AppConfig;
+
+/// How the stats are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+/// Prints the number of projects, columns and datapoints in the configured database
+pub async fn run(
+    config_path: PathBuf,
+    per_project: bool,
+    format: Format,
+) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load(config_path)?;
+    let database = Database::with_options(
+        config.get_database_url()?.get_as_str(),
+        config.get_pool_config()?,
+    )
+    .await?;
+
+    let stats = database.stats().await?;
+    println!("{}", render(&stats, per_project, format));
+
+    Ok(())
+}
+
+/// Formats the stats, including a breakdown per project if requested
+pub fn render(stats: &Stats, per_project: bool, format: Format) -> String {
+    match format {
+        Format::Table => render_table(stats, per_project),
+        Format::Json => render_json(stats, per_project),
+    }
+}
+
+fn render_table(stats: &Stats, per_project: bool) -> String {
+    let mut output = format!(
+        "Projects:   {}\nColumns:    {}\nDatapoints: {}",
+        stats.projects, stats.columns, stats.datapoints
+    );
+
+    if per_project {
+        let rows: Vec<[String; 3]> = std::iter::once([
+            "PROJECT".to_string(),
+            "COLUMNS".to_string(),
+            "DATAPOINTS".to_string(),
+        ])
+        .chain(stats.per_project.iter().map(|p| {
+            [
+                p.name.clone(),
+                p.columns.to_string(),
+                p.datapoints.to_string(),
+            ]
+        }))
+        .collect();
+
+        let widths: Vec<usize> = (0..3)
+            .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap_or(0))
+            .collect();
+
+        output.push('\n');
+        for row in rows {
+            let line = format!(
+                "{:<w0$}  {:>w1$}  {:>w2$}",
+                row[0],
+                row[1],
+                row[2],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2]
+            );
+            output.push('\n');
+            output.push_str(line.trim_end());
+        }
+    }
+
+    output
+}
+
+fn render_json(stats: &Stats, per_project: bool) -> String {
+    let mut json = json!({
+        "projects": stats.projects,
+        "columns": stats.columns,
+        "datapoints": stats.datapoints,
+    });
+
+    if per_project {
+        json["per_project"] = stats
+            .per_project
+            .iter()
+            .map(|p| {
+                json!({
+                    "name": p.name,
+                    "columns": p.columns,
+                    "datapoints": p.datapoints,
+                })
+            })
+            .collect();
+    }
+
+    json.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use database::Database;
+
+    use super::{render, Format};
+
+    #[tokio::test]
+    async fn totals() {
+        let database = Database::new("sqlite:file:stats_command?mode=memory&cache=shared")
+            .await
+            .unwrap();
+
+        let foo = database.create_project("foo").await.unwrap();
+        for value in ["1", "2", "3"] {
+            let mut data = HashMap::new();
+            data.insert("a".to_string(), value.to_string());
+            data.insert("b".to_string(), value.to_string());
+            foo.add_datapoint(data).await.unwrap();
+        }
+        database.create_project("bar").await.unwrap();
+
+        let stats = database.stats().await.unwrap();
+
+        let table = render(&stats, false, Format::Table);
+        assert!(table.contains("Projects:   2"));
+        assert!(table.contains("Columns:    2"));
+        assert!(table.contains("Datapoints: 3"));
+        assert!(!table.contains("PROJECT"));
+
+        let table = render(&stats, true, Format::Table);
+        assert!(table.contains("bar            0           0"));
+        assert!(table.contains("foo            2           3"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&stats, true, Format::Json)).unwrap();
+        assert_eq!(json["projects"], 2);
+        assert_eq!(json["columns"], 2);
+        assert_eq!(json["datapoints"], 3);
+        assert_eq!(json["per_project"][1]["name"], "foo");
+        assert_eq!(json["per_project"][1]["datapoints"], 3);
+    }
+}
@@ -0,0 +1,23 @@
+use std::{error::Error, path::PathBuf};
+
+use database::Database;
+
+use crate::config::AppConfig;
+
+/// Creates a token that can only write to the project and prints it
+///
+/// Only a hash of the token is stored, so this is the only time it is shown.
+pub async fn run(config_path: PathBuf, project: String) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load(config_path)?;
+    let database = Database::with_options(
+        config.get_database_url()?.get_as_str(),
+        config.get_pool_config()?,
+    )
+    .await?;
+
+    let token = database.create_project_token(&project).await?;
+    eprintln!("Token for project '{}', it is not shown again:", project);
+    println!("{}", token);
+
+    Ok(())
+}
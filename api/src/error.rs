@@ -0,0 +1,129 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use database::validation::FieldError;
+use serde_json::{json, Value};
+
+/// Error returned from a request handler
+///
+/// Rendered as a json body in the form of `{"error": "message"}` with the given status code.
+/// Errors about specific fields also list those under `"errors"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+    errors: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new<M>(status: StatusCode, message: M) -> ApiError
+    where
+        M: Into<String>,
+    {
+        ApiError {
+            status,
+            message: message.into(),
+            errors: None,
+        }
+    }
+
+    pub fn not_found<M: Into<String>>(message: M) -> ApiError {
+        ApiError::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn bad_request<M: Into<String>>(message: M) -> ApiError {
+        ApiError::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    #[cfg(test)]
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut body = json!({ "error": self.message });
+        if let Some(errors) = self.errors {
+            body["errors"] = errors;
+        }
+
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        // Creating, duplicating and renaming report taken names as ProjectExists or ColumnExists,
+        // any other unique violation is not the client's fault
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl From<database::Error> for ApiError {
+    fn from(e: database::Error) -> Self {
+        match e {
+            database::Error::Sqlx(e) => ApiError::from(e),
+            database::Error::Migrate(_) | database::Error::SchemaMismatch { .. } => {
+                ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+            }
+            database::Error::SchemaFrozen(_)
+            | database::Error::ColumnExists(_)
+            | database::Error::ProjectExists(_) => {
+                ApiError::new(StatusCode::CONFLICT, e.to_string())
+            }
+            database::Error::QuotaExceeded { .. } => {
+                ApiError::new(StatusCode::INSUFFICIENT_STORAGE, e.to_string())
+            }
+            database::Error::ValueTooLarge { .. } => {
+                ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+            }
+            database::Error::ProjectNotFound(_)
+            | database::Error::ColumnNotFound(_)
+            | database::Error::DatapointNotFound(_) => ApiError::not_found(e.to_string()),
+            database::Error::Csv(_)
+            | database::Error::NotNumeric(_)
+            | database::Error::InvalidDump(_)
+            | database::Error::ColumnNameTooLong { .. }
+            | database::Error::EmptyEncodedName(_)
+            | database::Error::Reserved(_) => ApiError::bad_request(e.to_string()),
+            database::Error::Validation(ref validation) => {
+                let errors = validation.errors.iter().map(field_error_to_json).collect();
+
+                ApiError {
+                    status: StatusCode::UNPROCESSABLE_ENTITY,
+                    message: e.to_string(),
+                    errors: Some(Value::Array(errors)),
+                }
+            }
+            database::Error::InvalidRows(ref rows) => {
+                let errors = rows
+                    .iter()
+                    .flat_map(|row| {
+                        row.errors.errors.iter().map(|error| {
+                            let mut error = field_error_to_json(error);
+                            error["row"] = json!(row.row);
+                            error
+                        })
+                    })
+                    .collect();
+
+                ApiError {
+                    status: StatusCode::UNPROCESSABLE_ENTITY,
+                    message: e.to_string(),
+                    errors: Some(Value::Array(errors)),
+                }
+            }
+        }
+    }
+}
+
+fn field_error_to_json(error: &FieldError) -> Value {
+    json!({
+        "field": error.field,
+        "rule": error.rule,
+        "message": error.message,
+    })
+}
@@ -0,0 +1,66 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+};
+
+use crate::{error::ApiError, routes::AppState};
+
+/// Proof that a request carries the admin token from the config
+///
+/// Requests are rejected with 404 Not Found when no token is configured, so the admin
+/// endpoints do not exist unless enabled, and with 401 Unauthorized when the
+/// `Authorization: Bearer <token>` header is missing or wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Admin;
+
+/// Compares two tokens in time that only depends on their length
+pub(super) fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Admin {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Some(expected) = state.config.get_admin_token() else {
+            return Err(ApiError::not_found("Admin endpoints are disabled"));
+        };
+
+        let given = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        match given {
+            Some(given) if tokens_match(given.as_bytes(), expected.as_bytes()) => Ok(Admin),
+            _ => Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Missing or wrong admin token",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tokens_match;
+
+    #[test]
+    fn compare_tokens() {
+        assert!(tokens_match(b"secret", b"secret"));
+        assert!(!tokens_match(b"secreT", b"secret"));
+        assert!(!tokens_match(b"secret2", b"secret"));
+        assert!(!tokens_match(b"", b"secret"));
+    }
+}
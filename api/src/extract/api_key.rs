@@ -0,0 +1,85 @@
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+};
+use database::Database;
+
+use crate::{error::ApiError, routes::AppState};
+
+use super::admin::tokens_match;
+
+/// Header holding the api key, as an alternative to `Authorization: Bearer <key>`
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Credentials of a request, either one of the api keys from the config or a project token
+///
+/// Every request is allowed when no keys are configured. Otherwise requests are rejected with
+/// 401 Unauthorized unless they have a key in either an `Authorization: Bearer <key>` or an
+/// `X-API-Key` header. A key that is not in the config is taken as a project token, which
+/// handlers check with [ApiKey::authorize].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiKey {
+    /// One of the keys from the config, or none are configured
+    Any,
+    /// A token that is only valid for the projects it was created for
+    Project(String),
+}
+
+impl ApiKey {
+    /// Fails with 403 Forbidden unless the request may write to the project
+    pub async fn authorize(&self, database: &Database, project: &str) -> Result<(), ApiError> {
+        let ApiKey::Project(token) = self else {
+            return Ok(());
+        };
+
+        match database.verify_token(token, project).await? {
+            true => Ok(()),
+            false => Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Token is not authorized for this project",
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiKey {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let keys = state.config.get_api_keys();
+        if keys.is_empty() {
+            return Ok(ApiKey::Any);
+        }
+
+        let bearer = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let header = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok());
+
+        // Compare against every key, so the time taken does not tell which one matched
+        let valid = [bearer, header].into_iter().flatten().any(|given| {
+            keys.iter().fold(false, |valid, key| {
+                valid | tokens_match(given.as_bytes(), key.as_bytes())
+            })
+        });
+
+        match (valid, bearer.or(header)) {
+            (true, _) => Ok(ApiKey::Any),
+            (false, Some(token)) => Ok(ApiKey::Project(token.to_string())),
+            (false, None) => Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid api key",
+            )),
+        }
+    }
+}
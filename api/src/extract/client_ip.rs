@@ -0,0 +1,120 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{request::Parts, StatusCode},
+};
+
+use crate::{error::ApiError, routes::AppState};
+
+/// Header proxies append the address they received a request from to
+pub const FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Address of the client that sent a request
+///
+/// This is the address of the connection, unless it comes from one of the `trusted_proxies`
+/// in the config. Then the client is read from `X-Forwarded-For`, see [client_ip].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// Resolves the client address of a request
+///
+/// `X-Forwarded-For` is only used when the connection comes from a trusted proxy. Its entries
+/// are read from the right, skipping every trusted hop, so a client can not pretend to be
+/// someone else by sending the header itself. An entry that is not an address stops the
+/// search at the hop that added it.
+///
+/// # Arguments
+/// * `peer` Address of the connection
+/// * `forwarded` Value of the `X-Forwarded-For` header, if any
+/// * `trusted` Addresses of the trusted proxies
+pub fn client_ip(peer: IpAddr, forwarded: Option<&str>, trusted: &[IpAddr]) -> IpAddr {
+    let Some(forwarded) = forwarded else {
+        return peer;
+    };
+
+    let mut client = peer;
+    for hop in forwarded.rsplit(',') {
+        if !trusted.contains(&client) {
+            break;
+        }
+
+        match hop.trim().parse::<IpAddr>() {
+            Ok(hop) => client = hop,
+            Err(_) => break,
+        }
+    }
+
+    client
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .copied()
+            .ok_or_else(|| {
+                ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Address of the connection is not available",
+                )
+            })?;
+
+        let forwarded = parts
+            .headers
+            .get(FORWARDED_FOR)
+            .and_then(|value| value.to_str().ok());
+
+        Ok(ClientIp(client_ip(
+            peer.ip(),
+            forwarded,
+            state.config.get_trusted_proxies(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::client_ip;
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        let client = client_ip(ip("1.2.3.4"), Some("5.6.7.8"), &[ip("10.0.0.1")]);
+        assert_eq!(client, ip("1.2.3.4"));
+
+        let client = client_ip(ip("1.2.3.4"), Some("5.6.7.8"), &[]);
+        assert_eq!(client, ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn trusted_peer() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        let client = client_ip(ip("10.0.0.1"), Some("1.2.3.4"), &trusted);
+        assert_eq!(client, ip("1.2.3.4"));
+
+        let client = client_ip(ip("10.0.0.1"), None, &trusted);
+        assert_eq!(client, ip("10.0.0.1"));
+
+        // Entries the client sent itself are ignored
+        let client = client_ip(ip("10.0.0.1"), Some("6.6.6.6, 1.2.3.4, 10.0.0.2"), &trusted);
+        assert_eq!(client, ip("1.2.3.4"));
+
+        let client = client_ip(ip("10.0.0.1"), Some("nonsense"), &trusted);
+        assert_eq!(client, ip("10.0.0.1"));
+    }
+}
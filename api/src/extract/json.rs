@@ -0,0 +1,32 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+use crate::error::ApiError;
+
+/// Json request body, like [axum::Json] but rejected with an [ApiError]
+///
+/// Malformed json, a body that does not match the expected type and a missing content type are
+/// all answered with the usual json error body, with the detail from axum as the message.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonBody<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for JsonBody<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|e| ApiError::new(e.status(), e.body_text()))?;
+
+        Ok(JsonBody(value))
+    }
+}
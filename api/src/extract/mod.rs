@@ -0,0 +1,16 @@
+mod admin;
+mod api_key;
+mod client_ip;
+mod json;
+mod pagination;
+mod time_range;
+mod value_range;
+
+pub use admin::Admin;
+pub use api_key::ApiKey;
+pub use client_ip::ClientIp;
+pub use json::JsonBody;
+pub use pagination::PaginationParams;
+pub(crate) use time_range::deserialize_time;
+pub use time_range::TimeRangeParams;
+pub use value_range::ValueRangeParams;
@@ -0,0 +1,119 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use serde::Deserialize;
+
+use crate::{error::ApiError, routes::AppState};
+
+/// Number of items returned when no limit is given
+pub const DEFAULT_LIMIT: i64 = 100;
+
+/// Validated `?limit=&offset=` query parameters for list and data endpoints
+///
+/// Requests with a limit outside of `1..=max_page_limit` or a negative offset are rejected
+/// with 400 Bad Request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationParams {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawPagination {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl PaginationParams {
+    /// Validate pagination parameters, filling in defaults for the missing ones
+    pub fn new(
+        limit: Option<i64>,
+        offset: Option<i64>,
+        max_limit: i64,
+    ) -> Result<PaginationParams, ApiError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT.min(max_limit));
+        let offset = offset.unwrap_or(0);
+
+        if !(1..=max_limit).contains(&limit) {
+            return Err(ApiError::bad_request(format!(
+                "limit must be between 1 and {}",
+                max_limit
+            )));
+        }
+
+        if offset < 0 {
+            return Err(ApiError::bad_request("offset can not be negative"));
+        }
+
+        Ok(PaginationParams { limit, offset })
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for PaginationParams {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(raw) = Query::<RawPagination>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::bad_request(e.body_text()))?;
+
+        PaginationParams::new(raw.limit, raw.offset, state.config.get_max_page_limit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::{PaginationParams, DEFAULT_LIMIT};
+
+    #[test]
+    fn defaults() {
+        let params = PaginationParams::new(None, None, 1000).unwrap();
+
+        assert_eq!(params.limit, DEFAULT_LIMIT);
+        assert_eq!(params.offset, 0);
+    }
+
+    #[test]
+    fn valid() {
+        let params = PaginationParams::new(Some(1000), Some(20), 1000).unwrap();
+
+        assert_eq!(
+            params,
+            PaginationParams {
+                limit: 1000,
+                offset: 20
+            }
+        );
+    }
+
+    #[test]
+    fn negative_limit() {
+        let err = PaginationParams::new(Some(-1), None, 1000).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+
+        let err = PaginationParams::new(Some(0), None, 1000).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn limit_over_max() {
+        let err = PaginationParams::new(Some(1001), None, 1000).unwrap_err();
+
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn negative_offset() {
+        let err = PaginationParams::new(None, Some(-5), 1000).unwrap_err();
+
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{de, Deserialize, Deserializer};
+
+/// `?from=&to=` query parameters limiting datapoints to a time range
+///
+/// Both bounds accept an ISO-8601 time or unix seconds. `from` is inclusive and `to` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub struct TimeRangeParams {
+    #[serde(default, deserialize_with = "deserialize_time")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_time")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl TimeRangeParams {
+    /// The range with missing bounds left open, None if neither bound is given
+    pub fn bounds(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if self.from.is_none() && self.to.is_none() {
+            return None;
+        }
+
+        Some((
+            self.from.unwrap_or(DateTime::<Utc>::MIN_UTC),
+            self.to.unwrap_or(DateTime::<Utc>::MAX_UTC),
+        ))
+    }
+}
+
+/// Parses an ISO-8601 time or unix seconds
+pub fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(seconds) = value.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0);
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+pub(crate) fn deserialize_time<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    parse_time(&value).map(Some).ok_or_else(|| {
+        de::Error::custom(format!(
+            "'{}' is neither an ISO-8601 time nor unix seconds",
+            value
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::parse_time;
+
+    #[test]
+    fn unix_seconds() {
+        assert_eq!(parse_time("100"), DateTime::from_timestamp(100, 0));
+    }
+
+    #[test]
+    fn iso_8601() {
+        assert_eq!(
+            parse_time("1970-01-01T00:01:40+00:00"),
+            DateTime::from_timestamp(100, 0)
+        );
+        assert_eq!(
+            parse_time("1970-01-01T01:01:40+01:00"),
+            DateTime::from_timestamp(100, 0)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(parse_time("yesterday"), None);
+    }
+}
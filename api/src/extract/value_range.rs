@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+
+use crate::error::ApiError;
+
+/// `?<column>_min=&<column>_max=` query parameters limiting datapoints to a range of values
+///
+/// Both bounds are inclusive and either can be left out. Only a single column can be filtered
+/// on, requests with bounds for more than one column are rejected with 400 Bad Request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueRangeParams {
+    /// The column and its bounds, None if no bounds are given
+    pub range: Option<ValueRange>,
+}
+
+/// Bounds of a single column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueRange {
+    pub column: String,
+    pub min: Option<String>,
+    pub max: Option<String>,
+}
+
+impl ValueRangeParams {
+    /// Finds the bounds among all query parameters
+    pub fn new(params: HashMap<String, String>) -> Result<ValueRangeParams, ApiError> {
+        let mut range: Option<ValueRange> = None;
+
+        for (key, value) in params {
+            let (column, is_min) = match (key.strip_suffix("_min"), key.strip_suffix("_max")) {
+                (Some(column), _) => (column, true),
+                (_, Some(column)) => (column, false),
+                _ => continue,
+            };
+            if column.is_empty() {
+                continue;
+            }
+
+            let range = range.get_or_insert_with(|| ValueRange {
+                column: column.to_string(),
+                min: None,
+                max: None,
+            });
+            if range.column != column {
+                return Err(ApiError::bad_request(
+                    "Values can only be filtered on a single column",
+                ));
+            }
+
+            match is_min {
+                true => range.min = Some(value),
+                false => range.max = Some(value),
+            }
+        }
+
+        Ok(ValueRangeParams { range })
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ValueRangeParams {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(params) = Query::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::bad_request(e.body_text()))?;
+
+        ValueRangeParams::new(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::StatusCode;
+
+    use super::{ValueRange, ValueRangeParams};
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn single_column() {
+        let params = ValueRangeParams::new(params(&[
+            ("size_min", "1"),
+            ("size_max", "5"),
+            ("limit", "10"),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            params.range,
+            Some(ValueRange {
+                column: "size".to_string(),
+                min: Some("1".to_string()),
+                max: Some("5".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn no_bounds() {
+        let params = ValueRangeParams::new(params(&[("from", "100"), ("_min", "1")])).unwrap();
+
+        assert_eq!(params.range, None);
+    }
+
+    #[test]
+    fn multiple_columns() {
+        let err =
+            ValueRangeParams::new(params(&[("size_min", "1"), ("price_max", "5")])).unwrap_err();
+
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, time::Duration};
+
+use database::Database;
+use tokio::sync::{mpsc, oneshot};
+
+/// How often buffered datapoints are written, unless configured otherwise
+pub const DEFAULT_BATCH_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// How many datapoints of a project are buffered before they are written, unless configured
+/// otherwise
+pub const DEFAULT_BATCH_MAX_ROWS: usize = 500;
+
+/// When buffered datapoints are written to the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchSettings {
+    /// Time between writes of everything that is buffered
+    pub interval: Duration,
+    /// Number of buffered datapoints for a single project that triggers a write right away
+    pub max_rows: usize,
+}
+
+impl Default for BatchSettings {
+    fn default() -> Self {
+        BatchSettings {
+            interval: DEFAULT_BATCH_INTERVAL,
+            max_rows: DEFAULT_BATCH_MAX_ROWS,
+        }
+    }
+}
+
+enum Message {
+    Datapoint {
+        project: String,
+        data: HashMap<String, String>,
+    },
+    Flush(oneshot::Sender<()>),
+    Shutdown(oneshot::Sender<()>),
+}
+
+/// Queues incoming datapoints and writes them to the database in batches
+///
+/// The datapoints are written by a background task, one transaction per project. Cloning the
+/// buffer gives another handle to the same queue.
+#[derive(Debug, Clone)]
+pub struct IngestBuffer {
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+impl IngestBuffer {
+    /// Starts the background task writing to the given database
+    pub fn spawn(database: Database, settings: BatchSettings) -> IngestBuffer {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(database, settings, receiver));
+
+        IngestBuffer { sender }
+    }
+
+    /// Queues a datapoint for the given project
+    ///
+    /// # Returns
+    /// false if the buffer has been shut down and the datapoint was dropped
+    pub fn push(&self, project: String, data: HashMap<String, String>) -> bool {
+        self.sender
+            .send(Message::Datapoint { project, data })
+            .is_ok()
+    }
+
+    /// Writes everything that is buffered, returning once it is in the database
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.sender.send(Message::Flush(done)).is_ok() {
+            wait.await.ok();
+        }
+    }
+
+    /// Writes everything that is buffered and stops the background task
+    ///
+    /// Datapoints pushed after this are dropped.
+    pub async fn shutdown(&self) {
+        let (done, wait) = oneshot::channel();
+        if self.sender.send(Message::Shutdown(done)).is_ok() {
+            wait.await.ok();
+        }
+    }
+}
+
+/// Background task receiving datapoints until the buffer is shut down or every handle is dropped
+async fn run(
+    database: Database,
+    settings: BatchSettings,
+    mut receiver: mpsc::UnboundedReceiver<Message>,
+) {
+    let mut pending: HashMap<String, Vec<HashMap<String, String>>> = HashMap::new();
+    let mut interval = tokio::time::interval(settings.interval);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => flush_all(&database, &mut pending).await,
+            message = receiver.recv() => match message {
+                Some(Message::Datapoint { project, data }) => {
+                    let rows = pending.entry(project.clone()).or_default();
+                    rows.push(data);
+
+                    if rows.len() >= settings.max_rows {
+                        let rows = pending.remove(&project).unwrap_or_default();
+                        write(&database, &project, rows).await;
+                    }
+                }
+                Some(Message::Flush(done)) => {
+                    flush_all(&database, &mut pending).await;
+                    done.send(()).ok();
+                }
+                Some(Message::Shutdown(done)) => {
+                    flush_all(&database, &mut pending).await;
+                    done.send(()).ok();
+                    break;
+                }
+                None => {
+                    flush_all(&database, &mut pending).await;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Writes the buffered datapoints of every project
+async fn flush_all(
+    database: &Database,
+    pending: &mut HashMap<String, Vec<HashMap<String, String>>>,
+) {
+    for (project, rows) in pending.drain() {
+        write(database, &project, rows).await;
+    }
+}
+
+/// Writes a batch of datapoints to a project, creating it if it does not exist
+///
+/// Nobody is waiting for the result anymore, so failures are only logged. The clients were
+/// already told their datapoints were accepted, so when the batch fails, for example because a
+/// single datapoint does not match the columns of the project, the datapoints are written one by
+/// one and only the invalid ones are lost.
+async fn write(database: &Database, project: &str, rows: Vec<HashMap<String, String>>) {
+    let count = rows.len();
+
+    let name = project;
+    let project = match database.get_or_create_project(name).await {
+        Ok(project) => project,
+        Err(e) => {
+            println!(
+                "Failed to write {} buffered datapoints to {}: {}",
+                count, name, e
+            );
+            return;
+        }
+    };
+
+    let Err(e) = project.add_datapoints(rows.clone()).await else {
+        return;
+    };
+    println!(
+        "Failed to write {} buffered datapoints to {} at once, writing them one by one: {}",
+        count, name, e
+    );
+
+    let mut failed = 0;
+    for row in rows {
+        if let Err(e) = project.add_datapoint_typed(row).await {
+            println!("Failed to write a buffered datapoint to {}: {}", name, e);
+            failed += 1;
+        }
+    }
+    println!(
+        "Wrote {} of {} buffered datapoints to {}, {} failed",
+        count - failed,
+        count,
+        name,
+        failed
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn flush_writes_everything() {
+        let database =
+            Database::new("sqlite:file:flush_writes_everything?mode=memory&cache=shared")
+                .await
+                .unwrap();
+
+        let settings = BatchSettings {
+            interval: Duration::from_secs(3600),
+            max_rows: 100,
+        };
+        let buffer = IngestBuffer::spawn(database.clone(), settings);
+
+        for i in 0..250 {
+            let data = HashMap::from([("count".to_string(), i.to_string())]);
+            assert!(buffer.push("foo".to_string(), data));
+        }
+        buffer.flush().await;
+
+        let project = database.get_project("foo").await.unwrap().unwrap();
+        assert_eq!(project.datapoint_count().await.unwrap(), 250);
+    }
+
+    #[tokio::test]
+    async fn invalid_datapoint_fails_alone() {
+        let database =
+            Database::new("sqlite:file:invalid_datapoint_fails_alone?mode=memory&cache=shared")
+                .await
+                .unwrap();
+
+        let settings = BatchSettings {
+            interval: Duration::from_secs(3600),
+            max_rows: 100,
+        };
+        let buffer = IngestBuffer::spawn(database.clone(), settings);
+
+        // The first datapoint makes count an integer column, which the third does not fit
+        for count in ["1", "2", "many", "4"] {
+            let data = HashMap::from([("count".to_string(), count.to_string())]);
+            assert!(buffer.push("foo".to_string(), data));
+        }
+        buffer.flush().await;
+
+        let project = database.get_project("foo").await.unwrap().unwrap();
+        assert_eq!(project.datapoint_count().await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn shutdown_writes_everything() {
+        let database =
+            Database::new("sqlite:file:shutdown_writes_everything?mode=memory&cache=shared")
+                .await
+                .unwrap();
+
+        let settings = BatchSettings {
+            interval: Duration::from_secs(3600),
+            max_rows: 100,
+        };
+        let buffer = IngestBuffer::spawn(database.clone(), settings);
+
+        for i in 0..10 {
+            let data = HashMap::from([("count".to_string(), i.to_string())]);
+            buffer.push("foo".to_string(), data);
+        }
+        buffer.shutdown().await;
+
+        let project = database.get_project("foo").await.unwrap().unwrap();
+        assert_eq!(project.datapoint_count().await.unwrap(), 10);
+        assert!(!buffer.push("foo".to_string(), HashMap::new()));
+    }
+}
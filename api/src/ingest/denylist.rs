@@ -0,0 +1,132 @@
+use std::{collections::HashMap, collections::HashSet, str::FromStr};
+
+use database::validation::ValidationErrors;
+
+/// Rule violated by a field that is on the denylist
+pub const RULE_DENIED_FIELD: &str = "denied_field";
+
+/// What happens to a datapoint with a denied field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeniedFieldAction {
+    /// Store the datapoint without the denied fields
+    Drop,
+    /// Refuse the whole datapoint
+    #[default]
+    Reject,
+}
+
+impl FromStr for DeniedFieldAction {
+    type Err = String;
+
+    /// Parses the config name of an action
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(DeniedFieldAction::Drop),
+            "reject" => Ok(DeniedFieldAction::Reject),
+            other => Err(format!(
+                "Unknown denied field action '{}', expected 'drop' or 'reject'",
+                other
+            )),
+        }
+    }
+}
+
+/// Field names that are never stored, like `password`
+///
+/// Names are compared after [normalize], so `Password` and `pass word` are denied by
+/// `password` as well.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeniedFields {
+    fields: HashSet<String>,
+    action: DeniedFieldAction,
+}
+
+/// Lower case name with only the characters that are kept in a column name
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl DeniedFields {
+    pub fn new<S: AsRef<str>>(fields: &[S], action: DeniedFieldAction) -> DeniedFields {
+        DeniedFields {
+            fields: fields.iter().map(|f| normalize(f.as_ref())).collect(),
+            action,
+        }
+    }
+
+    /// Whether the field is on the denylist
+    pub fn is_denied(&self, field: &str) -> bool {
+        !self.fields.is_empty() && self.fields.contains(&normalize(field))
+    }
+
+    /// Removes the denied fields from a datapoint, or fails with all of them when datapoints
+    /// with denied fields are rejected
+    pub fn apply(&self, datapoint: &mut HashMap<String, String>) -> Result<(), ValidationErrors> {
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+
+        match self.action {
+            DeniedFieldAction::Drop => {
+                datapoint.retain(|field, _| !self.is_denied(field));
+                Ok(())
+            }
+            DeniedFieldAction::Reject => {
+                let mut errors = ValidationErrors::default();
+                for field in datapoint.keys().filter(|field| self.is_denied(field)) {
+                    errors.push(
+                        field,
+                        RULE_DENIED_FIELD,
+                        format!("Field '{}' is not allowed to be stored", field),
+                    );
+                }
+                errors.into_result()
+            }
+        }
+    }
+
+    /// Fails with every denied name of columns that are created explicitly
+    ///
+    /// Unlike [DeniedFields::apply] these are always rejected, dropping a column that was asked
+    /// for by name would only hide the mistake.
+    pub fn check_columns<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+        for name in names.into_iter().filter(|name| self.is_denied(name)) {
+            errors.push(
+                name,
+                RULE_DENIED_FIELD,
+                format!("Column '{}' is not allowed to be created", name),
+            );
+        }
+        errors.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_after_normalizing() {
+        let denied = DeniedFields::new(&["Password", "ssn"], DeniedFieldAction::Drop);
+
+        assert!(denied.is_denied("password"));
+        assert!(denied.is_denied("PASS WORD"));
+        assert!(denied.is_denied("SSN"));
+        assert!(!denied.is_denied("username"));
+    }
+
+    #[test]
+    fn columns_rejected_when_dropping() {
+        let denied = DeniedFields::new(&["password"], DeniedFieldAction::Drop);
+
+        assert!(denied.check_columns(["name", "count"]).is_ok());
+        assert!(denied.check_columns(["name", "Password"]).is_err());
+    }
+}
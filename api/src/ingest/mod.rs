@@ -0,0 +1,7 @@
+mod batch;
+mod denylist;
+mod rate_limit;
+
+pub use batch::{BatchSettings, IngestBuffer};
+pub use denylist::{DeniedFieldAction, DeniedFields};
+pub use rate_limit::RateLimiter;
@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of clients tracked before buckets that are full again are forgotten
+const PRUNE_AFTER_CLIENTS: usize = 10_000;
+
+/// Requests a client can still make, refilled over time
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Limits how many requests every client can make per minute
+///
+/// Every client has a bucket holding up to `per_minute` requests, which refills evenly over a
+/// minute. A burst of `per_minute` requests is allowed, after that requests are only allowed
+/// as fast as the bucket refills.
+#[derive(Debug)]
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> RateLimiter {
+        RateLimiter {
+            per_minute: per_minute.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a request from the bucket of the client
+    ///
+    /// # Returns
+    /// How long the client has to wait before its next request is allowed, if this one is not
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = self.per_minute as f64;
+        let per_second = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= PRUNE_AFTER_CLIENTS {
+            buckets.retain(|_, bucket| {
+                let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+                bucket.tokens + elapsed * per_second < capacity
+            });
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::IpAddr,
+        time::{Duration, Instant},
+    };
+
+    use super::RateLimiter;
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(2);
+        let client: IpAddr = "1.2.3.4".parse().unwrap();
+        let other: IpAddr = "5.6.7.8".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(client, start).is_ok());
+        assert!(limiter.check_at(client, start).is_ok());
+
+        let wait = limiter.check_at(client, start).unwrap_err();
+        assert_eq!(wait.as_secs(), 30);
+
+        // Other clients have buckets of their own
+        assert!(limiter.check_at(other, start).is_ok());
+
+        assert!(limiter
+            .check_at(client, start + Duration::from_secs(31))
+            .is_ok());
+        assert!(limiter
+            .check_at(client, start + Duration::from_secs(32))
+            .is_err());
+    }
+}
@@ -1,17 +1,19 @@
 use clap::{Parser, Subcommand};
-use config::AppConfig;
+use commands::{delete, export, init, stats, token};
+use config::{AppConfig, Schema};
 use database::Database;
-use std::{collections::HashMap, error::Error, path::PathBuf};
-
-use axum::{
-    extract::{Path, Query, State},
-    response::{IntoResponse, Result},
-    routing::post,
-    Router,
-};
+use routes::AppState;
+use std::{error::Error, net::SocketAddr, path::PathBuf};
+
 use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
 
+mod commands;
 mod config;
+mod error;
+mod extract;
+mod ingest;
+mod routes;
 mod utils;
 
 #[derive(Parser, Debug)]
@@ -24,27 +26,109 @@ struct Args {
 
 #[derive(Debug, Subcommand)]
 enum Command {
-    Init {},
+    /// Creates a config file with the default settings in the current directory
+    Init {
+        /// Overwrite an existing config file
+        #[clap(short, long)]
+        force: bool,
+    },
     Run {
         #[clap(short, long)]
         config: Option<PathBuf>,
+        /// Port to listen on, overrides the port set in the config file
+        #[clap(short, long)]
+        port: Option<u16>,
+    },
+    /// Prints the number of projects, columns and datapoints in the database
+    Stats {
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// Also list every project
+        #[clap(long)]
+        per_project: bool,
+        #[clap(long, value_enum, default_value_t = stats::Format::Table)]
+        format: stats::Format,
+    },
+    /// Exports all datapoints of a project as CSV, json or Parquet
+    Export {
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// Name of the project to export
+        project: String,
+        #[clap(long, value_enum, default_value_t = export::Format::Csv)]
+        format: export::Format,
+        /// File to write the export to, stdout if not set
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Deletes a project and all of its data, after typing its name to confirm
+    Delete {
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// Name of the project to delete
+        project: String,
+        /// Delete without asking for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+    /// Creates a token that can only add datapoints to a single project and prints it once
+    Token {
+        #[clap(short, long)]
+        config: Option<PathBuf>,
+        /// Name of the project the token is for
+        project: String,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // Log to stderr filtered by RUST_LOG, only errors unless it is set
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
     let args = Args::parse();
     match args.command {
-        Some(Command::Init {}) => {
-            check_config_file()?;
+        Some(Command::Init { force }) => {
+            init::run(&PathBuf::from("fkit.toml"), force)?;
         }
-        Some(Command::Run { config }) => {
+        Some(Command::Run { config, port }) => {
             // if help {
             //     println!("Runs the fkit server with the given config file.");
             //     println!("If no config file is provided the program will fail.");
             //     println!("A standard config file can be created with \"fkit init\", but it can also be created manually.");
             // }
-            run(config).await?;
+            run(config, port).await?;
+        }
+        Some(Command::Stats {
+            config,
+            per_project,
+            format,
+        }) => {
+            let config = config.unwrap_or_else(|| PathBuf::from("fkit.toml"));
+            stats::run(config, per_project, format).await?;
+        }
+        Some(Command::Export {
+            config,
+            project,
+            format,
+            output,
+        }) => {
+            let config = config.unwrap_or_else(|| PathBuf::from("fkit.toml"));
+            export::run(config, project, format, output).await?;
+        }
+        Some(Command::Delete {
+            config,
+            project,
+            yes,
+        }) => {
+            let config = config.unwrap_or_else(|| PathBuf::from("fkit.toml"));
+            delete::run(config, project, yes).await?;
+        }
+        Some(Command::Token { config, project }) => {
+            let config = config.unwrap_or_else(|| PathBuf::from("fkit.toml"));
+            token::run(config, project).await?;
         }
         None => {
             if args.config_help {
@@ -60,136 +144,87 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-async fn run(config_path: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+async fn run(config_path: Option<PathBuf>, port: Option<u16>) -> Result<(), Box<dyn Error>> {
     // Load the config file
     let config_path = config_path.unwrap_or_else(|| PathBuf::from("fkit.toml"));
     let config = AppConfig::load(config_path)?;
 
     // Make sure the database file exists and open the database
-    let database_url = config.get_database_url();
-    check_database_file(database_url.get_location().into())?;
-    let database = Database::new(database_url.get_as_str()).await?;
-
-    // Create the routes
-    let routes = Router::new()
-        .route("/new/:project", post(create_project))
-        .route("/:project", post(add_datapoint))
-        .route("/:project/columns", post(define_columns));
-
-    // Create the app
-    let app = Router::new().nest("/", routes).with_state(database);
-
-    // Create the serber
-    let port = config.get_server_port().unwrap_or(3000);
-    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-
-    // Start the server
-    println!("Listening on: http://localhost:{}", port);
-    axum::serve(listener, app).await?;
-
-    Ok(())
-}
-
-/// Catches the keys and values from the query string and returns them in a formatted string.
-async fn catch_all_text(
-    Path(project): Path<String>,
-    Query(data): Query<HashMap<String, String>>,
-) -> String {
-    let mut response = format!("Project: {}\n", project);
-    let mut datapoint = HashMap::new();
-
-    for (key, value) in data {
-        let entry = format!("{}: {}\n", key, value);
-        datapoint.insert(key, value);
-
-        response.push_str(&entry)
+    let database_url = config.get_database_url()?;
+    if let Schema::Sqlite = database_url.get_schema()? {
+        utils::check_database_file(
+            database_url.get_location().into(),
+            config.get_create_if_missing(),
+        )?;
     }
-
-    response
-}
-
-async fn add_datapoint(
-    Path(project): Path<String>,
-    Query(data): Query<HashMap<String, String>>,
-    State(database): State<Database>,
-) -> Result<String>{
-    let project = match database.get_project(&project).await.map_err(|e| format!("Error: {:?}", e).into_response())? {
-        None => {
-            println!("Project not found, creating new: {}", project);
-            database.create_project(&project).await.map_err(|e| format!("Error: {:?}", e).into_response())?
-        },
-        Some(p) => p,
-    };
-
-    let mut datapoint = HashMap::new();
-    for (key, value) in data {
-        datapoint.insert(key, value);
+    let database = Database::with_options(database_url.get_as_str(), config.get_pool_config()?)
+        .await?
+        .with_max_column_name_len(config.get_max_column_name_len())
+        .with_max_db_bytes(config.get_max_db_bytes())
+        .with_max_blob_bytes(config.get_max_blob_bytes())
+        .with_project_cache(config.get_cache_projects())
+        .with_table_verification(config.get_verify_tables());
+
+    // Only start listening once the database is migrated and reachable
+    if !database.ready().await? {
+        return Err("Database migrations have not been applied".into());
     }
 
-    project.add_datapoint(datapoint).await.map_err(|e| format!("Error: {:?}", e).into_response())?;
-
-    Ok("Success".to_string())
-}
-
-/// Creates a new project and inserts it into the database along with a corresponding table.
-async fn create_project(Path(project): Path<String>, State(database): State<Database>) -> String {
-    if project.contains('/') {
-        return "Project name cannot contain a '/'".to_string();
-    }
+    // Create the serber
+    let addr = config.resolve_server_addr(port)?;
 
-    println!("Creating new project: {}", project);
-    database.create_project(&project).await.unwrap();
+    // Create the app
+    let state = AppState::new(database.clone(), config)?;
+    let ingest = state.ingest.clone();
+    let app = routes::router(state);
 
-    format!("{:?}", project)
-}
+    let listener = TcpListener::bind(addr).await?;
 
-/// Will check that the config file exists in the current directory and create it if it doesnt,
-/// populating it with the default config.
-fn check_config_file() -> Result<(), Box<dyn Error>> {
-    let config_path = PathBuf::from("fkit.toml");
-    if config_path.exists() {
-        return Ok(());
+    // Start the server
+    println!("Listening on: http://{}", addr);
+    // The connection address is needed to find the client behind trusted proxies
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    // Write anything still buffered before exiting
+    if let Some(ingest) = ingest {
+        ingest.shutdown().await;
     }
-
-    std::fs::File::create(&config_path).unwrap();
-    std::fs::write(&config_path, config::generate_default_config()?)?;
+    database.close().await;
+    println!("Shutdown complete");
 
     Ok(())
 }
 
-/// Will check that the database file exists and create it if it doesnt.
-/// The database file path is extracted from the config file.
-fn check_database_file(database_path: PathBuf) -> Result<(), Box<dyn Error>> {
-    if database_path.exists() {
-        println!("Database exists");
-        return Ok(());
-    }
-
-    let write_res = std::fs::File::create(&database_path);
+/// Resolves on Ctrl-C or SIGTERM, after which the server stops accepting connections and waits
+/// for the requests in flight to finish
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
 
-    if let Err(e) = &write_res {
-        match e.kind() {
-            std::io::ErrorKind::NotFound => {
-                println!("Could not create the database file. Ensure the database url is correct in the config file.");
-                println!("For explanations on the config, run \"fkit --config-help\"");
-            }
-            _ => {
-                println!("Error creating database file: {:?}", e);
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
             }
+            // Without a SIGTERM handler only Ctrl-C shuts down gracefully
+            Err(_) => std::future::pending().await,
         }
     };
 
-    write_res?;
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    Ok(())
-}
-
-async fn define_columns(
-    Path(project): Path<String>,
-    State(database): State<Database>,
-    Query(query): Query<HashMap<String, String>>,
-) -> String {
-    let project = database.get_project(&project).await.unwrap().unwrap();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
 
-    "bozo".to_string()
+    println!("Shutting down, waiting for requests in flight to finish");
 }
@@ -0,0 +1,76 @@
+use axum::{extract::State, Json};
+use database::Database;
+use serde_json::{json, Value};
+
+use crate::{error::ApiError, extract::Admin};
+
+/// Writes the SQLite WAL back into the database file and truncates it
+///
+/// Reports whether another connection kept the checkpoint from finishing and how many frames
+/// were written. Other databases have no WAL, for those the checkpoint is skipped.
+pub async fn checkpoint(
+    _: Admin,
+    State(database): State<Database>,
+) -> Result<Json<Value>, ApiError> {
+    let Some(checkpoint) = database.checkpoint().await? else {
+        return Ok(Json(json!({ "status": "skipped" })));
+    };
+
+    Ok(Json(json!({
+        "status": if checkpoint.busy { "busy" } else { "ok" },
+        "log_frames": checkpoint.log_frames,
+        "checkpointed_frames": checkpoint.checkpointed_frames,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+
+    use crate::routes::tests::{body_string, send, test_app};
+
+    #[tokio::test]
+    async fn checkpoint_disabled() {
+        let app = test_app("checkpoint_disabled", "").await;
+
+        let response = send(
+            &app,
+            Request::post("/admin/checkpoint")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn checkpoint() {
+        let app = test_app("checkpoint", "[admin]\ntoken = \"secret\"").await;
+
+        let response = send(
+            &app,
+            Request::post("/admin/checkpoint")
+                .header(header::AUTHORIZATION, "Bearer wrong")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(
+            &app,
+            Request::post("/admin/checkpoint")
+                .header(header::AUTHORIZATION, "Bearer secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["status"], "ok");
+    }
+}
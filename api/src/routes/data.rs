@@ -0,0 +1,1315 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::{header, StatusCode, Uri},
+    response::{IntoResponse, Response, Result},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use database::{
+    csv::CsvOptions,
+    project::{validate_new_rows, Datapoint},
+    validation::RowError,
+    Database,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::ApiError,
+    extract::{
+        deserialize_time, ApiKey, JsonBody, PaginationParams, TimeRangeParams, ValueRangeParams,
+    },
+    ingest::DeniedFields,
+};
+
+use super::AppState;
+
+/// Header with the total number of datapoints of a paginated response
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Header with the time a datapoint was stored under
+const DATAPOINT_TIMESTAMP_HEADER: &str = "x-datapoint-timestamp";
+
+/// Adds a datapoint to a project, creating the project and any missing columns.
+///
+/// The datapoint is read from the query string, where every key is a column name. Values are
+/// validated against their columns, every invalid field is reported with a 422. Fields on the
+/// configured denylist are dropped or reject the datapoint, see [DeniedFields]. A token for a
+/// different project is rejected with a 403.
+///
+/// The time the datapoint was stored under is returned in the `X-Datapoint-Timestamp` header.
+/// When ingest batching is configured the datapoint is only queued and 202 Accepted is returned
+/// without the header, any errors while writing it are logged instead.
+pub async fn add_datapoint(
+    Path(project): Path<String>,
+    State(state): State<AppState>,
+    key: ApiKey,
+    uri: Uri,
+) -> Result<Response> {
+    key.authorize(&state.database, &project).await?;
+
+    // Check the raw length before parsing anything
+    let max_query_bytes = state.config.get_max_query_bytes();
+    if uri.query().map_or(0, str::len) > max_query_bytes {
+        return Err(ApiError::new(
+            StatusCode::URI_TOO_LONG,
+            format!("Query string is longer than {} bytes", max_query_bytes),
+        )
+        .into());
+    }
+
+    let Query(mut data) = Query::<HashMap<String, String>>::try_from_uri(&uri)
+        .map_err(|e| ApiError::bad_request(e.body_text()))?;
+    state
+        .denied_fields
+        .apply(&mut data)
+        .map_err(|e| ApiError::from(database::Error::Validation(e)))?;
+
+    if let Some(ingest) = &state.ingest {
+        if !ingest.push(project, data) {
+            return Err(
+                ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into(),
+            );
+        }
+        return Ok((StatusCode::ACCEPTED, "Accepted").into_response());
+    }
+
+    let project = state
+        .database
+        .get_or_create_project(&project)
+        .await
+        .map_err(ApiError::from)?;
+
+    let timestamp = project
+        .add_datapoint_typed(data)
+        .await
+        .map_err(ApiError::from)?;
+
+    Ok((
+        [(DATAPOINT_TIMESTAMP_HEADER, timestamp.to_rfc3339())],
+        "Success",
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchParams {
+    /// Only check the datapoints against the schema, without inserting anything
+    #[serde(default)]
+    validate_only: bool,
+    /// Create the project and columns in the same transaction as the datapoints
+    #[serde(default)]
+    atomic: bool,
+}
+
+/// Result of a batch of datapoints
+#[derive(Debug, Serialize)]
+pub struct BatchResponse {
+    /// Number of datapoints in the batch
+    rows: usize,
+    /// Whether the datapoints were inserted, false when only validating
+    inserted: bool,
+}
+
+/// Adds a json array of datapoints to a project at once, creating the project and any missing
+/// columns.
+///
+/// Every datapoint is an object with the values keyed by column name. Strings are used as they
+/// are, other values as their json text and nulls are left out. If any datapoint is invalid
+/// nothing is inserted and every invalid row is reported with a 422.
+///
+/// With `?validate_only=true` the datapoints are only checked, not inserted. With `?atomic=true`
+/// the project and missing columns are only created if the datapoints are inserted as well.
+///
+/// Denied fields are handled per datapoint like in [add_datapoint], every rejected row is
+/// reported with a 422.
+pub async fn add_datapoints(
+    Path(project): Path<String>,
+    State(state): State<AppState>,
+    Query(params): Query<BatchParams>,
+    JsonBody(batch): JsonBody<Vec<Map<String, Value>>>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    let database = state.database;
+    let rows = deny_fields(&state.denied_fields, batch)?;
+
+    if params.validate_only {
+        match database.get_project(&project).await? {
+            Some(project) => project.validate_rows(&rows).await?,
+            // The columns of a new project are inferred from the rows themselves
+            None => validate_new_rows(&rows)?,
+        }
+
+        return Ok(Json(BatchResponse {
+            rows: rows.len(),
+            inserted: false,
+        }));
+    }
+
+    let rows = match params.atomic {
+        true => database.add_datapoints_atomic(&project, &rows).await?,
+        false => {
+            let project = database.get_or_create_project(&project).await?;
+            project.add_datapoints(rows).await?
+        }
+    };
+
+    Ok(Json(BatchResponse {
+        rows,
+        inserted: true,
+    }))
+}
+
+/// Replaces all data of a project with a json array of datapoints, creating the project and
+/// any missing columns.
+///
+/// The datapoints are read like in [add_datapoints]. If any of them is invalid the existing
+/// data is kept and every invalid row is reported with a 422.
+pub async fn replace_data(
+    Path(project): Path<String>,
+    State(state): State<AppState>,
+    JsonBody(batch): JsonBody<Vec<Map<String, Value>>>,
+) -> Result<Json<BatchResponse>, ApiError> {
+    let rows = deny_fields(&state.denied_fields, batch)?;
+
+    let project = state.database.get_or_create_project(&project).await?;
+    let rows = project.replace_all_data(rows).await?;
+
+    Ok(Json(BatchResponse {
+        rows,
+        inserted: true,
+    }))
+}
+
+/// Changes the values of a single datapoint, found by the `__id__` it is listed with.
+///
+/// The new values are a json object like a datapoint of [add_datapoints], columns that are left
+/// out keep their value. Missing columns are created and values are validated like when adding
+/// a datapoint, denied fields included.
+pub async fn update_datapoint(
+    Path((project, id)): Path<(String, i64)>,
+    State(state): State<AppState>,
+    JsonBody(data): JsonBody<Map<String, Value>>,
+) -> Result<StatusCode, ApiError> {
+    let mut data = json_to_datapoint(data);
+    state
+        .denied_fields
+        .apply(&mut data)
+        .map_err(database::Error::Validation)?;
+
+    let project = state
+        .database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    project.update_datapoint(id, data).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes a single datapoint, found by the `__id__` it is listed with
+pub async fn delete_datapoint(
+    Path((project, id)): Path<(String, i64)>,
+    State(database): State<Database>,
+) -> Result<StatusCode, ApiError> {
+    let project = database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    match project.delete_datapoint(id).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(ApiError::not_found("Datapoint not found")),
+    }
+}
+
+/// Converts a batch of json objects into datapoints without their denied fields
+///
+/// Fails with every row holding a denied field if those are rejected.
+fn deny_fields(
+    denied_fields: &DeniedFields,
+    batch: Vec<Map<String, Value>>,
+) -> Result<Vec<HashMap<String, String>>, database::Error> {
+    let mut rows: Vec<HashMap<String, String>> = batch.into_iter().map(json_to_datapoint).collect();
+
+    let errors: Vec<RowError> = rows
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(row, datapoint)| {
+            let errors = denied_fields.apply(datapoint).err()?;
+            Some(RowError { row, errors })
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(rows),
+        false => Err(database::Error::InvalidRows(errors)),
+    }
+}
+
+/// Converts a json object into the values of a datapoint
+fn json_to_datapoint(object: Map<String, Value>) -> HashMap<String, String> {
+    object
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            Value::Null => None,
+            Value::String(value) => Some((key, value)),
+            value => Some((key, value.to_string())),
+        })
+        .collect()
+}
+
+/// Returns a page of datapoints of a project as a json array, oldest first
+///
+/// Every datapoint is an object with the values keyed by column name, along with the time it was
+/// added as an ISO-8601 string under `__timestamp__` and its id under `__id__`. The total number
+/// of datapoints is returned in the `X-Total-Count` header.
+///
+/// `?from=` and `?to=` limit the datapoints to a time range, see [TimeRangeParams].
+/// `?<column>_min=` and `?<column>_max=` limit them to a range of values of a numeric column, see
+/// [ValueRangeParams]. The total count is then the number of datapoints in the ranges.
+pub async fn get_data(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+    pagination: PaginationParams,
+    range: Result<Query<TimeRangeParams>, QueryRejection>,
+    values: ValueRangeParams,
+) -> Result<impl IntoResponse, ApiError> {
+    let Query(range) = range.map_err(|e| ApiError::bad_request(e.body_text()))?;
+
+    let project = database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let (total, datapoints) = match (range.bounds(), values.range) {
+        (time, Some(values)) => (
+            project
+                .datapoint_count_range(&values.column, values.min.clone(), values.max.clone(), time)
+                .await?,
+            project
+                .get_data_range(
+                    &values.column,
+                    values.min,
+                    values.max,
+                    time,
+                    pagination.limit,
+                    pagination.offset,
+                )
+                .await?,
+        ),
+        (Some((from, to)), None) => (
+            project.datapoint_count_between(from, to).await?,
+            project
+                .get_data_between_paged(from, to, pagination.limit, pagination.offset)
+                .await?,
+        ),
+        (None, None) => (
+            project.datapoint_count().await?,
+            project
+                .get_data_paged(pagination.limit, pagination.offset)
+                .await?,
+        ),
+    };
+
+    Ok((
+        [(TOTAL_COUNT_HEADER, total.to_string())],
+        Json(
+            datapoints
+                .into_iter()
+                .map(datapoint_to_json)
+                .collect::<Vec<Value>>(),
+        ),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesParams {
+    /// Only datapoints added or changed at or after this time, all datapoints if not given
+    #[serde(default, deserialize_with = "deserialize_time")]
+    since: Option<DateTime<Utc>>,
+}
+
+/// Returns a page of the datapoints added or changed at or after `?since=`, least recently
+/// changed first
+///
+/// `since` accepts an ISO-8601 time or unix seconds. Datapoints changed in the same second as
+/// `since` are returned again, clients should deduplicate them by `__id__`. Datapoints are in the
+/// same form as from [get_data], and the total number of changed datapoints is returned in the
+/// `X-Total-Count` header.
+pub async fn get_changes(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+    pagination: PaginationParams,
+    params: Result<Query<ChangesParams>, QueryRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+
+    let project = database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let since = params.since.unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let total = project.change_count_since(since).await?;
+    let changes = project
+        .changes_since_paged(since, pagination.limit, pagination.offset)
+        .await?;
+
+    Ok((
+        [(TOTAL_COUNT_HEADER, total.to_string())],
+        Json(
+            changes
+                .into_iter()
+                .map(datapoint_to_json)
+                .collect::<Vec<Value>>(),
+        ),
+    ))
+}
+
+/// Converts a datapoint into a json object
+pub(crate) fn datapoint_to_json(datapoint: Datapoint) -> Value {
+    let mut object = Map::with_capacity(datapoint.values.len() + 2);
+    object.insert("__id__".to_string(), Value::from(datapoint.id));
+    object.insert(
+        "__timestamp__".to_string(),
+        Value::String(datapoint.timestamp.to_rfc3339()),
+    );
+
+    for (name, value) in datapoint.values {
+        object.insert(name, value.map_or(Value::Null, Value::String));
+    }
+
+    Value::Object(object)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HistogramParams {
+    /// Column to count the values of
+    column: String,
+}
+
+/// Number of datapoints with a single value of a column
+#[derive(Debug, Serialize)]
+pub struct HistogramEntry {
+    value: String,
+    count: i64,
+}
+
+/// Counts the datapoints per distinct value of `?column=`, most common value first
+pub async fn value_histogram(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+    params: Result<Query<HistogramParams>, QueryRejection>,
+) -> Result<Json<Vec<HistogramEntry>>, ApiError> {
+    let Query(params) = params.map_err(|e| ApiError::bad_request(e.body_text()))?;
+
+    let project = database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let histogram = project.value_histogram(&params.column).await?;
+
+    Ok(Json(
+        histogram
+            .into_iter()
+            .map(|(value, count)| HistogramEntry { value, count })
+            .collect(),
+    ))
+}
+
+/// Exports all datapoints of a project as a CSV file
+///
+/// Columns are in the order they were created, an empty project still gets a header row.
+pub async fn export_csv(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+) -> Result<impl IntoResponse, ApiError> {
+    let project = database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let csv = project.to_csv(&CsvOptions::default()).await?;
+    let disposition = format!(
+        "attachment; filename=\"{}.csv\"",
+        attachment_name(&project.name)
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        csv,
+    ))
+}
+
+/// Project name made safe for the quoted filename of a Content-Disposition header
+fn attachment_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Exports all datapoints of a project as a Parquet file
+pub async fn export_parquet(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+) -> Result<impl IntoResponse, ApiError> {
+    let project = database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let mut buffer = Vec::new();
+    project.to_parquet(&mut buffer).await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/vnd.apache.parquet")],
+        buffer,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{header, Request, StatusCode},
+    };
+
+    use crate::routes::{
+        router,
+        tests::{body_string, send, test_app, test_state},
+    };
+
+    #[tokio::test]
+    async fn add_datapoint() {
+        let app = test_app("add_datapoint", "").await;
+
+        let response = send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_concurrent_create() {
+        let app = test_app("add_datapoint_concurrent_create", "").await;
+
+        // Every request finds the project missing and races to create it, the columns differ so
+        // that only the project is contended
+        let requests: Vec<_> = (0..8)
+            .map(|i| {
+                let app = app.clone();
+                tokio::spawn(async move {
+                    let uri = format!("/foo?count{}={}", i, i);
+                    send(&app, Request::post(uri).body(Body::empty()).unwrap()).await
+                })
+            })
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.unwrap().status(), StatusCode::OK);
+        }
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.headers()["x-total-count"], "8");
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_errors() {
+        let app = test_app("add_datapoint_errors", "").await;
+        let post = |uri: &str| Request::post(uri).body(Body::empty()).unwrap();
+        let error = |body: String| {
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            body["error"].as_str().unwrap().to_string()
+        };
+
+        // No project can have the name
+        let response = send(&app, post("/@@@?bar=baz")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(error(body_string(response).await).contains("@@@"));
+
+        // The table is gone, so the insert fails in the database
+        send(&app, post("/foo?bar=baz")).await;
+        let pool =
+            sqlx::AnyPool::connect("sqlite:file:add_datapoint_errors?mode=memory&cache=shared")
+                .await
+                .unwrap();
+        sqlx::query("DROP TABLE foo").execute(&pool).await.unwrap();
+
+        let response = send(&app, post("/foo?bar=baz")).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!error(body_string(response).await).is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_timestamp_header() {
+        let app = test_app("add_datapoint_timestamp_header", "").await;
+
+        let response = send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let header = response.headers()["x-datapoint-timestamp"]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body[0]["__timestamp__"], header);
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_frozen_schema() {
+        let app = test_app("add_datapoint_frozen_schema", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let response = send(
+            &app,
+            Request::patch("/foo")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"schema_frozen": true}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(
+            &app,
+            Request::post("/foo?qux=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = send(
+            &app,
+            Request::post("/foo?bar=qux").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_reports_all_violations() {
+        let app = test_app("add_datapoint_reports_all_violations", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?count=1&ratio=0.5")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        let response = send(
+            &app,
+            Request::post("/foo?count=many&ratio=half")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let errors = body["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0]["field"], "count");
+        assert_eq!(errors[0]["rule"], "type");
+        assert_eq!(errors[1]["field"], "ratio");
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_denied_field_dropped() {
+        let config = "[ingest]\ndenied_fields = [\"password\"]\ndenied_fields_action = \"drop\"";
+        let app = test_app("add_datapoint_denied_field_dropped", config).await;
+
+        let response = send(
+            &app,
+            Request::post("/foo?user=bob&Password=hunter2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(
+            &app,
+            Request::post("/foo/batch")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"[{"user": "alice", "PASSWORD": "secret"}]"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let rows = body.as_array().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["user"], "bob");
+        assert_eq!(rows[1]["user"], "alice");
+
+        // No column was created for the denied field
+        let response = send(
+            &app,
+            Request::get("/foo/columns").body(Body::empty()).unwrap(),
+        )
+        .await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let columns = body.as_array().unwrap();
+        assert_eq!(columns.len(), 1);
+        assert_eq!(columns[0]["name"], "user");
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_denied_field_rejected() {
+        let config = "[ingest]\ndenied_fields = [\"password\"]\ndenied_fields_action = \"reject\"";
+        let app = test_app("add_datapoint_denied_field_rejected", config).await;
+
+        let response = send(
+            &app,
+            Request::post("/foo?user=bob&Password=hunter2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let errors = body["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["field"], "Password");
+        assert_eq!(errors[0]["rule"], "denied_field");
+
+        let response = send(
+            &app,
+            Request::post("/foo/batch")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"[{"user": "alice"}, {"user": "eve", "password": "secret"}]"#,
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["errors"][0]["row"], 1);
+
+        // Nothing was stored, not even the project
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_batched() {
+        let state = test_state(
+            "add_datapoint_batched",
+            "[ingest.batch]\ninterval_ms = 3600000",
+        )
+        .await;
+        let ingest = state.ingest.clone().unwrap();
+        let app = router(state);
+
+        for i in 0..20 {
+            let response = send(
+                &app,
+                Request::post(format!("/foo?count={}", i))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::ACCEPTED);
+        }
+
+        ingest.flush().await;
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 20);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints() {
+        let app = test_app("add_datapoints", "").await;
+
+        let response = send(
+            &app,
+            Request::post("/foo/batch")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"[{"count": 1, "name": "a"}, {"count": 2}, {"name": null}]"#,
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["rows"], 3);
+        assert_eq!(body["inserted"], true);
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.headers()["x-total-count"], "3");
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_invalid_row() {
+        let app = test_app("add_datapoints_invalid_row", "").await;
+
+        let batch = |uri: &str| {
+            Request::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(
+                    r#"[{"count": 1}, {"count": "many"}, {"count": 3}]"#,
+                ))
+                .unwrap()
+        };
+
+        // Without a project the first row decides the type of the column
+        let response = send(&app, batch("/foo/batch?validate_only=true")).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        send(
+            &app,
+            Request::post("/foo?count=0").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let response = send(&app, batch("/foo/batch?validate_only=true")).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let errors = body["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["row"], 1);
+        assert_eq!(errors[0]["field"], "count");
+
+        let response = send(&app, batch("/foo/batch")).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Neither validating nor the failed batch inserted anything
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.headers()["x-total-count"], "1");
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_atomic() {
+        let app = test_app("add_datapoints_atomic", "").await;
+
+        let batch = |uri: &str, body: &'static str| {
+            Request::post(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let last_row_invalid = r#"[{"count": 1}, {"count": 2}, {"count": "many"}]"#;
+
+        // Without atomic the project is created before the batch fails
+        let response = send(&app, batch("/foo/batch", last_row_invalid)).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "0");
+
+        let response = send(&app, batch("/bar/batch?atomic=true", last_row_invalid)).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = send(&app, Request::get("/bar").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(
+            &app,
+            batch("/bar/batch?atomic=true", r#"[{"count": 1}, {"count": 2}]"#),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(&app, Request::get("/bar").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.headers()["x-total-count"], "2");
+    }
+
+    #[tokio::test]
+    async fn replace_data() {
+        let app = test_app("replace_data", "").await;
+
+        let request = |method: &str, uri: &str, body: &'static str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = send(
+            &app,
+            request(
+                "POST",
+                "/foo/batch",
+                r#"[{"count": 1}, {"count": 2}, {"count": 3}]"#,
+            ),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(
+            &app,
+            request("PUT", "/foo/data", r#"[{"count": 4}, {"count": 5}]"#),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["rows"], 2);
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.headers()["x-total-count"], "2");
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_body_too_large() {
+        let app = test_app(
+            "add_datapoints_body_too_large",
+            "[server]\nmax_body_bytes = 256",
+        )
+        .await;
+
+        let batch = |rows: usize| {
+            let body = format!("[{}]", vec![r#"{"count": 1}"#; rows].join(","));
+            Request::post("/foo/batch")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = send(&app, batch(100)).await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = send(&app, batch(10)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_malformed_json() {
+        let app = test_app("add_datapoints_malformed_json", "").await;
+
+        let batch = |body: &'static str| {
+            Request::post("/foo/batch")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = send(&app, batch(r#"[{"count": 1"#)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("line 1"));
+
+        // Valid json of the wrong shape
+        let response = send(&app, batch(r#"{"count": 1}"#)).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn get_data() {
+        let app = test_app("get_data", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let datapoints = body.as_array().unwrap();
+        assert_eq!(datapoints.len(), 1);
+        assert_eq!(datapoints[0]["bar"], "baz");
+
+        let timestamp = datapoints[0]["__timestamp__"].as_str().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+    }
+
+    #[tokio::test]
+    async fn get_data_paged() {
+        let app = test_app("get_data_paged", "").await;
+
+        for i in 0..5 {
+            send(
+                &app,
+                Request::post(format!("/foo?count={}", i))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        }
+
+        let response = send(
+            &app,
+            Request::get("/foo?limit=2&offset=4")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "5");
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 1);
+
+        let response = send(
+            &app,
+            Request::get("/foo?limit=0").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn update_datapoint() {
+        let app = test_app("update_datapoint", "").await;
+
+        for uri in ["/foo?count=1", "/foo?count=2"] {
+            send(&app, Request::post(uri).body(Body::empty()).unwrap()).await;
+        }
+
+        let get = || Request::get("/foo").body(Body::empty()).unwrap();
+        let body: serde_json::Value =
+            serde_json::from_str(&body_string(send(&app, get()).await).await).unwrap();
+        let id = body[1]["__id__"].as_i64().unwrap();
+
+        let update = |id: i64, body: &'static str| {
+            Request::patch(format!("/foo/{}", id))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = send(&app, update(id, r#"{"count": 3, "note": "fixed"}"#)).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let body: serde_json::Value =
+            serde_json::from_str(&body_string(send(&app, get()).await).await).unwrap();
+        assert_eq!(body[0]["count"], "1");
+        assert_eq!(body[1]["count"], "3");
+        assert_eq!(body[1]["note"], "fixed");
+
+        let response = send(&app, update(id, r#"{"count": "many"}"#)).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = send(&app, update(id + 100, r#"{"count": 4}"#)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_datapoint() {
+        let app = test_app("delete_datapoint", "").await;
+
+        for uri in ["/foo?count=1", "/foo?count=2"] {
+            send(&app, Request::post(uri).body(Body::empty()).unwrap()).await;
+        }
+
+        let get = || Request::get("/foo").body(Body::empty()).unwrap();
+        let body: serde_json::Value =
+            serde_json::from_str(&body_string(send(&app, get()).await).await).unwrap();
+        let id = body[0]["__id__"].as_i64().unwrap();
+
+        let delete = || {
+            Request::delete(format!("/foo/{}", id))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = send(&app, delete()).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = send(&app, delete()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(&app, get()).await;
+        assert_eq!(response.headers()["x-total-count"], "1");
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body[0]["count"], "2");
+    }
+
+    #[tokio::test]
+    async fn value_histogram() {
+        let app = test_app("value_histogram", "").await;
+
+        for color in ["red", "blue", "red"] {
+            send(
+                &app,
+                Request::post(format!("/foo?color={}", color))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        }
+
+        let histogram = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let response = send(&app, histogram("/foo/histogram?column=color")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([
+                { "value": "red", "count": 2 },
+                { "value": "blue", "count": 1 },
+            ])
+        );
+
+        let response = send(&app, histogram("/foo/histogram?column=size")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(&app, histogram("/foo/histogram")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_changes() {
+        let app = test_app("get_changes", "").await;
+
+        for uri in ["/foo?count=1", "/foo?count=2"] {
+            send(&app, Request::post(uri).body(Body::empty()).unwrap()).await;
+        }
+
+        let changes = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let response = send(&app, changes("/foo/changes")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "2");
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let newest = body[1]["__timestamp__"].as_str().unwrap();
+        let newest = chrono::DateTime::parse_from_rfc3339(newest).unwrap();
+
+        // Datapoints changed in the same second as `since` are returned again
+        let uri = format!("/foo/changes?since={}", newest.timestamp());
+        let response = send(&app, changes(&uri)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body.as_array().unwrap().last().unwrap()["count"], "2");
+
+        let uri = format!("/foo/changes?since={}", newest.timestamp() + 1);
+        let response = send(&app, changes(&uri)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "[]");
+
+        let response = send(&app, changes("/foo/changes?limit=1&offset=1")).await;
+        assert_eq!(response.headers()["x-total-count"], "2");
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["count"], "2");
+
+        let response = send(&app, changes("/foo/changes?since=yesterday")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(&app, changes("/bar/changes")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn get_data_time_range() {
+        let app = test_app("get_data_time_range", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let response = send(&app, get("/foo?from=2000-01-01T00:00:00Z")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "1");
+
+        let response = send(&app, get("/foo?to=946684800")).await;
+        assert_eq!(response.headers()["x-total-count"], "0");
+
+        // Inverted ranges are empty
+        let response = send(&app, get("/foo?from=946684800&to=0")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body.as_array().unwrap().is_empty());
+
+        let response = send(&app, get("/foo?from=yesterday")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_data_value_range() {
+        let app = test_app("get_data_value_range", "").await;
+
+        for size in 1..=4 {
+            let uri = format!("/foo?size={}&name=n{}", size, size);
+            send(&app, Request::post(uri).body(Body::empty()).unwrap()).await;
+        }
+
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+        let sizes = |body: String| {
+            let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+            body.as_array()
+                .unwrap()
+                .iter()
+                .map(|d| d["size"].as_str().unwrap().to_string())
+                .collect::<Vec<String>>()
+        };
+
+        let response = send(&app, get("/foo?size_min=2&size_max=3")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "2");
+        assert_eq!(sizes(body_string(response).await), vec!["2", "3"]);
+
+        let response = send(&app, get("/foo?size_min=3&limit=1")).await;
+        assert_eq!(response.headers()["x-total-count"], "2");
+        assert_eq!(sizes(body_string(response).await), vec!["3"]);
+
+        let response = send(&app, get("/foo?name_min=a")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(&app, get("/foo?size_min=small")).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = send(&app, get("/foo?size_min=1&name_max=b")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_data_missing_project() {
+        let app = test_app("get_data_missing_project", "").await;
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn export_csv() {
+        let app = test_app("export_csv", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=a%2Cb").body(Body::empty()).unwrap(),
+        )
+        .await;
+        send(
+            &app,
+            Request::post("/foo?baz=%22hi%22")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        let response = send(&app, Request::get("/foo/csv").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"foo.csv\""
+        );
+
+        let body = body_string(response).await;
+        assert_eq!(body, "bar,baz\r\n\"a,b\",\r\n,\"\"\"hi\"\"\"\r\n");
+    }
+
+    #[tokio::test]
+    async fn export_csv_empty_project() {
+        let app = test_app("export_csv_empty_project", "").await;
+
+        send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+
+        let response = send(&app, Request::get("/foo/csv").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "\r\n");
+    }
+
+    #[tokio::test]
+    async fn export_parquet() {
+        let app = test_app("export_parquet", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let response = send(
+            &app,
+            Request::get("/foo/parquet").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/vnd.apache.parquet"
+        );
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.starts_with(b"PAR1"));
+
+        let response = send(
+            &app,
+            Request::get("/missing/parquet")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn query_too_long() {
+        let app = test_app("query_too_long", "[limits]\nmax_query_bytes = 16").await;
+
+        let query = "bar=baz&".repeat(4);
+        let response = send(
+            &app,
+            Request::post(format!("/foo?{}", query))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        assert_eq!(response.status(), StatusCode::URI_TOO_LONG);
+    }
+}
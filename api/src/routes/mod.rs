@@ -0,0 +1,606 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{DefaultBodyLimit, FromRef, FromRequestParts, MatchedPath, Path, Request, State},
+    handler::Handler,
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, patch, post, put},
+    Json, Router,
+};
+use config_rs::ConfigError;
+use database::{utils::sql_encode, Database};
+use serde_json::{json, Value};
+
+use crate::{
+    config::AppConfig,
+    error::ApiError,
+    extract::{ApiKey, ClientIp},
+    ingest::{DeniedFields, IngestBuffer, RateLimiter},
+};
+
+mod admin;
+pub(crate) mod data;
+mod projects;
+
+/// State shared by all request handlers
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub database: Database,
+    pub config: Arc<AppConfig>,
+    /// Queue for incoming datapoints, None if they are written right away
+    pub ingest: Option<IngestBuffer>,
+    /// Fields that are dropped from or reject incoming datapoints
+    pub denied_fields: Arc<DeniedFields>,
+    /// Limit on datapoints added per client, None if there is none
+    pub rate_limit: Option<Arc<RateLimiter>>,
+}
+
+impl AppState {
+    /// Creates the state, starting the ingest buffer if batching is configured
+    ///
+    /// Fails if the denied fields are configured with an unknown action.
+    pub fn new(database: Database, config: AppConfig) -> Result<AppState, ConfigError> {
+        let denied_fields = config.get_denied_fields()?;
+        let ingest = config
+            .get_batch_settings()
+            .map(|settings| IngestBuffer::spawn(database.clone(), settings));
+        let rate_limit = config
+            .get_rate_limit()
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+
+        Ok(AppState {
+            database,
+            config: Arc::new(config),
+            ingest,
+            denied_fields: Arc::new(denied_fields),
+            rate_limit,
+        })
+    }
+}
+
+impl FromRef<AppState> for Database {
+    fn from_ref(state: &AppState) -> Self {
+        state.database.clone()
+    }
+}
+
+/// All endpoints of the api, listed by the root endpoint
+const ENDPOINTS: &[&str] = &[
+    "GET /",
+    "GET /ready",
+    "GET /health",
+    "POST /admin/checkpoint",
+    "GET /projects",
+    "GET /columns/:column/projects",
+    "POST /new/:project",
+    "POST /:project",
+    "GET /:project",
+    "PATCH /:project",
+    "DELETE /:project",
+    "PATCH /:project/:id",
+    "DELETE /:project/:id",
+    "POST /:project/batch",
+    "PUT /:project/data",
+    "GET /:project/columns",
+    "POST /:project/columns",
+    "PATCH /:project/columns/:column",
+    "POST /:project/duplicate",
+    "GET /:project/changes",
+    "GET /:project/histogram",
+    "GET /:project/csv",
+    "GET /:project/parquet",
+];
+
+/// Creates the router with all endpoints of the api
+///
+/// Every endpoint except the index, the readiness and health checks and the admin endpoints,
+/// which have a token of their own, requires an api key once keys are configured.
+///
+/// Request bodies larger than the configured `max_body_bytes` are rejected with 413 Payload
+/// Too Large, which keeps batches of datapoints from using up the memory of the server.
+pub fn router(state: AppState) -> Router {
+    let max_body_bytes = state.config.get_max_body_bytes();
+
+    Router::new()
+        .route("/", get(index))
+        .route("/ready", get(ready))
+        .route("/health", get(health))
+        .route("/admin/checkpoint", post(admin::checkpoint))
+        .merge(api_router(state.clone()))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .with_state(state)
+}
+
+/// Endpoints for projects and their data, behind the api key check
+fn api_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/projects", get(projects::list_projects))
+        .route(
+            "/columns/:column/projects",
+            get(projects::projects_with_column),
+        )
+        .route("/new/:project", post(projects::create_project))
+        .route(
+            "/:project",
+            post(
+                data::add_datapoint
+                    .layer(middleware::from_fn_with_state(state.clone(), limit_rate)),
+            )
+            .get(data::get_data)
+            .patch(projects::update_project)
+            .delete(projects::delete_project),
+        )
+        .route(
+            "/:project/:id",
+            patch(data::update_datapoint).delete(data::delete_datapoint),
+        )
+        .route("/:project/batch", post(data::add_datapoints))
+        .route("/:project/data", put(data::replace_data))
+        .route(
+            "/:project/columns",
+            get(projects::list_columns).post(projects::define_columns),
+        )
+        .route("/:project/columns/:column", patch(projects::rename_column))
+        .route("/:project/duplicate", post(projects::duplicate_project))
+        .route("/:project/changes", get(data::get_changes))
+        .route("/:project/histogram", get(data::value_histogram))
+        .route("/:project/csv", get(data::export_csv))
+        .route("/:project/parquet", get(data::export_parquet))
+        .route_layer(middleware::from_fn(check_project_name))
+        .route_layer(middleware::from_fn_with_state(state, require_api_key))
+}
+
+/// Project names taken by the static first segments of [ENDPOINTS], a project with one of them
+/// could not be reached
+const RESERVED_PROJECT_NAMES: &[&str] = &["ready", "health", "admin", "projects", "columns", "new"];
+
+/// Rejects project names that can not be stored as they are with 400 Bad Request
+///
+/// Names have to be non-empty and only contain letters, digits and underscores. Names with
+/// other characters would be stored under an encoded name with a hash appended, so they are
+/// refused instead of being mangled. Names of static routes like `health` are refused as well.
+pub(crate) fn validate_project_name(name: &str) -> Result<(), ApiError> {
+    if name.is_empty() {
+        return Err(ApiError::bad_request("Project name cannot be empty"));
+    }
+    if RESERVED_PROJECT_NAMES.contains(&name) {
+        return Err(ApiError::bad_request(format!(
+            "Project name '{}' is reserved",
+            name
+        )));
+    }
+    if name.contains('/') {
+        return Err(ApiError::bad_request("Project name cannot contain a '/'"));
+    }
+    if sql_encode(name).is_err() {
+        return Err(ApiError::bad_request(format!(
+            "Project name '{}' can only contain letters, digits and underscores",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks the `:project` of a route with [validate_project_name] before its handler runs
+async fn check_project_name(
+    params: Option<Path<HashMap<String, String>>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if let Some(project) = params
+        .as_ref()
+        .and_then(|Path(params)| params.get("project"))
+    {
+        validate_project_name(project)?;
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Rejects clients that added too many datapoints with 429 Too Many Requests, see
+/// [RateLimiter]
+///
+/// The `Retry-After` header holds the seconds until the client may try again.
+async fn limit_rate(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let Some(limiter) = &state.rate_limit else {
+        return Ok(next.run(request).await);
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let ClientIp(client) = ClientIp::from_request_parts(&mut parts, &state)
+        .await
+        .map_err(IntoResponse::into_response)?;
+
+    if let Err(wait) = limiter.check(client) {
+        let seconds = wait.as_secs_f64().ceil().max(1.0) as u64;
+        return Err((
+            [(header::RETRY_AFTER, seconds.to_string())],
+            ApiError::new(StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+        )
+            .into_response());
+    }
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+/// Endpoints that accept a project token, the handlers check it against their project
+const PROJECT_TOKEN_ENDPOINTS: &[(Method, &str)] =
+    &[(Method::POST, "/new/:project"), (Method::POST, "/:project")];
+
+/// Rejects requests without a valid api key, see [ApiKey]
+///
+/// Project tokens are only let through to the endpoints that write to their project.
+async fn require_api_key(
+    key: ApiKey,
+    path: MatchedPath,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if let ApiKey::Project(_) = key {
+        let allowed = PROJECT_TOKEN_ENDPOINTS
+            .iter()
+            .any(|(method, endpoint)| request.method() == method && path.as_str() == *endpoint);
+        if !allowed {
+            return Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                "Missing or invalid api key",
+            ));
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Describes the service, useful for checking that the server is up
+async fn index() -> Json<Value> {
+    Json(json!({
+        "name": "fkit",
+        "version": env!("CARGO_PKG_VERSION"),
+        "endpoints": ENDPOINTS,
+    }))
+}
+
+/// Whether the server can handle requests, 503 until the database is migrated and reachable
+async fn ready(State(database): State<Database>) -> Result<Json<Value>, ApiError> {
+    match database.ready().await {
+        Ok(true) => Ok(Json(json!({ "status": "ready" }))),
+        Ok(false) => Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Database migrations have not been applied",
+        )),
+        Err(e) => Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Database is not reachable: {}", e),
+        )),
+    }
+}
+
+/// Whether the database can be reached, 503 if it can not
+///
+/// Meant for load balancers and orchestrators restarting unhealthy instances, so unlike
+/// [ready] it does not care about migrations.
+async fn health(State(database): State<Database>) -> Result<Json<Value>, ApiError> {
+    match database.ping().await {
+        Ok(()) => Ok(Json(json!({ "status": "ok" }))),
+        Err(e) => Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Database is not reachable: {}", e),
+        )),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{
+        body::{to_bytes, Body},
+        extract::ConnectInfo,
+        http::{header, Request, StatusCode},
+        response::Response,
+        Router,
+    };
+    use database::Database;
+    use tower::ServiceExt;
+
+    use super::{router, AppState};
+    use crate::config::AppConfig;
+
+    #[tokio::test]
+    async fn index() {
+        let app = test_app("index", "").await;
+
+        let response = send(&app, Request::get("/").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["name"], "fkit");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(!body["endpoints"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ready() {
+        let app = test_app("ready", "").await;
+
+        let response = send(&app, Request::get("/ready").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Roll back the bookkeeping of the last migration, as if it were still running
+        let pool = sqlx::AnyPool::connect("sqlite:file:ready?mode=memory&cache=shared")
+            .await
+            .unwrap();
+        sqlx::query(
+            "DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let response = send(&app, Request::get("/ready").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn health() {
+        let app = test_app("health", "").await;
+
+        let response = send(&app, Request::get("/health").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, r#"{"status":"ok"}"#);
+    }
+
+    #[test]
+    fn validate_project_name() {
+        assert!(super::validate_project_name("foo_bar2").is_ok());
+
+        for name in ["", "a/b", "a b", "health", "ready", "projects", "new"] {
+            let err = super::validate_project_name(name).unwrap_err();
+            assert_eq!(err.status(), StatusCode::BAD_REQUEST, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn reserved_project_names() {
+        for endpoint in super::ENDPOINTS {
+            let (_, path) = endpoint.split_once(' ').unwrap();
+            let segment = path.trim_start_matches('/').split('/').next().unwrap();
+            if !segment.is_empty() && !segment.starts_with(':') {
+                assert!(
+                    super::RESERVED_PROJECT_NAMES.contains(&segment),
+                    "{}",
+                    endpoint
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_project_names() {
+        let app = test_app("invalid_project_names", "").await;
+
+        for request in [
+            Request::post("/new/a%20b"),
+            Request::post("/new/health"),
+            Request::post("/new/ready"),
+            Request::post("/new/new"),
+            Request::post("/new/admin"),
+            Request::post("/new/columns"),
+            Request::post("/new?bar=baz"),
+            Request::post("/admin?bar=baz"),
+            Request::post("/columns?bar=baz"),
+            Request::post("/a%20b?bar=baz"),
+            Request::get("/a%2Fb"),
+            Request::delete("/a%20b"),
+            Request::get("/a%20b/csv"),
+            Request::post("/foo/duplicate?name=a%20b"),
+        ] {
+            let response = send(&app, request.body(Body::empty()).unwrap()).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+            let body: serde_json::Value =
+                serde_json::from_str(&body_string(response).await).unwrap();
+            assert!(body["error"].is_string());
+        }
+
+        // Nothing was created along the way
+        let response = send(&app, Request::get("/projects").body(Body::empty()).unwrap()).await;
+        assert_eq!(body_string(response).await, "[]");
+    }
+
+    #[tokio::test]
+    async fn static_segments_are_not_projects() {
+        let app = test_app("static_segments_are_not_projects", "").await;
+
+        // Creates a project named batch, as no project can be named new
+        let response = send(
+            &app,
+            Request::post("/new/batch").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        for request in [
+            Request::post("/new/batch"),
+            Request::post("/admin/batch"),
+            Request::put("/columns/data"),
+            Request::get("/projects/csv"),
+        ] {
+            let response = send(&app, request.body(Body::empty()).unwrap()).await;
+            assert!(response.status().is_client_error());
+        }
+
+        let response = send(&app, Request::get("/projects").body(Body::empty()).unwrap()).await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let names: Vec<_> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| &p["name"])
+            .collect();
+        assert_eq!(names, ["batch"]);
+    }
+
+    #[tokio::test]
+    async fn api_keys_disabled() {
+        let app = test_app("api_keys_disabled", "").await;
+
+        let response = send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(&app, Request::get("/projects").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn api_keys_required() {
+        let app = test_app(
+            "api_keys_required",
+            "[auth]\nkeys = [\"first\", \"second\"]",
+        )
+        .await;
+
+        let projects = |name: &str, value: &str| {
+            Request::get("/projects")
+                .header(name, value)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = send(&app, Request::get("/projects").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(&app, projects("authorization", "Bearer third")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(&app, projects("authorization", "first")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(&app, projects("authorization", "Bearer first")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(&app, projects("x-api-key", "second")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Nothing is written without a key
+        let response = send(&app, Request::post("/foo?a=1").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(
+            &app,
+            projects(header::AUTHORIZATION.as_str(), "Bearer first"),
+        )
+        .await;
+        assert_eq!(body_string(response).await, "[]");
+
+        for uri in ["/", "/ready", "/health"] {
+            let response = send(&app, Request::get(uri).body(Body::empty()).unwrap()).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn project_tokens() {
+        let app = test_app("project_tokens", "[auth]\nkeys = [\"first\"]").await;
+        let database = Database::new("sqlite:file:project_tokens?mode=memory&cache=shared")
+            .await
+            .unwrap();
+        let token = database.create_project_token("foo").await.unwrap();
+
+        let with_token = |request: axum::http::request::Builder| {
+            request
+                .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = send(&app, with_token(Request::post("/new/foo"))).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = send(&app, with_token(Request::post("/foo?a=1"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The token is only valid for its own project
+        let response = send(&app, with_token(Request::post("/new/bar"))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = send(&app, with_token(Request::post("/bar?a=1"))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // And only for writing datapoints
+        let response = send(&app, with_token(Request::get("/foo"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(&app, with_token(Request::delete("/foo"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        let app = test_app("rate_limit", "[server]\nrate_limit = 3").await;
+
+        let add = |client: [u8; 4]| {
+            Request::post("/foo?a=1")
+                .extension(ConnectInfo(SocketAddr::from((client, 4000))))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for _ in 0..3 {
+            let response = send(&app, add([1, 2, 3, 4])).await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = send(&app, add([1, 2, 3, 4])).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "20");
+
+        // Other clients and reading are not limited
+        let response = send(&app, add([5, 6, 7, 8])).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    /// Creates an app backed by a fresh in-memory database
+    ///
+    /// # Arguments
+    /// * `name` Unique name for the database
+    /// * `config` Extra toml appended after the `[database]` section of the config
+    pub async fn test_app(name: &str, config: &str) -> Router {
+        router(test_state(name, config).await)
+    }
+
+    /// Creates the state of an app like [test_app], for tests that need to reach into it
+    pub async fn test_state(name: &str, config: &str) -> AppState {
+        let url = format!("sqlite:file:{}?mode=memory&cache=shared", name);
+        let config = AppConfig::from_toml(&format!("[database]\nurl = \"{}\"\n{}", url, config))
+            .expect("Config should be valid");
+
+        let database = Database::new(&url)
+            .await
+            .expect("Database should be created");
+
+        AppState::new(database, config).expect("State should be created")
+    }
+
+    /// Sends a single request to the app
+    pub async fn send(app: &Router, request: Request<Body>) -> Response {
+        app.clone()
+            .oneshot(request)
+            .await
+            .expect("Request should be handled")
+    }
+
+    /// Reads the whole body of a response as a string
+    pub async fn body_string(response: Response) -> String {
+        let bytes = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("Body should be readable");
+
+        String::from_utf8(bytes.to_vec()).expect("Body should be utf8")
+    }
+}
@@ -0,0 +1,892 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use database::{
+    project::{Column, DataType, Project},
+    Database,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    error::ApiError,
+    extract::{ApiKey, JsonBody, PaginationParams},
+};
+
+use super::{validate_project_name, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    /// Only list projects without any activity since this time
+    stale_before: Option<DateTime<Utc>>,
+}
+
+/// Project as returned by the api
+#[derive(Debug, Serialize)]
+pub struct ProjectResponse {
+    name: String,
+    created_at: DateTime<Utc>,
+    last_datapoint_at: Option<DateTime<Utc>>,
+    schema_frozen: bool,
+    /// Number of columns, only included when listing projects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column_count: Option<i64>,
+}
+
+impl From<Project> for ProjectResponse {
+    fn from(project: Project) -> Self {
+        ProjectResponse {
+            name: project.name,
+            created_at: project.created_at,
+            last_datapoint_at: project.last_datapoint_at,
+            schema_frozen: project.schema_frozen,
+            column_count: None,
+        }
+    }
+}
+
+/// Column as returned by the api
+#[derive(Debug, Serialize)]
+pub struct ColumnResponse {
+    name: String,
+    column_type: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<Column> for ColumnResponse {
+    fn from(column: Column) -> Self {
+        ColumnResponse {
+            name: column.name,
+            column_type: column.column_type.as_api_str().to_string(),
+            created_at: column.created_at,
+        }
+    }
+}
+
+/// Column as listed in the schema of a project
+#[derive(Debug, Serialize)]
+pub struct ColumnSchema {
+    name: String,
+    /// Name of the column in the project table
+    encoded: String,
+    #[serde(rename = "type")]
+    column_type: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<Column> for ColumnSchema {
+    fn from(column: Column) -> Self {
+        ColumnSchema {
+            name: column.name,
+            encoded: column.encoded,
+            column_type: column.column_type.as_api_str().to_string(),
+            created_at: column.created_at,
+        }
+    }
+}
+
+/// Changes to a column
+#[derive(Debug, Deserialize)]
+pub struct UpdateColumn {
+    /// New name of the column
+    name: String,
+}
+
+/// Result of defining columns, by name
+#[derive(Debug, Default, Serialize)]
+pub struct DefinedColumns {
+    created: Vec<String>,
+    existing: Vec<String>,
+}
+
+/// Column to create along with a new project
+#[derive(Debug, Deserialize)]
+pub struct ColumnSpec {
+    name: String,
+    #[serde(rename = "type")]
+    column_type: String,
+}
+
+/// Changes to a project, fields that are left out are not changed
+#[derive(Debug, Deserialize)]
+pub struct UpdateProject {
+    /// Reject new columns while the schema is frozen
+    schema_frozen: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DuplicateParams {
+    /// Name of the new project
+    name: String,
+}
+
+/// Lists all projects with their number of columns, optionally only the ones that have been
+/// inactive since `?stale_before=`
+pub async fn list_projects(
+    State(database): State<Database>,
+    Query(params): Query<ListParams>,
+    pagination: PaginationParams,
+) -> Result<Json<Vec<ProjectResponse>>, ApiError> {
+    let projects = match params.stale_before {
+        Some(time) => database.get_stale_projects(time).await?,
+        None => database.get_projects().await?,
+    };
+
+    let column_counts: HashMap<String, i64> = database
+        .project_summaries()
+        .await?
+        .into_iter()
+        .map(|summary| (summary.name, summary.column_count))
+        .collect();
+
+    Ok(Json(
+        projects
+            .into_iter()
+            .skip(pagination.offset as usize)
+            .take(pagination.limit as usize)
+            .map(|project| {
+                let column_count = column_counts.get(&project.name).copied().unwrap_or(0);
+                ProjectResponse {
+                    column_count: Some(column_count),
+                    ..ProjectResponse::from(project)
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// Lists the names of all projects that have a column with the given name
+pub async fn projects_with_column(
+    Path(column): Path<String>,
+    State(database): State<Database>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    Ok(Json(database.projects_with_column(&column).await?))
+}
+
+/// Creates a new project and inserts it into the database along with a corresponding table.
+///
+/// The body can hold a json array of columns to create, like
+/// `[{"name": "count", "type": "integer"}]`. They are created in the order of the array, which
+/// is the order they are exported in. Responds with `201 Created` and the new project,
+/// `409 Conflict` if the project already exists, and with `403 Forbidden` if the request has a
+/// token for a different project. Columns on the denylist are rejected with
+/// `422 Unprocessable Entity`.
+pub async fn create_project(
+    Path(project): Path<String>,
+    State(state): State<AppState>,
+    key: ApiKey,
+    body: Bytes,
+) -> Result<impl IntoResponse, ApiError> {
+    key.authorize(&state.database, &project).await?;
+
+    let columns = if body.is_empty() {
+        Vec::new()
+    } else {
+        parse_column_specs(&body)?
+    };
+    state
+        .denied_fields
+        .check_columns(columns.iter().map(|(name, _)| name.as_str()))
+        .map_err(database::Error::Validation)?;
+
+    println!("Creating new project: {}", project);
+    let project = state
+        .database
+        .create_project_with_columns(&project, &columns)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(ProjectResponse::from(project))))
+}
+
+/// Parses the columns of a new project, rejecting unknown data types and repeated names
+fn parse_column_specs(body: &[u8]) -> Result<Vec<(String, DataType)>, ApiError> {
+    let specs: Vec<ColumnSpec> = serde_json::from_slice(body)
+        .map_err(|e| ApiError::bad_request(format!("Invalid column list: {}", e)))?;
+
+    let mut columns: Vec<(String, DataType)> = Vec::with_capacity(specs.len());
+    for spec in specs {
+        let data_type = spec
+            .column_type
+            .parse::<DataType>()
+            .map_err(|e| ApiError::bad_request(format!("Column '{}': {}", spec.name, e)))?;
+
+        if columns.iter().any(|(name, _)| *name == spec.name) {
+            return Err(ApiError::bad_request(format!(
+                "Column '{}' is listed more than once",
+                spec.name
+            )));
+        }
+        columns.push((spec.name, data_type));
+    }
+
+    Ok(columns)
+}
+
+/// Copies a project, including all of its columns and data, into a new project
+///
+/// The new name has to be valid like every other project name, see [validate_project_name].
+pub async fn duplicate_project(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+    Query(params): Query<DuplicateParams>,
+) -> Result<(StatusCode, String), ApiError> {
+    validate_project_name(&params.name)?;
+    database.duplicate_project(&project, &params.name).await?;
+
+    Ok((StatusCode::CREATED, params.name))
+}
+
+/// Deletes a project and all of its data
+pub async fn delete_project(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+) -> Result<StatusCode, ApiError> {
+    match database.delete_project(&project).await? {
+        true => Ok(StatusCode::NO_CONTENT),
+        false => Err(ApiError::not_found("Project not found")),
+    }
+}
+
+/// Updates the settings of a project
+pub async fn update_project(
+    Path(project): Path<String>,
+    State(database): State<Database>,
+    JsonBody(update): JsonBody<UpdateProject>,
+) -> Result<Json<ProjectResponse>, ApiError> {
+    let mut updated = database.get_project(&project).await?;
+
+    if let Some(frozen) = update.schema_frozen {
+        updated = database.set_schema_frozen(&project, frozen).await?;
+    }
+
+    let updated = updated.ok_or_else(|| ApiError::not_found("Project not found"))?;
+    Ok(Json(ProjectResponse::from(updated)))
+}
+
+/// Lists the columns of a project with their types, in the order they were created
+///
+/// The response can be cached by clients for the configured max age, and revalidated with the
+/// ETag in `If-None-Match`, which gets a 304 as long as the columns are unchanged.
+pub async fn list_columns(
+    Path(project): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let project = state
+        .database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let columns = project.get_columns().await?;
+    let etag = columns_etag(&columns);
+    let caching = [
+        (header::ETAG, etag.clone()),
+        (
+            header::CACHE_CONTROL,
+            format!("max-age={}", state.config.get_schema_max_age()),
+        ),
+    ];
+
+    if etag_matches(&headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, caching).into_response());
+    }
+
+    let columns: Vec<ColumnSchema> = columns.into_iter().map(ColumnSchema::from).collect();
+    Ok((caching, Json(columns)).into_response())
+}
+
+/// ETag of a column listing, starting with the time the newest column was created
+///
+/// The rest is a hash of every column, since renaming or deleting a column or creating one in
+/// the same second does not change the newest creation time. SHA-256 keeps the ETag the same
+/// across builds and restarts.
+fn columns_etag(columns: &[Column]) -> String {
+    let newest = columns
+        .iter()
+        .map(|c| c.created_at.timestamp())
+        .max()
+        .unwrap_or(0);
+
+    // Every field ends in a NUL, so moving text between fields changes the hash
+    let mut hasher = Sha256::new();
+    for column in columns {
+        for field in [
+            column.name.as_str(),
+            column.encoded.as_str(),
+            column.column_type.to_sql(),
+            &column.created_at.timestamp().to_string(),
+        ] {
+            hasher.update(field.as_bytes());
+            hasher.update([0]);
+        }
+    }
+
+    let hash: String = hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("\"{}-{}\"", newest, hash)
+}
+
+/// Whether the `If-None-Match` header lists the ETag, weak or not, or is `*`
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// Defines columns of a project up front
+///
+/// Every key of the query string is a column name and its value the data type, like
+/// `?count=integer&name=text`. Nothing is created if any data type is invalid or any name is
+/// on the denylist.
+pub async fn define_columns(
+    Path(project): Path<String>,
+    State(state): State<AppState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Result<Json<DefinedColumns>, ApiError> {
+    let project = state
+        .database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    // Validate everything before creating any columns
+    let mut columns = query
+        .into_iter()
+        .map(|(name, data_type)| match data_type.parse::<DataType>() {
+            Ok(data_type) => Ok((name, data_type)),
+            Err(e) => Err(ApiError::bad_request(format!("Column '{}': {}", name, e))),
+        })
+        .collect::<Result<Vec<(String, DataType)>, ApiError>>()?;
+    columns.sort();
+    state
+        .denied_fields
+        .check_columns(columns.iter().map(|(name, _)| name.as_str()))
+        .map_err(database::Error::Validation)?;
+
+    let existing: Vec<String> = project
+        .get_columns()
+        .await?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+
+    let mut defined = DefinedColumns::default();
+    for (name, data_type) in columns {
+        if existing.contains(&name) {
+            defined.existing.push(name);
+        } else {
+            project.create_column(&name, data_type).await?;
+            defined.created.push(name);
+        }
+    }
+
+    Ok(Json(defined))
+}
+
+/// Renames a column of a project, the data of the column is kept
+///
+/// The new name can not be on the denylist, like the names of newly created columns.
+pub async fn rename_column(
+    Path((project, column)): Path<(String, String)>,
+    State(state): State<AppState>,
+    JsonBody(update): JsonBody<UpdateColumn>,
+) -> Result<Json<ColumnResponse>, ApiError> {
+    state
+        .denied_fields
+        .check_columns([update.name.as_str()])
+        .map_err(database::Error::Validation)?;
+
+    let project = state
+        .database
+        .get_project(&project)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Project not found"))?;
+
+    let renamed = project
+        .rename_column(&column, &update.name)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Column not found"))?;
+
+    Ok(Json(ColumnResponse::from(renamed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+    };
+
+    use crate::routes::tests::{body_string, send, test_app};
+
+    #[tokio::test]
+    async fn list_projects() {
+        let app = test_app("list_projects", "").await;
+
+        for name in ["foo", "bar", "baz"] {
+            send(
+                &app,
+                Request::post(format!("/new/{}", name))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        }
+
+        let list = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let response = send(&app, list("/projects")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let projects: serde_json::Value =
+            serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(projects.as_array().unwrap().len(), 3);
+        assert!(projects
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|p| p["column_count"] == 0));
+
+        send(
+            &app,
+            Request::post("/foo?a=1&b=2").body(Body::empty()).unwrap(),
+        )
+        .await;
+        let response = send(&app, list("/projects")).await;
+        let projects: serde_json::Value =
+            serde_json::from_str(&body_string(response).await).unwrap();
+        let foo = projects
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "foo")
+            .unwrap();
+        assert_eq!(foo["column_count"], 2);
+
+        let response = send(&app, list("/projects?limit=2&offset=0")).await;
+        let projects: serde_json::Value =
+            serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(projects.as_array().unwrap().len(), 2);
+
+        let response = send(&app, list("/projects?stale_before=2000-01-01T00:00:00Z")).await;
+        let projects: serde_json::Value =
+            serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(projects.as_array().unwrap().len(), 0);
+
+        let response = send(&app, list("/projects?limit=0")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn projects_with_column() {
+        let app = test_app("projects_with_column_route", "").await;
+
+        for uri in [
+            "/foo?temperature=1",
+            "/bar?humidity=2",
+            "/baz?temperature=3&humidity=4",
+        ] {
+            send(&app, Request::post(uri).body(Body::empty()).unwrap()).await;
+        }
+
+        let response = send(
+            &app,
+            Request::get("/columns/temperature/projects")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let projects: Vec<String> = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(projects, vec!["baz".to_string(), "foo".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn create_project() {
+        let app = test_app("create_project_route", "").await;
+
+        let request = || Request::post("/new/foo").body(Body::empty()).unwrap();
+
+        let response = send(&app, request()).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["name"], "foo");
+
+        let response = send(&app, request()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("already exists"));
+
+        let response = send(
+            &app,
+            Request::post("/new/foo%2Fbar").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(&app, Request::post("/new/@@@").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn create_project_with_columns() {
+        let app = test_app("create_project_with_columns", "").await;
+
+        let columns = r#"[
+            {"name": "zeta", "type": "integer"},
+            {"name": "alpha", "type": "text"},
+            {"name": "mid", "type": "float"}
+        ]"#;
+        let response = send(
+            &app,
+            Request::post("/new/foo").body(Body::from(columns)).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(&app, Request::get("/foo/csv").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "zeta,alpha,mid\r\n");
+    }
+
+    #[tokio::test]
+    async fn create_project_with_invalid_columns() {
+        let app = test_app("create_project_with_invalid_columns", "").await;
+
+        for columns in [
+            r#"[{"name": "count", "type": "number"}]"#,
+            r#"[{"name": "count", "type": "integer"}, {"name": "count", "type": "text"}]"#,
+            r#"{"count": "integer"}"#,
+        ] {
+            let response = send(
+                &app,
+                Request::post("/new/foo").body(Body::from(columns)).unwrap(),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", columns);
+        }
+
+        // The project was never created
+        let response = send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn denied_column_names() {
+        let config = "[ingest]\ndenied_fields = [\"password\"]\ndenied_fields_action = \"drop\"";
+        let app = test_app("denied_column_names", config).await;
+
+        // Even though datapoints only lose the field, columns asked for by name are refused
+        let columns = r#"[{"name": "name", "type": "text"}, {"name": "Password", "type": "text"}]"#;
+        let response = send(
+            &app,
+            Request::post("/new/foo").body(Body::from(columns)).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let columns = r#"[{"name": "name", "type": "text"}]"#;
+        let response = send(
+            &app,
+            Request::post("/new/foo").body(Body::from(columns)).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(
+            &app,
+            Request::post("/foo/columns?count=integer&password=text")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = send(
+            &app,
+            Request::patch("/foo/columns/name")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"name": "password"}"#))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = send(&app, Request::get("/foo/csv").body(Body::empty()).unwrap()).await;
+        assert_eq!(body_string(response).await, "name\r\n");
+    }
+
+    #[tokio::test]
+    async fn duplicate_project() {
+        let app = test_app("duplicate_project_route", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let request = || {
+            Request::post("/foo/duplicate?name=copy")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = send(&app, request()).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = send(&app, request()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let response = send(
+            &app,
+            Request::post("/missing/duplicate?name=other")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn update_project() {
+        let app = test_app("update_project", "").await;
+
+        send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+
+        let patch = |uri: &str, body: &'static str| {
+            Request::patch(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = send(&app, patch("/foo", r#"{"schema_frozen": true}"#)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let project: serde_json::Value =
+            serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(project["schema_frozen"], true);
+
+        let response = send(&app, patch("/foo", r#"{"schema_frozen": false}"#)).await;
+        let project: serde_json::Value =
+            serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(project["schema_frozen"], false);
+
+        let response = send(&app, patch("/missing", r#"{"schema_frozen": true}"#)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn list_columns() {
+        let app = test_app("list_columns", "").await;
+
+        send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+
+        let response = send(
+            &app,
+            Request::get("/foo/columns").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_string(response).await, "[]");
+
+        send(
+            &app,
+            Request::post("/foo/columns?count=bigint&my%20name=text")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        let response = send(
+            &app,
+            Request::get("/foo/columns").body(Body::empty()).unwrap(),
+        )
+        .await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        let columns = body.as_array().unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0]["name"], "count");
+        assert_eq!(columns[0]["encoded"], "count");
+        assert_eq!(columns[0]["type"], "bigint");
+        assert_eq!(columns[1]["name"], "my name");
+        assert_ne!(columns[1]["encoded"], "my name");
+        assert_eq!(columns[1]["type"], "text");
+        assert!(columns[1]["created_at"].is_string());
+
+        let response = send(
+            &app,
+            Request::get("/missing/columns")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn list_columns_caching() {
+        let app = test_app("list_columns_caching", "[server]\nschema_max_age = 30").await;
+
+        send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+        send(
+            &app,
+            Request::post("/foo/columns?count=integer")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        let response = send(
+            &app,
+            Request::get("/foo/columns").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "max-age=30");
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let revalidate = |etag: &str| {
+            Request::get("/foo/columns")
+                .header(header::IF_NONE_MATCH, etag)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = send(&app, revalidate(&etag)).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        assert!(body_string(response).await.is_empty());
+
+        // A new column in the same second still changes the ETag
+        send(
+            &app,
+            Request::post("/foo/columns?name=text")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        let response = send(&app, revalidate(&etag)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag.as_str());
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn define_columns() {
+        let app = test_app("define_columns", "").await;
+
+        send(&app, Request::post("/new/foo").body(Body::empty()).unwrap()).await;
+
+        let define = |uri: &str| Request::post(uri).body(Body::empty()).unwrap();
+
+        let response = send(&app, define("/foo/columns?count=integer&name=text")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["created"], serde_json::json!(["count", "name"]));
+        assert_eq!(body["existing"], serde_json::json!([]));
+
+        let response = send(&app, define("/foo/columns?count=integer&ratio=float")).await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["created"], serde_json::json!(["ratio"]));
+        assert_eq!(body["existing"], serde_json::json!(["count"]));
+
+        let response = send(&app, define("/foo/columns?other=text&broken=number")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("broken"));
+
+        // Nothing is created when a type is invalid
+        let response = send(&app, define("/foo/columns?other=text")).await;
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["created"], serde_json::json!(["other"]));
+
+        let response = send(&app, define("/missing/columns?count=integer")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn delete_project() {
+        let app = test_app("delete_project_route", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=baz").body(Body::empty()).unwrap(),
+        )
+        .await;
+
+        let delete = || Request::delete("/foo").body(Body::empty()).unwrap();
+
+        let response = send(&app, delete()).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(&app, delete()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn rename_column() {
+        let app = test_app("rename_column", "").await;
+
+        send(
+            &app,
+            Request::post("/foo?bar=1&taken=2")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+
+        let rename = |uri: &str, name: &str| {
+            Request::patch(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(format!(r#"{{"name": "{}"}}"#, name)))
+                .unwrap()
+        };
+
+        let response = send(&app, rename("/foo/columns/bar", "baz")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let column: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(column["name"], "baz");
+        assert_eq!(column["column_type"], "integer");
+
+        let response = send(&app, Request::get("/foo").body(Body::empty()).unwrap()).await;
+        let data: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(data[0]["baz"], "1");
+        assert!(data[0].get("bar").is_none());
+
+        let response = send(&app, rename("/foo/columns/baz", "taken")).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let response = send(&app, rename("/foo/columns/bar", "qux")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = send(&app, rename("/missing/columns/bar", "qux")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{error::Error, path::PathBuf};
 
 /// Gets the name of the current directory the binary was run from.
 /// If youa re in a directory named `fkit` this function will return the string `"fkit"`
@@ -12,3 +12,65 @@ pub fn current_directory_name() -> Result<String, Box<dyn Error>> {
 
     Ok(dir_name.to_string())
 }
+
+/// Will check that the database file exists and create it if it doesnt.
+/// The database file path is extracted from the config file.
+///
+/// With `create_if_missing` disabled a missing file is an error instead, so a wrong path in the
+/// config does not silently start out with an empty database.
+pub fn check_database_file(
+    database_path: PathBuf,
+    create_if_missing: bool,
+) -> Result<(), Box<dyn Error>> {
+    if database_path.exists() {
+        println!("Database exists");
+        return Ok(());
+    }
+
+    if !create_if_missing {
+        return Err(format!(
+            "Database file {} does not exist and create_if_missing is disabled in the config",
+            database_path.display()
+        )
+        .into());
+    }
+
+    let write_res = std::fs::File::create(&database_path);
+
+    if let Err(e) = &write_res {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                println!("Could not create the database file. Ensure the database url is correct in the config file.");
+                println!("For explanations on the config, run \"fkit --config-help\"");
+            }
+            _ => {
+                println!("Error creating database file: {:?}", e);
+            }
+        }
+    };
+
+    write_res?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_database_file;
+
+    #[test]
+    fn missing_database_file() {
+        let path = std::env::temp_dir().join("fkit_missing_database_file.db");
+        std::fs::remove_file(&path).ok();
+
+        let err = check_database_file(path.clone(), false).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+        assert!(err.to_string().contains("fkit_missing_database_file.db"));
+        assert!(!path.exists());
+
+        check_database_file(path.clone(), true).unwrap();
+        assert!(path.exists());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,2 @@
+-- Time the last datapoint was added to a project, NULL until the first one
+ALTER TABLE projects ADD COLUMN last_datapoint_at INTEGER;
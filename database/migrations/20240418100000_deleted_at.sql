@@ -0,0 +1,2 @@
+-- Time a project was archived, NULL for active projects. Archived projects keep their table
+ALTER TABLE projects ADD COLUMN deleted_at INTEGER;
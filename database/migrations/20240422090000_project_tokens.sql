@@ -0,0 +1,6 @@
+-- Tokens that may only write to a single project. Only a SHA-256 hash of the token is stored
+CREATE TABLE IF NOT EXISTS project_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY NOT NULL,
+    project_name TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,20 @@
+-- Same schema as the SQLite migration. MySQL can only index text with a length, so names are
+-- VARCHAR. Timestamps are unix seconds and need 64 bits
+CREATE TABLE IF NOT EXISTS projects (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    name VARCHAR(255) UNIQUE NOT NULL,
+    encoded_name VARCHAR(255) UNIQUE NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+-- MySQL has no rowid, columns created in the same second are ordered by id instead
+CREATE TABLE IF NOT EXISTS columns (
+    id BIGINT AUTO_INCREMENT PRIMARY KEY,
+    project_id BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    encoded TEXT NOT NULL,
+    column_type TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+
+    FOREIGN KEY (project_id) REFERENCES projects(id)
+);
@@ -0,0 +1,17 @@
+-- Same schema as the SQLite migration. Timestamps are unix seconds and need 64 bits
+CREATE TABLE IF NOT EXISTS projects (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    encoded_name TEXT UNIQUE NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+-- Postgres has no rowid, columns created in the same second are ordered by id instead
+CREATE TABLE IF NOT EXISTS columns (
+    id BIGSERIAL PRIMARY KEY,
+    project_id BIGINT NOT NULL REFERENCES projects(id),
+    name TEXT NOT NULL,
+    encoded TEXT NOT NULL,
+    column_type TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
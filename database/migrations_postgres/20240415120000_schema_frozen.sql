@@ -0,0 +1,2 @@
+-- Projects with a frozen schema reject new columns, 0 or 1
+ALTER TABLE projects ADD COLUMN schema_frozen BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,16 @@
+-- Store the time of every datapoint as a native timestamp instead of unix seconds. New project
+-- tables are created this way, the existing ones are converted in place
+DO $$
+DECLARE
+    project RECORD;
+BEGIN
+    FOR project IN SELECT encoded_name FROM projects LOOP
+        IF to_regclass(format('%I', project.encoded_name)) IS NOT NULL THEN
+            EXECUTE format(
+                'ALTER TABLE %I ALTER COLUMN __timestamp__ TYPE TIMESTAMPTZ USING TO_TIMESTAMP(__timestamp__)',
+                project.encoded_name
+            );
+        END IF;
+    END LOOP;
+END
+$$;
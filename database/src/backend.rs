@@ -0,0 +1,85 @@
+/// Database backends that can be used through the generic sqlx driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Sqlite,
+    Postgres,
+    MySql,
+}
+
+impl Backend {
+    /// Detect the backend from the scheme of a database url
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::Backend;
+    /// assert_eq!(Backend::from_url("sqlite://./foo.db"), Some(Backend::Sqlite));
+    /// assert_eq!(Backend::from_url("postgres://localhost/foo"), Some(Backend::Postgres));
+    /// assert_eq!(Backend::from_url("redis://localhost"), None);
+    /// ```
+    ///
+    /// # Returns
+    /// The backend or None if the scheme is not supported
+    pub fn from_url(url: &str) -> Option<Backend> {
+        match url.split(':').next()? {
+            "sqlite" => Some(Backend::Sqlite),
+            "postgres" | "postgresql" => Some(Backend::Postgres),
+            "mysql" | "mariadb" => Some(Backend::MySql),
+            _ => None,
+        }
+    }
+
+    /// Check if a url points to an in-memory SQLite database
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::Backend;
+    /// assert!(Backend::is_memory_url("sqlite:file:foo?mode=memory"));
+    /// assert!(Backend::is_memory_url("sqlite::memory:"));
+    /// assert!(!Backend::is_memory_url("sqlite://./foo.db"));
+    /// ```
+    pub fn is_memory_url(url: &str) -> bool {
+        Backend::from_url(url) == Some(Backend::Sqlite)
+            && (url.contains("mode=memory") || url.contains(":memory:"))
+    }
+
+    /// Rewrite the `?` placeholders of a query to the style of the backend
+    ///
+    /// The generic driver passes queries through as they are, but Postgres only understands
+    /// numbered placeholders. Queries are written with `?` and converted right before they are
+    /// run. Question marks inside quotes are left alone.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::Backend;
+    /// assert_eq!(
+    ///     Backend::Postgres.sql("SELECT * FROM projects WHERE name = ? AND id = ?"),
+    ///     "SELECT * FROM projects WHERE name = $1 AND id = $2"
+    /// );
+    /// assert_eq!(Backend::Sqlite.sql("SELECT '?' = ?"), "SELECT '?' = ?");
+    /// ```
+    pub fn sql(&self, query: &str) -> String {
+        if *self != Backend::Postgres {
+            return query.to_string();
+        }
+
+        let mut output = String::with_capacity(query.len() + 8);
+        let mut quote = None;
+        let mut placeholders = 0;
+
+        for c in query.chars() {
+            match (c, quote) {
+                ('\'' | '"', None) => quote = Some(c),
+                (c, Some(open)) if c == open => quote = None,
+                ('?', None) => {
+                    placeholders += 1;
+                    output.push_str(&format!("${}", placeholders));
+                    continue;
+                }
+                _ => {}
+            }
+            output.push(c);
+        }
+
+        output
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use chrono::DateTime;
+
+use crate::{project::Column, Project};
+
+/// Active projects by name and their columns, shared between all clones of a
+/// [crate::Database]
+///
+/// Projects are only cached by [crate::Database::get_project]. Changes to a project made
+/// through the [crate::Database] remove it from the cache, and changes to the columns of a
+/// project remove its columns, so the next lookup fetches them again.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ProjectCache {
+    projects: Arc<RwLock<HashMap<String, Project>>>,
+    columns: Arc<RwLock<ColumnCache>>,
+}
+
+/// Columns by project id
+#[derive(Debug, Default)]
+struct ColumnCache {
+    /// Bumped whenever columns are removed, so lists fetched before a change are not stored
+    generation: u64,
+    by_project: HashMap<i64, Vec<Column>>,
+}
+
+impl ProjectCache {
+    /// Cached project with the name, None if it has not been fetched yet
+    pub fn get(&self, name: &str) -> Option<Project> {
+        let projects = self.projects.read().unwrap_or_else(PoisonError::into_inner);
+        let mut project = projects.get(name)?.clone();
+        project.cache = Some(self.clone());
+        Some(project)
+    }
+
+    pub fn insert(&self, project: &Project) {
+        // Cached projects do not hold on to the cache, which would keep it alive forever
+        let mut project = project.clone();
+        project.cache = None;
+
+        self.projects
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(project.name.clone(), project);
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.projects
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(name);
+    }
+
+    pub fn clear(&self) {
+        self.projects
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+
+        let mut columns = self.columns.write().unwrap_or_else(PoisonError::into_inner);
+        columns.generation += 1;
+        columns.by_project.clear();
+    }
+
+    /// Update the time of the last datapoint of a cached project
+    pub fn touch(&self, name: &str, timestamp: i64) {
+        let mut projects = self
+            .projects
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(project) = projects.get_mut(name) {
+            project.last_datapoint_at = DateTime::from_timestamp(timestamp, 0);
+        }
+    }
+
+    /// Cached columns of a project, None if they have not been fetched since the last change
+    pub fn columns(&self, project_id: i64) -> Option<Vec<Column>> {
+        self.columns
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .by_project
+            .get(&project_id)
+            .cloned()
+    }
+
+    /// Generation to pass to [ProjectCache::insert_columns], taken before fetching columns
+    pub fn column_generation(&self) -> u64 {
+        self.columns
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .generation
+    }
+
+    /// Cache the columns of a project, unless any columns changed since they were fetched
+    pub fn insert_columns(&self, project_id: i64, columns: &[Column], generation: u64) {
+        let mut cache = self.columns.write().unwrap_or_else(PoisonError::into_inner);
+        if cache.generation == generation {
+            cache.by_project.insert(project_id, columns.to_vec());
+        }
+    }
+
+    pub fn remove_columns(&self, project_id: i64) {
+        let mut columns = self.columns.write().unwrap_or_else(PoisonError::into_inner);
+        columns.generation += 1;
+        columns.by_project.remove(&project_id);
+    }
+}
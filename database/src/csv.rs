@@ -0,0 +1,658 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+};
+
+use crate::{
+    project::{DataType, Datapoint, Project, TIMESTAMP_COLUMN},
+    Database, Error,
+};
+
+/// Datapoints read at once by [Database::merged_export] from the first project
+const MERGED_EXPORT_PAGE_SIZE: i64 = 1000;
+
+/// Options for exporting project data with [Project::to_csv] and importing it with
+/// [Project::import_csv]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// Text written in place of NULL values. Empty by default, which makes NULL and empty text
+    /// indistinguishable, use something like `\N` to keep them apart
+    pub null: String,
+
+    /// Write the data type after every column name in the header, like `count:integer`
+    pub typed_headers: bool,
+}
+
+impl CsvOptions {
+    /// Options writing NULL values as the given token
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::csv::CsvOptions;
+    /// let options = CsvOptions::with_null("\\N");
+    /// assert_eq!(options.null, "\\N");
+    /// ```
+    pub fn with_null(null: impl Into<String>) -> CsvOptions {
+        CsvOptions {
+            null: null.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Project {
+    /// Export all datapoints of the project as CSV
+    ///
+    /// The first row is a header with the column names, in the same order as
+    /// [Project::get_columns], followed by their data type if [CsvOptions::typed_headers] is set.
+    /// NULL values are written as [CsvOptions::null], empty text is always written as an empty
+    /// field.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, csv::CsvOptions};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:to_csv?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let csv = project.to_csv(&CsvOptions::default()).await?;
+    /// assert_eq!(csv, "bar\r\nbaz\r\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn to_csv(&self, options: &CsvOptions) -> Result<String, Error> {
+        let columns = self.get_columns().await?;
+        let data = self.get_data().await?;
+
+        let mut csv = String::new();
+        push_row(
+            &mut csv,
+            columns.iter().map(|c| match options.typed_headers {
+                true => escape_field(&format!("{}:{}", c.name, c.column_type.as_api_str())),
+                false => escape_field(&c.name),
+            }),
+        );
+
+        for row in data {
+            push_row(
+                &mut csv,
+                columns.iter().map(|c| match row.get(&c.name) {
+                    Some(Some(value)) => escape_field(value),
+                    _ => options.null.clone(),
+                }),
+            );
+        }
+
+        Ok(csv)
+    }
+
+    /// Import datapoints from CSV, like the output of [Project::to_csv] with the same options
+    ///
+    /// The first row is the header with the column names. With [CsvOptions::typed_headers]
+    /// every name is followed by its data type, and missing columns are created with that type.
+    /// Otherwise missing columns get their type inferred from the data. Fields equal to
+    /// [CsvOptions::null] are left out. All rows are added in a single batch, see
+    /// [Project::add_datapoints]. Columns from typed headers are created in the same transaction
+    /// as the rows, so a failed import does not leave them behind.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, csv::CsvOptions, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:import_csv?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let options = CsvOptions {
+    ///     typed_headers: true,
+    ///     ..Default::default()
+    /// };
+    /// let rows = project.import_csv("zip:text\r\n01234\r\n", &options).await?;
+    ///
+    /// assert_eq!(rows, 1);
+    /// assert_eq!(project.get_columns().await?[0].column_type, DataType::Text);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The number of imported rows
+    pub async fn import_csv(&self, csv: &str, options: &CsvOptions) -> Result<usize, Error> {
+        let mut records = parse_csv(csv)?.into_iter();
+        let header = match records.next() {
+            // The export of a project without columns has an empty header
+            Some(header) if header == [""] => Vec::new(),
+            Some(header) => header,
+            None => return Ok(0),
+        };
+
+        let mut names = Vec::with_capacity(header.len());
+        let mut types = Vec::new();
+        for field in header {
+            let name = match options.typed_headers {
+                true => {
+                    let (name, data_type) = field.rsplit_once(':').ok_or_else(|| {
+                        Error::Csv(format!("Header '{}' has no data type", field))
+                    })?;
+                    let data_type: DataType = data_type
+                        .parse()
+                        .map_err(|e| Error::Csv(format!("Header '{}': {}", field, e)))?;
+
+                    types.push((name.to_string(), data_type));
+                    name.to_string()
+                }
+                false => field,
+            };
+
+            if names.contains(&name) {
+                return Err(Error::Csv(format!("Column '{}' appears twice", name)));
+            }
+            names.push(name);
+        }
+
+        let mut rows = Vec::new();
+        for (index, record) in records.enumerate() {
+            if record.len() != names.len() {
+                return Err(Error::Csv(format!(
+                    "Row {} has {} fields, expected {}",
+                    index + 1,
+                    record.len(),
+                    names.len()
+                )));
+            }
+
+            let row: HashMap<String, String> = names
+                .iter()
+                .cloned()
+                .zip(record)
+                .filter(|(_, value)| *value != options.null)
+                .collect();
+            rows.push(row);
+        }
+
+        match types.is_empty() {
+            true => self.add_datapoints(rows).await,
+            false => self.add_datapoints_with_columns(&types, &rows).await,
+        }
+    }
+}
+
+impl Database {
+    /// Export several projects as a single CSV, outer joined on a column they share
+    ///
+    /// Joining on [TIMESTAMP_COLUMN] matches datapoints added at the same time, written as
+    /// RFC 3339. The first column holds the value joined on, followed by the other columns of
+    /// every project in order, named `project.column` so they can not clash. Values that are
+    /// equal as text are joined, and a value appearing several times in more than one project
+    /// gives a row for every combination.
+    ///
+    /// Rows are written as they are merged. The first project is read a page at a time and only
+    /// the other projects are held in memory, so the largest project should come first. Rows
+    /// without a value to join on, including every row of a project without the column, match
+    /// nothing and have an empty first column. Those of the first project are written in
+    /// place, those of the other projects at the end.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:merged_export?mode=memory").await?;
+    /// let prices = db.create_project("prices").await?;
+    /// let stock = db.create_project("stock").await?;
+    ///
+    /// let row = |key: &str, value: &str| {
+    ///     HashMap::from([
+    ///         ("item".to_string(), key.to_string()),
+    ///         ("value".to_string(), value.to_string()),
+    ///     ])
+    /// };
+    /// prices.add_datapoint(row("apple", "3")).await?;
+    /// stock.add_datapoint(row("apple", "40")).await?;
+    ///
+    /// let projects = ["prices".to_string(), "stock".to_string()];
+    /// let mut csv = Vec::new();
+    /// db.merged_export(&projects, "item", &mut csv).await?;
+    /// assert_eq!(csv, b"item,prices.value,stock.value\r\napple,3,40\r\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// [Error::ProjectNotFound] if any of the projects does not exist, before anything is
+    /// written
+    pub async fn merged_export<W: Write + Send>(
+        &self,
+        projects: &[String],
+        on: &str,
+        mut writer: W,
+    ) -> Result<(), Error> {
+        let mut merged = Vec::with_capacity(projects.len());
+        for name in projects {
+            let project = self
+                .get_project(name)
+                .await?
+                .ok_or_else(|| Error::ProjectNotFound(name.clone()))?;
+            let columns: Vec<String> = project
+                .get_columns()
+                .await?
+                .into_iter()
+                .map(|c| c.name)
+                .filter(|c| c != on)
+                .collect();
+            merged.push((project, columns));
+        }
+        let Some(((first, first_columns), others)) = merged.split_first() else {
+            return write_row(&mut writer, std::iter::once(escape_field(on)));
+        };
+
+        let mut header = vec![escape_field(on)];
+        for (project, columns) in &merged {
+            header.extend(
+                columns
+                    .iter()
+                    .map(|c| escape_field(&format!("{}.{}", project.name, c))),
+            );
+        }
+        write_row(&mut writer, header.into_iter())?;
+
+        // Keys of the other projects in the order they first appear, and their rows by key
+        let mut keys: Vec<String> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut joined: Vec<HashMap<String, Vec<Vec<String>>>> = Vec::new();
+        let mut unmatched: Vec<Vec<Vec<String>>> = Vec::new();
+        for (project, columns) in others {
+            let mut by_key: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+            let mut without_key = Vec::new();
+            for datapoint in project.get_data_with_timestamps().await? {
+                let fields = merged_fields(&datapoint, columns);
+                match merged_key(&datapoint, on) {
+                    Some(key) => {
+                        if seen.insert(key.clone()) {
+                            keys.push(key.clone());
+                        }
+                        by_key.entry(key).or_default().push(fields);
+                    }
+                    None => without_key.push(fields),
+                }
+            }
+            joined.push(by_key);
+            unmatched.push(without_key);
+        }
+        let widths: Vec<usize> = merged.iter().map(|(_, columns)| columns.len()).collect();
+
+        // Rows of the first project are joined as they are read
+        let mut matched: HashSet<String> = HashSet::new();
+        let mut offset = 0;
+        loop {
+            let page = first
+                .get_data_paged(MERGED_EXPORT_PAGE_SIZE, offset)
+                .await?;
+            for datapoint in &page {
+                let fields = merged_fields(datapoint, first_columns);
+                match merged_key(datapoint, on) {
+                    Some(key) => {
+                        write_combinations(&mut writer, &key, fields, &joined, &widths[1..])?;
+                        matched.insert(key);
+                    }
+                    None => {
+                        let after: usize = widths[1..].iter().sum();
+                        let row = std::iter::once(String::new())
+                            .chain(fields)
+                            .chain(vec![String::new(); after]);
+                        write_row(&mut writer, row)?;
+                    }
+                }
+            }
+
+            if (page.len() as i64) < MERGED_EXPORT_PAGE_SIZE {
+                break;
+            }
+            offset += MERGED_EXPORT_PAGE_SIZE;
+        }
+
+        // Keys the first project does not have
+        for key in keys.iter().filter(|key| !matched.contains(*key)) {
+            let blank = vec![String::new(); widths[0]];
+            write_combinations(&mut writer, key, blank, &joined, &widths[1..])?;
+        }
+
+        for (index, without_key) in unmatched.into_iter().enumerate() {
+            let before: usize = widths[..=index].iter().sum();
+            let after: usize = widths[index + 2..].iter().sum();
+            for fields in without_key {
+                let row = std::iter::once(String::new())
+                    .chain(vec![String::new(); before])
+                    .chain(fields)
+                    .chain(vec![String::new(); after]);
+                write_row(&mut writer, row)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Value a datapoint is joined on in [Database::merged_export]
+fn merged_key(datapoint: &Datapoint, on: &str) -> Option<String> {
+    match on {
+        TIMESTAMP_COLUMN => Some(datapoint.timestamp.to_rfc3339()),
+        _ => datapoint.values.get(on).cloned().flatten(),
+    }
+}
+
+/// Escaped values of the columns of a datapoint, empty where it has none
+fn merged_fields(datapoint: &Datapoint, columns: &[String]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|c| match datapoint.values.get(c) {
+            Some(Some(value)) => escape_field(value),
+            _ => String::new(),
+        })
+        .collect()
+}
+
+/// Write every combination of the fields of a row with the rows of the other projects sharing
+/// its key, a project without any adds blanks
+fn write_combinations(
+    writer: &mut impl Write,
+    key: &str,
+    fields: Vec<String>,
+    joined: &[HashMap<String, Vec<Vec<String>>>],
+    widths: &[usize],
+) -> Result<(), Error> {
+    let mut rows: Vec<Vec<String>> =
+        vec![std::iter::once(escape_field(key)).chain(fields).collect()];
+    for (by_key, width) in joined.iter().zip(widths) {
+        let blank = vec![vec![String::new(); *width]];
+        let matches = by_key.get(key).unwrap_or(&blank);
+        rows = rows
+            .into_iter()
+            .flat_map(|row| {
+                matches.iter().map(move |fields| {
+                    let mut row = row.clone();
+                    row.extend(fields.iter().cloned());
+                    row
+                })
+            })
+            .collect();
+    }
+
+    for row in rows {
+        write_row(writer, row.into_iter())?;
+    }
+    Ok(())
+}
+
+/// Split CSV into records of unescaped fields, according to RFC 4180
+///
+/// Records can end with either CRLF or LF, a trailing line break is optional.
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>, Error> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(Error::Csv("Unterminated quoted field".to_string()));
+    }
+
+    // Last record without a trailing line break
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Append a single CSV record, fields have to be escaped already
+fn push_row(csv: &mut String, fields: impl Iterator<Item = String>) {
+    let fields: Vec<String> = fields.collect();
+    csv.push_str(&fields.join(","));
+    csv.push_str("\r\n");
+}
+
+/// Write a row of already escaped fields
+fn write_row(writer: &mut impl Write, fields: impl Iterator<Item = String>) -> Result<(), Error> {
+    let mut row = String::new();
+    push_row(&mut row, fields);
+    writer
+        .write_all(row.as_bytes())
+        .map_err(|e| Error::Sqlx(sqlx::Error::Io(e)))
+}
+
+/// Escape a field according to RFC 4180, quoting it if it contains a comma, quote or line break
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{escape_field, parse_csv, CsvOptions};
+    use crate::{database::methods::create_mem_db, project::DataType, Error};
+
+    #[test]
+    fn escape() {
+        assert_eq!(escape_field("foo"), "foo");
+        assert_eq!(escape_field("foo,bar"), "\"foo,bar\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[tokio::test]
+    async fn empty_project_has_header() {
+        let db = create_mem_db("csv_empty_project").await;
+        let project = db.create("foo").await;
+
+        project.create("b").await;
+        project.create("a").await;
+
+        let csv = project.to_csv(&CsvOptions::default()).await.unwrap();
+        assert_eq!(csv, "b,a\r\n");
+    }
+
+    #[tokio::test]
+    async fn null_and_empty_differ() {
+        let db = create_mem_db("csv_null_and_empty").await;
+        let project = db.create("foo").await;
+
+        project.create("a").await;
+        project.create("b").await;
+
+        let mut data = HashMap::new();
+        data.insert("a".to_string(), "".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        let default = project.to_csv(&CsvOptions::default()).await.unwrap();
+        assert_eq!(default, "a,b\r\n,\r\n");
+
+        let custom = project.to_csv(&CsvOptions::with_null("\\N")).await.unwrap();
+        assert_eq!(custom, "a,b\r\n,\\N\r\n");
+    }
+
+    #[test]
+    fn parse() {
+        let records = parse_csv("a,b\r\n\"x,\"\"y\"\"\",\nlast,\"two\nlines\"").unwrap();
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["x,\"y\"".to_string(), "".to_string()],
+                vec!["last".to_string(), "two\nlines".to_string()],
+            ]
+        );
+
+        assert!(matches!(parse_csv("\"open"), Err(Error::Csv(_))));
+    }
+
+    #[tokio::test]
+    async fn typed_headers_round_trip() {
+        let db = create_mem_db("csv_typed_headers_round_trip").await;
+        let source = db.create("foo").await;
+
+        source.create_column("zip", DataType::Text).await.unwrap();
+        source
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        source
+            .create_column("price", DataType::Decimal)
+            .await
+            .unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("zip".to_string(), "01234".to_string());
+        data.insert("count".to_string(), "3".to_string());
+        data.insert("price".to_string(), "1.10".to_string());
+        source.add_datapoint(data).await.unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("zip".to_string(), "a,b".to_string());
+        source.add_datapoint(data).await.unwrap();
+
+        let options = CsvOptions {
+            null: "\\N".to_string(),
+            typed_headers: true,
+        };
+        let csv = source.to_csv(&options).await.unwrap();
+        assert!(csv.starts_with("zip:text,count:integer,price:decimal\r\n"));
+
+        let target = db.create("bar").await;
+        assert_eq!(target.import_csv(&csv, &options).await.unwrap(), 2);
+
+        let types = |columns: Vec<crate::project::Column>| {
+            columns
+                .into_iter()
+                .map(|c| (c.name, c.column_type))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(types(target.get_all().await), types(source.get_all().await));
+        assert_eq!(target.to_csv(&options).await.unwrap(), csv);
+    }
+
+    #[tokio::test]
+    async fn failed_import_creates_no_columns() {
+        let db = create_mem_db("csv_failed_import_creates_no_columns").await;
+        let project = db.create("foo").await;
+        project.create_column("zip", DataType::Text).await.unwrap();
+
+        let options = CsvOptions {
+            typed_headers: true,
+            ..Default::default()
+        };
+        let csv = "zip:text,count:integer\r\n01234,3\r\n56789,many\r\n";
+        assert!(project.import_csv(csv, &options).await.is_err());
+
+        let columns = project.get_all().await;
+        assert_eq!(columns.len(), 1);
+        assert!(project.get_data().await.unwrap().is_empty());
+
+        // Only the header creates the columns of an empty import
+        let rows = project.import_csv("count:integer\r\n", &options).await;
+        assert_eq!(rows.unwrap(), 0);
+        assert_eq!(project.get_all().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn plain_headers_infer_types() {
+        let db = create_mem_db("csv_plain_headers_infer_types").await;
+        let project = db.create("foo").await;
+
+        let rows = project
+            .import_csv("zip,count\r\n01234,3\r\n", &CsvOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        let columns = project.get_all().await;
+        assert_eq!(columns[0].column_type, DataType::Integer);
+        assert_eq!(columns[1].column_type, DataType::Integer);
+    }
+
+    #[tokio::test]
+    async fn merged_export() {
+        let db = create_mem_db("csv_merged_export").await;
+
+        let add = |project: &crate::Project, values: &[(&str, &str)]| {
+            let data: HashMap<String, String> = values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            let project = project.clone();
+            async move { project.add_datapoint(data).await.unwrap() }
+        };
+
+        let a = db.create("a").await;
+        a.create_column("key", DataType::Integer).await.unwrap();
+        a.create_column("x", DataType::Text).await.unwrap();
+        add(&a, &[("key", "1"), ("x", "a1")]).await;
+        add(&a, &[("key", "2"), ("x", "a2")]).await;
+
+        let b = db.create("b").await;
+        b.create_column("key", DataType::Integer).await.unwrap();
+        b.create_column("y", DataType::Text).await.unwrap();
+        add(&b, &[("key", "2"), ("y", "b2")]).await;
+        add(&b, &[("key", "2"), ("y", "b2,again")]).await;
+        add(&b, &[("key", "3"), ("y", "b3")]).await;
+
+        // Has no column to join on, so its rows match nothing
+        let c = db.create("c").await;
+        add(&c, &[("z", "c1")]).await;
+
+        let projects = ["a".to_string(), "b".to_string(), "c".to_string()];
+        let mut csv = Vec::new();
+        db.merged_export(&projects, "key", &mut csv).await.unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "key,a.x,b.y,c.z");
+        let mut joined = lines[1..lines.len() - 1].to_vec();
+        joined.sort();
+        assert_eq!(
+            joined,
+            ["1,a1,,", "2,a2,\"b2,again\",", "2,a2,b2,", "3,,b3,"]
+        );
+        assert_eq!(lines[lines.len() - 1], ",,,c1");
+
+        let projects = ["a".to_string(), "missing".to_string()];
+        let mut csv = Vec::new();
+        match db.merged_export(&projects, "key", &mut csv).await {
+            Err(Error::ProjectNotFound(name)) => assert_eq!(name, "missing"),
+            other => panic!("Expected a missing project, got {:?}", other),
+        }
+    }
+}
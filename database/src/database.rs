@@ -1,16 +1,88 @@
+use std::{collections::HashMap, future::Future};
+
 use crate::{
-    project::{Project, RawProject},
-    utils::sql_encode,
+    cache::ProjectCache,
+    pool::{IsolationLevel, LOCKED_RETRY_DELAY, MAX_LOCKED_RETRIES, MAX_SERIALIZATION_RETRIES},
+    project::{
+        column_list, id_column_definition, timestamp_column_type, DataType, Project, RawProject,
+        DEFAULT_MAX_COLUMN_NAME_LEN, PROJECT_COLUMNS, UPDATED_AT_COLUMN,
+    },
+    utils::{encode_identifier, quote_identifier},
+    Backend, Error, PoolConfig,
 };
-use chrono::Utc;
-use sqlx::{migrate, AnyPool};
+use chrono::{DateTime, Utc};
+use sqlx::{migrate, migrate::Migrator, AnyConnection, AnyPool, Connection, Executor, FromRow};
+
+/// Tables used by the project index itself, no project can be stored under these names
+const RESERVED_TABLES: &[&str] = &["projects", "columns", "project_tokens", "_sqlx_migrations"];
+
+/// Migrations for the project index, run when the database is opened
+static MIGRATOR: Migrator = migrate!("./migrations");
+
+/// The same migrations written for Postgres, which has no rowid or AUTOINCREMENT
+static POSTGRES_MIGRATOR: Migrator = migrate!("./migrations_postgres");
+
+/// The same migrations written for MySQL, which has no rowid and only indexes text of a
+/// bounded length
+static MYSQL_MIGRATOR: Migrator = migrate!("./migrations_mysql");
 
 /// Database for holding all project data and metadata
 #[allow(unused)]
 #[derive(Debug, Clone)]
 pub struct Database {
     /// generic sqlx connection pool
-    pool: AnyPool,
+    pub(crate) pool: AnyPool,
+
+    /// Backend the pool is connected to
+    backend: Backend,
+
+    /// Isolation level set on every connection, only ever set for Postgres
+    isolation_level: Option<IsolationLevel>,
+
+    /// Longest encoded column name in bytes, passed on to every project
+    max_column_name_len: usize,
+
+    /// Size in bytes at which writes are rejected, passed on to every project
+    max_db_bytes: Option<u64>,
+
+    /// Largest raw value in bytes, passed on to every project
+    max_blob_bytes: Option<usize>,
+
+    /// Projects fetched by name, None if caching is disabled
+    cache: Option<ProjectCache>,
+
+    /// Check that the table of a project exists when it is fetched
+    verify_tables: bool,
+}
+
+/// Result of a SQLite WAL checkpoint, as reported by `PRAGMA wal_checkpoint`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Whether the checkpoint was blocked by another connection reading or writing
+    pub busy: bool,
+
+    /// Frames in the WAL file before the checkpoint, -1 if the database is not in WAL mode
+    pub log_frames: i64,
+
+    /// Frames moved back into the database file, -1 if the database is not in WAL mode
+    pub checkpointed_frames: i64,
+}
+
+/// State of a single bundled migration, see [Database::migration_status]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationInfo {
+    /// Version of the migration, the timestamp its file name starts with
+    pub version: i64,
+
+    /// Description of the migration, taken from its file name
+    pub description: String,
+
+    /// Whether the migration has been applied successfully
+    pub applied: bool,
+
+    /// Whether the applied migration matches the bundled one, None if it is not applied.
+    /// A mismatch means the migration file was changed after it ran.
+    pub checksum_valid: Option<bool>,
 }
 
 impl Database {
@@ -25,33 +97,395 @@ impl Database {
     /// ```rust
     /// # use database::Database;
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:new_database?mode=memory").await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn new(url: &str) -> Result<Database, sqlx::Error> {
+    pub async fn new(url: &str) -> Result<Database, Error> {
+        Database::with_options(url, PoolConfig::for_url(url)).await
+    }
+
+    /// Create a new database connection with custom pool settings.
+    ///
+    /// Like [Database::new] this installs all drivers and runs the migrations.
+    ///
+    /// # Arguments
+    /// * `url` Url to the database
+    /// * `config` Settings for the connection pool
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, PoolConfig};
+    /// # use std::time::Duration;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let url = "sqlite:file:with_options?mode=memory";
+    /// let config = PoolConfig {
+    ///     max_connections: 5,
+    ///     idle_timeout: Some(Duration::from_secs(60)),
+    ///     ..PoolConfig::for_url(url)
+    /// };
+    /// let db = Database::with_options(url, config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn with_options(url: &str, config: PoolConfig) -> Result<Database, Error> {
+        let backend = Backend::from_url(url)
+            .ok_or_else(|| sqlx::Error::Configuration("Unsupported database url".into()))?;
+
+        // SQLite has no session isolation level to set
+        let isolation_level = match backend {
+            Backend::Postgres => config.isolation_level,
+            _ => None,
+        };
+
         // Install all drivers and setup connection
         sqlx::any::install_default_drivers();
-        let pool = sqlx::pool::PoolOptions::new()
-            .max_connections(99)
-            .idle_timeout(None)
+        let pool = sqlx::pool::PoolOptions::<sqlx::Any>::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(config.acquire_timeout)
+            .idle_timeout(config.idle_timeout)
+            .after_connect(move |conn, _meta| {
+                Box::pin(async move {
+                    if let Some(level) = isolation_level {
+                        conn.execute(level.session_statement()).await?;
+                    }
+                    // Identifiers are quoted with double quotes, which MySQL reads as strings
+                    if backend == Backend::MySql {
+                        conn.execute(
+                            "SET SESSION sql_mode = CONCAT(@@SESSION.sql_mode, ',ANSI_QUOTES')",
+                        )
+                        .await?;
+                    }
+                    Ok(())
+                })
+            })
             .connect(url)
             .await?;
 
         // Run migrations
-        migrate!("./migrations").run(&pool).await?;
+        migrator(backend).run(&pool).await?;
+        Database::add_updated_at_columns(&pool, backend).await?;
+
+        Ok(Database {
+            pool,
+            backend,
+            isolation_level,
+            max_column_name_len: DEFAULT_MAX_COLUMN_NAME_LEN,
+            max_db_bytes: None,
+            max_blob_bytes: None,
+            cache: Some(ProjectCache::default()),
+            verify_tables: false,
+        })
+    }
+
+    /// Set the longest encoded column name in bytes, longer names are rejected with
+    /// [Error::ColumnNameTooLong] when creating or renaming a column
+    ///
+    /// Defaults to [DEFAULT_MAX_COLUMN_NAME_LEN], the limit of Postgres.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, Error, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:with_max_column_name_len?mode=memory")
+    ///     .await?
+    ///     .with_max_column_name_len(3);
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// project.create_column("bar", DataType::Text).await?;
+    /// let error = project.create_column("barbaz", DataType::Text).await;
+    /// assert!(matches!(error, Err(Error::ColumnNameTooLong { .. })));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_column_name_len(mut self, len: usize) -> Database {
+        self.max_column_name_len = len;
+        self.reset_cache();
+        self
+    }
+
+    /// Set the size in bytes at which the database stops accepting datapoints, writes are then
+    /// rejected with [Error::QuotaExceeded]
+    ///
+    /// Only SQLite databases are checked, the limit is ignored for other backends. `None`, the
+    /// default, disables the check.
+    pub fn with_max_db_bytes(mut self, max: Option<u64>) -> Database {
+        self.max_db_bytes = max;
+        self.reset_cache();
+        self
+    }
+
+    /// Set the largest value in bytes that can be stored in a raw column, larger values are
+    /// rejected with [Error::ValueTooLarge]
+    ///
+    /// The size is that of the value as it is stored. `None`, the default, allows values of any
+    /// size.
+    pub fn with_max_blob_bytes(mut self, max: Option<usize>) -> Database {
+        self.max_blob_bytes = max;
+        self.reset_cache();
+        self
+    }
+
+    /// Enable or disable the cache of projects used by [Database::get_project], along with the
+    /// cache of their columns used by [Project::get_columns]
+    ///
+    /// Cached projects are only invalidated by changes made through this process, so caching
+    /// should be disabled when other processes create, archive or delete projects, or change
+    /// their columns, in the same database. Enabled by default.
+    pub fn with_project_cache(mut self, enabled: bool) -> Database {
+        self.cache = enabled.then(ProjectCache::default);
+        self
+    }
+
+    /// Check that the table of a project exists whenever [Database::get_project] fetches it
+    ///
+    /// Catches projects whose encoded name no longer matches their table, like after editing
+    /// the database by hand, with [Error::SchemaMismatch] instead of failing on the first query
+    /// of the project. Costs an extra query for every project that is not cached. Disabled by
+    /// default.
+    pub fn with_table_verification(mut self, enabled: bool) -> Database {
+        self.verify_tables = enabled;
+        self
+    }
+
+    /// Forget all cached projects and columns, they are fetched from the database again on next use
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
+    /// Start over with an empty cache not shared with other clones, after changing settings
+    /// that are copied into every project
+    fn reset_cache(&mut self) {
+        if self.cache.is_some() {
+            self.cache = Some(ProjectCache::default());
+        }
+    }
+
+    /// Remove a project from the cache after changing it
+    fn invalidate(&self, name: &str) {
+        if let Some(cache) = &self.cache {
+            cache.remove(name);
+        }
+    }
+
+    /// Convert a RawProject to a Project using the settings of this database
+    fn to_project(&self, raw: RawProject) -> Option<Project> {
+        let mut project = Project::from_raw(raw, self.pool.clone())?;
+        project.max_column_name_len = self.max_column_name_len;
+        project.max_db_bytes = self.max_db_bytes;
+        project.max_blob_bytes = self.max_blob_bytes;
+        project.cache = self.cache.clone();
+        Some(project)
+    }
+
+    /// Check whether a database already has the fkit schema, without running the migrations
+    ///
+    /// Only reads from the database. SQLite files are opened read-only, unless the url already
+    /// sets a mode.
+    ///
+    /// # Arguments
+    /// * `url` Url to the database
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let url = "sqlite:file:schema_present?mode=memory&cache=shared";
+    /// let db = Database::new(url).await?;
+    ///
+    /// assert!(Database::schema_present(url).await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// Error if the database can not be connected to
+    pub async fn schema_present(url: &str) -> Result<bool, Error> {
+        let backend = Backend::from_url(url)
+            .ok_or_else(|| sqlx::Error::Configuration("Unsupported database url".into()))?;
+
+        let url = match backend {
+            Backend::Sqlite if !url.contains("mode=") => {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!("{}{}mode=ro", url, separator)
+            }
+            _ => url.to_string(),
+        };
+
+        sqlx::any::install_default_drivers();
+        let mut conn = AnyConnection::connect(&url).await?;
+        let tables: i64 = sqlx::query_scalar(&table_count_query(backend))
+            .bind("projects")
+            .fetch_one(&mut conn)
+            .await?;
+        conn.close().await?;
+
+        Ok(tables > 0)
+    }
+
+    /// Backend the database is connected to
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Close every connection of the pool, waiting for the ones in use to be returned
+    ///
+    /// Every clone of the database shares the pool, so none of them can be used afterwards.
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    /// Check that the database is reachable with a trivial query
+    ///
+    /// Unlike [Database::ready] this does not look at the migrations, so it only fails when the
+    /// database can not be reached at all.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:ping?mode=memory").await?;
+    /// db.ping().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn ping(&self) -> Result<(), Error> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Whether the database is reachable and every migration has been applied
+    ///
+    /// Opening a database already waits for the migrations, this can be used to check that
+    /// nothing has changed since, like another instance rolling back a migration.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:ready?mode=memory").await?;
+    /// assert!(db.ready().await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// Error if the database can not be reached
+    pub async fn ready(&self) -> Result<bool, Error> {
+        let applied: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(applied as usize >= migrator(self.backend).iter().count())
+    }
 
-        Ok(Database { pool })
+    /// Write the SQLite WAL back into the database file and truncate it
+    ///
+    /// Only SQLite has a WAL to checkpoint, other backends return None.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:checkpoint?mode=memory").await?;
+    ///
+    /// // In memory databases have no WAL
+    /// let checkpoint = db.checkpoint().await?.unwrap();
+    /// assert_eq!(checkpoint.log_frames, -1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn checkpoint(&self) -> Result<Option<Checkpoint>, Error> {
+        if self.backend != Backend::Sqlite {
+            return Ok(None);
+        }
+
+        let (busy, log_frames, checkpointed_frames): (i64, i64, i64) =
+            sqlx::query_as("PRAGMA wal_checkpoint(TRUNCATE)")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(Some(Checkpoint {
+            busy: busy != 0,
+            log_frames,
+            checkpointed_frames,
+        }))
+    }
+
+    /// State of every migration bundled with this version, oldest first
+    ///
+    /// Compares the bundled migrations against the ones recorded in the database, so a
+    /// migration that failed or was rolled back shows up as not applied.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:migration_status?mode=memory").await?;
+    ///
+    /// let migrations = db.migration_status().await?;
+    /// assert!(migrations.iter().all(|m| m.applied));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// Error if the database can not be reached
+    pub async fn migration_status(&self) -> Result<Vec<MigrationInfo>, Error> {
+        // Versions are timestamps too large for the generic driver to read from SQLite as
+        // integers, so they are selected as text
+        let text = match self.backend {
+            Backend::MySql => "CHAR",
+            Backend::Sqlite | Backend::Postgres => "TEXT",
+        };
+        let applied: Vec<(String, Vec<u8>)> = sqlx::query_as(&format!(
+            "SELECT CAST(version AS {}), checksum FROM _sqlx_migrations WHERE success",
+            text
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+        let applied: Vec<(i64, Vec<u8>)> = applied
+            .into_iter()
+            .filter_map(|(version, checksum)| Some((version.parse().ok()?, checksum)))
+            .collect();
+
+        Ok(migrator(self.backend)
+            .iter()
+            .map(|migration| {
+                let checksum = applied
+                    .iter()
+                    .find(|(version, _)| *version == migration.version)
+                    .map(|(_, checksum)| checksum);
+
+                MigrationInfo {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                    applied: checksum.is_some(),
+                    checksum_valid: checksum.map(|c| *c == *migration.checksum),
+                }
+            })
+            .collect())
     }
 
-    /// Get a list of all the projects in the database
+    /// Get a list of all the projects in the database, except archived ones
     ///
     /// # Examples
     /// ```
     /// # use database::Database;
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
     ///
     /// let projects = db.get_projects().await?;
@@ -67,21 +501,113 @@ impl Database {
     /// ```
     ///
     /// # Returns
-    /// [Project]s or sqlx error if the query failed
-    pub async fn get_projects(&self) -> Result<Vec<Project>, sqlx::Error> {
+    /// [Project]s or an error if the query failed
+    pub async fn get_projects(&self) -> Result<Vec<Project>, Error> {
+        self.find_projects(false).await
+    }
+
+    /// Get a list of all the projects in the database, including archived ones if asked for
+    ///
+    /// # Arguments
+    /// * `include_archived` - Also return projects archived with [Database::archive_project]
+    pub async fn find_projects(&self, include_archived: bool) -> Result<Vec<Project>, Error> {
         // Fetch and deserialize
-        let projects: Vec<RawProject> = sqlx::query_as("SELECT * FROM projects")
-            .fetch_all(&self.pool)
-            .await?;
+        let projects: Vec<RawProject> = sqlx::query_as(&self.backend.sql(&format!(
+            "SELECT {PROJECT_COLUMNS} FROM projects WHERE deleted_at IS NULL OR ?"
+        )))
+        .bind(include_archived)
+        .fetch_all(&self.pool)
+        .await?;
 
         // Convert from Raw to actual project
         Ok(projects
             .into_iter()
-            .map(|p| Project::from_raw(p, self.pool.clone()).expect("project should be valid"))
+            .map(|p| self.to_project(p).expect("project should be valid"))
+            .collect())
+    }
+
+    /// Get all projects without any activity since the given time
+    ///
+    /// A project is active when it was created or received a datapoint, projects without any
+    /// datapoints are judged by their creation time. Archived projects are never returned.
+    ///
+    /// # Arguments
+    /// * `older_than` - Projects last active before this time are returned
+    ///
+    /// # Examples
+    /// ```
+    /// # use database::Database;
+    /// # use chrono::{Duration, Utc};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
+    /// db.create_project("foo").await?;
+    ///
+    /// let stale = db.get_stale_projects(Utc::now() - Duration::days(1)).await?;
+    /// assert_eq!(stale.len(), 0);
+    ///
+    /// let stale = db.get_stale_projects(Utc::now() + Duration::days(1)).await?;
+    /// assert_eq!(stale.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_stale_projects(
+        &self,
+        older_than: DateTime<Utc>,
+    ) -> Result<Vec<Project>, Error> {
+        let projects: Vec<RawProject> = sqlx::query_as(&self.backend.sql(&format!(
+            r#"
+            SELECT {PROJECT_COLUMNS} FROM projects
+            WHERE COALESCE(last_datapoint_at, created_at) < ? AND deleted_at IS NULL
+            "#,
+        )))
+        .bind(older_than.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(projects
+            .into_iter()
+            .map(|p| self.to_project(p).expect("project should be valid"))
             .collect())
     }
 
-    /// Get a specific project by name
+    /// Names of all projects with a column of the given name, sorted by name
+    ///
+    /// Only reads the column index, none of the project tables. Archived projects are left out.
+    ///
+    /// # Examples
+    /// ```
+    /// # use database::{Database, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:projects_with_column?mode=memory").await?;
+    /// let foo = db.create_project("foo").await?;
+    /// db.create_project("bar").await?;
+    ///
+    /// foo.create_column("temperature", DataType::Float).await?;
+    ///
+    /// let projects = db.projects_with_column("temperature").await?;
+    /// assert_eq!(projects, vec!["foo".to_string()]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn projects_with_column(&self, column: &str) -> Result<Vec<String>, Error> {
+        let names = sqlx::query_scalar(&self.backend.sql(
+            r#"
+            SELECT DISTINCT projects.name FROM projects
+            JOIN columns ON columns.project_id = projects.id
+            WHERE columns.name = ? AND projects.deleted_at IS NULL
+            ORDER BY projects.name
+            "#,
+        ))
+        .bind(column)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(names)
+    }
+
+    /// Get a specific project by name, archived projects are treated as missing
     ///
     /// # Arguments
     /// * `name` - Name of the project to fetch
@@ -90,7 +616,7 @@ impl Database {
     /// ```
     /// # use database::Database;
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
     ///
     /// db.create_project("foo").await?;
@@ -104,7 +630,7 @@ impl Database {
     /// ```
     /// # use database::Database;
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
     /// let project = db.get_project("foo").await?;
     ///
@@ -116,98 +642,1258 @@ impl Database {
     /// # Returns
     /// [Project] or None if the project does not exist
     /// Error if the query failed
-    pub async fn get_project(&self, name: &str) -> Result<Option<Project>, sqlx::Error> {
-        // Fetch and deserialize
-        let project: RawProject = match sqlx::query_as("SELECT * FROM projects WHERE name = ?")
-            .bind(name)
+    pub async fn get_project(&self, name: &str) -> Result<Option<Project>, Error> {
+        if let Some(project) = self.cache.as_ref().and_then(|cache| cache.get(name)) {
+            return Ok(Some(project));
+        }
+
+        let project = self.find_project(name, false).await?;
+        if let Some(project) = &project {
+            if self.verify_tables {
+                self.verify_table(project).await?;
+            }
+            if let Some(cache) = &self.cache {
+                cache.insert(project);
+            }
+        }
+        Ok(project)
+    }
+
+    /// Fail with [Error::SchemaMismatch] if the table of the project does not exist
+    async fn verify_table(&self, project: &Project) -> Result<(), Error> {
+        let tables: i64 = sqlx::query_scalar(&table_count_query(self.backend))
+            .bind(&project.encoded)
             .fetch_one(&self.pool)
-            .await
-        {
-            Err(sqlx::Error::RowNotFound) => return Ok(None),
-            Err(e) => return Err(e),
-            Ok(p) => p,
-        };
+            .await?;
+
+        match tables {
+            0 => Err(Error::SchemaMismatch {
+                name: project.name.clone(),
+                encoded: project.encoded.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get a specific project by name, including archived projects if asked for
+    ///
+    /// # Arguments
+    /// * `name` - Name of the project to fetch
+    /// * `include_archived` - Also return the project if it is archived
+    pub async fn find_project(
+        &self,
+        name: &str,
+        include_archived: bool,
+    ) -> Result<Option<Project>, Error> {
+        let mut conn = self.pool.acquire().await?;
+        let project = Self::select_project(&mut conn, name, include_archived, self.backend).await?;
 
         // Convert from Raw to actual project
-        Ok(Project::from_raw(project, self.pool.clone()))
+        Ok(project.and_then(|project| self.to_project(project)))
+    }
+
+    /// Fetch the row of a project by name using the given connection
+    async fn select_project(
+        conn: &mut AnyConnection,
+        name: &str,
+        include_archived: bool,
+        backend: Backend,
+    ) -> Result<Option<RawProject>, sqlx::Error> {
+        // Fetch and deserialize
+        match sqlx::query_as(&backend.sql(&format!(
+            "SELECT {PROJECT_COLUMNS} FROM projects WHERE name = ? AND (deleted_at IS NULL OR ?)"
+        )))
+        .bind(name)
+        .bind(include_archived)
+        .fetch_one(conn)
+        .await
+        {
+            Err(sqlx::Error::RowNotFound) => Ok(None),
+            result => result.map(Some),
+        }
     }
 
     /// Create a new project
     ///
     /// # Examples
     /// ```rust
-    /// # use database::Database;
+    /// # use database::{Database, Error};
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
     /// let project = db.create_project("foo").await?;
     ///
     /// assert_eq!(project.name, "foo");
     /// assert_eq!(project.encoded, "foo");
+    ///
+    /// assert!(matches!(db.create_project("foo").await, Err(Error::ProjectExists(_))));
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn create_project(&self, name: &str) -> Result<Project, sqlx::Error> {
-        // Encode the name
-        let encoded = sql_encode(name).unwrap_or_else(|e| e);
-
-        // Create table
-        self.create_project_table(&encoded).await?;
-
-        // Insert the project
-        let now = Utc::now().timestamp();
-        let project = self.insert_project(name, &encoded, now).await?;
-
-        // Convert from Raw to actual project
-        Ok(Project::from_raw(project, self.pool.clone()).unwrap())
+    ///
+    /// # Returns
+    /// [Error::ProjectExists] if a project with the name, or one that encodes to the same
+    /// identifier, already exists. [Error::EmptyEncodedName] or [Error::Reserved] if the name
+    /// can not be used for a table
+    pub async fn create_project(&self, name: &str) -> Result<Project, Error> {
+        self.invalidate(name);
+        match self.try_create_project(name, &[]).await {
+            Err(e) if is_unique_violation(&e) => Err(Error::ProjectExists(name.to_string())),
+            result => result,
+        }
     }
 
-    /// Creates a base table with a given name. Name is not sanitized so please do that before
-    /// calling the function.
-    async fn create_project_table(&self, encoded_name: &str) -> Result<(), sqlx::Error> {
-        sqlx::query(&format!(
-            "CREATE TABLE {} (__timestamp__ INTEGER NOT NULL);",
-            encoded_name
-        ))
-        .execute(&self.pool)
-        .await?;
+    /// Create a new project with the given columns, created in the order they are listed
+    ///
+    /// Columns are returned by [Project::get_columns] in creation order, so this fixes the
+    /// order of exports like CSV. The project and its columns are created in one transaction, if
+    /// a column can not be created there is no project either. MySQL implicitly commits every
+    /// `ALTER TABLE`, so there a failed column leaves the project and the columns before it.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:create_project_with_columns?mode=memory").await?;
+    /// let columns = [
+    ///     ("zeta".to_string(), DataType::Integer),
+    ///     ("alpha".to_string(), DataType::Text),
+    /// ];
+    /// let project = db.create_project_with_columns("foo", &columns).await?;
+    ///
+    /// let names: Vec<String> = project.get_columns().await?.into_iter().map(|c| c.name).collect();
+    /// assert_eq!(names, ["zeta", "alpha"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The same errors as [Database::create_project] and [Project::create_column]
+    pub async fn create_project_with_columns(
+        &self,
+        name: &str,
+        columns: &[(String, DataType)],
+    ) -> Result<Project, Error> {
+        self.invalidate(name);
+        match self.try_create_project(name, columns).await {
+            Err(e) if is_unique_violation(&e) => Err(Error::ProjectExists(name.to_string())),
+            result => result,
+        }
+    }
+
+    /// Create a new project with the given columns, failing with the unique violation of the
+    /// database if it exists
+    #[tracing::instrument(level = "debug", skip(self, columns))]
+    async fn try_create_project(
+        &self,
+        name: &str,
+        columns: &[(String, DataType)],
+    ) -> Result<Project, Error> {
+        // Encode the name
+        let encoded = encode_project_name(name)?;
+
+        // Insert the project before creating its table, so a concurrent create of the same
+        // project fails on the unique name instead of on an existing table
+        let now = Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        let project = Self::insert_project(&mut tx, name, &encoded, now, self.backend).await?;
+
+        // Create table
+        Self::create_project_table(&mut tx, &encoded, self.backend).await?;
+
+        // Convert from Raw to actual project
+        let project = self.to_project(project).unwrap();
+        if let Err(e) = project.create_columns_on(&mut tx, columns).await {
+            tx.rollback().await?;
+            return Err(e);
+        }
+        tx.commit().await?;
+
+        tracing::debug!(encoded, id = project.id, "created project");
+
+        Ok(project)
+    }
+
+    /// Get a project by name, creating it if it does not exist yet
+    ///
+    /// Safe to call concurrently for the same name, if another caller creates the project first
+    /// the existing project is fetched and returned instead.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
+    ///
+    /// let created = db.get_or_create_project("foo").await?;
+    /// let fetched = db.get_or_create_project("foo").await?;
+    ///
+    /// assert_eq!(created.id, fetched.id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_or_create_project(&self, name: &str) -> Result<Project, Error> {
+        retry_locked(|| self.try_get_or_create_project(name)).await
+    }
+
+    /// A single attempt of [Database::get_or_create_project]
+    async fn try_get_or_create_project(&self, name: &str) -> Result<Project, Error> {
+        if let Some(project) = self.get_project(name).await? {
+            return Ok(project);
+        }
+
+        match self
+            .retry_serialization(|| self.try_create_project(name, &[]))
+            .await
+        {
+            Ok(project) => Ok(project),
+
+            // Someone else created the project between the fetch and the insert
+            Err(e) if is_unique_violation(&e) => self
+                .get_project(name)
+                .await?
+                .ok_or_else(|| Error::ProjectNotFound(name.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Adds many datapoints to a project in a single transaction, which also creates the
+    /// project and any missing columns
+    ///
+    /// Unlike [Project::add_datapoints], which creates the project and columns before inserting
+    /// the rows, either all of it is written or nothing is. A batch that fails leaves no empty
+    /// project or unused columns behind. This relies on the backend supporting schema changes
+    /// in transactions, MySQL commits them right away. The project is looked up in the same
+    /// transaction, if another caller creates it or one of its columns in the meantime the
+    /// batch is tried once more.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:add_datapoints_atomic?mode=memory").await?;
+    ///
+    /// let rows = vec![
+    ///     HashMap::from([("count".to_string(), "1".to_string())]),
+    ///     HashMap::from([("count".to_string(), "many".to_string())]),
+    /// ];
+    /// assert!(db.add_datapoints_atomic("foo", &rows).await.is_err());
+    /// assert!(db.get_project("foo").await?.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The number of inserted rows, or the same errors as [Project::add_datapoints]
+    pub async fn add_datapoints_atomic(
+        &self,
+        name: &str,
+        rows: &[HashMap<String, String>],
+    ) -> Result<usize, Error> {
+        retry_locked(|| async {
+            match self.try_add_datapoints_atomic(name, rows).await {
+                // Someone else created the project or a column between the lookup and the
+                // insert, the second attempt finds them
+                Err(e) if is_unique_violation(&e) => {
+                    self.try_add_datapoints_atomic(name, rows).await
+                }
+                result => result,
+            }
+        })
+        .await
+    }
+
+    /// A single attempt of [Database::add_datapoints_atomic]
+    async fn try_add_datapoints_atomic(
+        &self,
+        name: &str,
+        rows: &[HashMap<String, String>],
+    ) -> Result<usize, Error> {
+        let mut tx = self.pool.begin().await?;
+        let written = async {
+            let existing = Self::select_project(&mut tx, name, false, self.backend).await?;
+            let (project, columns, frozen) = match existing {
+                Some(raw) => {
+                    let project = self
+                        .to_project(raw)
+                        .ok_or_else(|| Error::ProjectNotFound(name.to_string()))?;
+                    if self.verify_tables {
+                        self.verify_table(&project).await?;
+                    }
+                    let columns = project.fetch_columns_on(&mut tx).await?;
+                    let frozen = project.is_schema_frozen_on(&mut tx).await?;
+                    (project, columns, frozen)
+                }
+                None => {
+                    let encoded = encode_project_name(name)?;
+                    let now = Utc::now().timestamp();
+                    let raw =
+                        Self::insert_project(&mut tx, name, &encoded, now, self.backend).await?;
+                    Self::create_project_table(&mut tx, &encoded, self.backend).await?;
+                    let project = self
+                        .to_project(raw)
+                        .ok_or_else(|| Error::ProjectNotFound(name.to_string()))?;
+                    (project, Vec::new(), false)
+                }
+            };
+
+            let inserted = project
+                .write_datapoints_on(&mut tx, rows, columns, frozen)
+                .await?;
+            Ok::<_, Error>((project, inserted))
+        }
+        .await;
+
+        // Roll back right away, a dropped transaction keeps SQLite's schema locked until the
+        // connection is used again
+        let (project, inserted) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                tx.rollback().await?;
+                return Err(e);
+            }
+        };
+        tx.commit().await?;
+
+        self.invalidate(name);
+        project.invalidate_columns();
+
+        Ok(inserted)
+    }
+
+    /// Delete a project along with its table, columns and all of its data
+    ///
+    /// Everything is deleted in a single transaction. Archived projects can be deleted as well,
+    /// and tokens for the project are revoked, see [Database::create_project_token].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:delete_project?mode=memory").await?;
+    /// db.create_project("foo").await?;
+    ///
+    /// assert!(db.delete_project("foo").await?);
+    /// assert!(!db.delete_project("foo").await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// true if the project was deleted, false if it does not exist
+    pub async fn delete_project(&self, name: &str) -> Result<bool, Error> {
+        let project = match self.find_project(name, true).await? {
+            Some(project) => project,
+            None => return Ok(false),
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&format!("DROP TABLE {}", project.table()))
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.backend.sql("DELETE FROM columns WHERE project_id = ?"))
+            .bind(project.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(&self.backend.sql("DELETE FROM projects WHERE id = ?"))
+            .bind(project.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            &self
+                .backend
+                .sql("DELETE FROM project_tokens WHERE project_name = ?"),
+        )
+        .bind(&project.name)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.invalidate(name);
+        if let Some(cache) = &self.cache {
+            cache.remove_columns(project.id);
+        }
+
+        Ok(true)
+    }
+
+    /// Archive a project, hiding it without deleting any of its data
+    ///
+    /// Archived projects are left out by [Database::get_project] and [Database::get_projects]
+    /// until they are brought back with [Database::restore_project]. The name stays taken.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:archive_project?mode=memory").await?;
+    /// db.create_project("foo").await?;
+    ///
+    /// db.archive_project("foo").await?;
+    /// assert!(db.get_project("foo").await?.is_none());
+    ///
+    /// db.restore_project("foo").await?;
+    /// assert!(db.get_project("foo").await?.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The archived [Project] or None if there is no active project with the name
+    pub async fn archive_project(&self, name: &str) -> Result<Option<Project>, Error> {
+        let result = sqlx::query(
+            &self
+                .backend
+                .sql("UPDATE projects SET deleted_at = ? WHERE name = ? AND deleted_at IS NULL"),
+        )
+        .bind(Utc::now().timestamp())
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        self.invalidate(name);
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        self.find_project(name, true).await
+    }
+
+    /// Restore a project archived with [Database::archive_project]
+    ///
+    /// # Returns
+    /// The restored [Project] or None if there is no archived project with the name
+    pub async fn restore_project(&self, name: &str) -> Result<Option<Project>, Error> {
+        let result = sqlx::query(&self.backend.sql(
+            "UPDATE projects SET deleted_at = NULL WHERE name = ? AND deleted_at IS NOT NULL",
+        ))
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        self.invalidate(name);
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        self.get_project(name).await
+    }
+
+    /// Freeze the schema of a project, so no more columns can be added to it
+    ///
+    /// Datapoints can still be added to the existing columns, new keys are rejected with
+    /// [crate::Error::SchemaFrozen].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:freeze_schema?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let frozen = db.freeze_schema("foo").await?.unwrap();
+    /// assert!(frozen.schema_frozen);
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// assert!(project.add_datapoint(data).await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The updated [Project] or None if it does not exist
+    pub async fn freeze_schema(&self, name: &str) -> Result<Option<Project>, Error> {
+        self.set_schema_frozen(name, true).await
+    }
+
+    /// Freeze or unfreeze the schema of a project, see [Database::freeze_schema]
+    ///
+    /// # Returns
+    /// The updated [Project] or None if it does not exist
+    pub async fn set_schema_frozen(
+        &self,
+        name: &str,
+        frozen: bool,
+    ) -> Result<Option<Project>, Error> {
+        let result = sqlx::query(
+            &self
+                .backend
+                .sql("UPDATE projects SET schema_frozen = ? WHERE name = ?"),
+        )
+        .bind(frozen as i64)
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
+        self.invalidate(name);
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        self.get_project(name).await
+    }
+
+    /// Create a copy of a project with all of its columns and datapoints
+    ///
+    /// Everything is copied in a single transaction, so the new project either has all data or
+    /// does not exist at all.
+    ///
+    /// # Arguments
+    /// * `source` - Name of the project to copy
+    /// * `new_name` - Name of the new project
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, Error};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:foo?mode=memory").await?;
+    /// db.create_project("foo").await?;
+    ///
+    /// let copy = db.duplicate_project("foo", "bar").await?;
+    /// assert_eq!(copy.name, "bar");
+    ///
+    /// let missing = db.duplicate_project("baz", "qux").await;
+    /// assert!(matches!(missing, Err(Error::ProjectNotFound(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The new [Project]. [Error::ProjectNotFound] if the source project does not exist and
+    /// [Error::ProjectExists] if the new name is already taken
+    pub async fn duplicate_project(&self, source: &str, new_name: &str) -> Result<Project, Error> {
+        match self
+            .retry_serialization(|| self.try_duplicate_project(source, new_name))
+            .await
+        {
+            Err(e) if is_unique_violation(&e) => Err(Error::ProjectExists(new_name.to_string())),
+            result => result,
+        }
+    }
+
+    /// Single attempt at [Database::duplicate_project]
+    async fn try_duplicate_project(&self, source: &str, new_name: &str) -> Result<Project, Error> {
+        let source = self
+            .get_project(source)
+            .await?
+            .ok_or_else(|| Error::ProjectNotFound(source.to_string()))?;
+        let columns = source.get_columns().await?;
+
+        let encoded = encode_project_name(new_name)?;
+        let now = Utc::now().timestamp();
+
+        let mut tx = self.pool.begin().await?;
+        let project = Self::insert_project(&mut tx, new_name, &encoded, now, self.backend).await?;
+        Self::create_project_table(&mut tx, &encoded, self.backend).await?;
+
+        // Copy the schema
+        for column in &columns {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                quote_identifier(&encoded),
+                column.quoted(),
+                column.column_type.to_sql_for(self.backend)
+            ))
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(&self.backend.sql(
+                r#"
+                INSERT INTO columns
+                    (project_id, name, encoded, column_type, created_at)
+                VALUES
+                    (?, ?, ?, ?, ?)
+                "#,
+            ))
+            .bind(project.id)
+            .bind(&column.name)
+            .bind(&column.encoded)
+            .bind(column.column_type.to_sql())
+            .bind(column.created_at.timestamp())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Copy the data
+        let names = column_list(&columns);
+        sqlx::query(&format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {}",
+            quote_identifier(&encoded),
+            names,
+            names,
+            source.table()
+        ))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(self.to_project(project).expect("project should be valid"))
+    }
+
+    /// Run an operation, retrying it when a serializable transaction fails to commit
+    ///
+    /// Postgres aborts serializable transactions that conflict with a concurrent one, those are
+    /// safe to retry from the start. Without a serializable isolation level the operation is
+    /// only run once.
+    async fn retry_serialization<T, F, Fut>(&self, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let retry = self.isolation_level == Some(IsolationLevel::Serializable);
+        let mut attempts = 0;
+
+        loop {
+            match operation().await {
+                Err(e)
+                    if retry
+                        && attempts < MAX_SERIALIZATION_RETRIES
+                        && is_serialization_failure(&e) =>
+                {
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Creates a base table with a given name. Name is not sanitized so please do that before
+    /// calling the function.
+    async fn create_project_table(
+        conn: &mut AnyConnection,
+        encoded_name: &str,
+        backend: Backend,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(&format!(
+            "CREATE TABLE {} ({}, __timestamp__ {} NOT NULL, {} BIGINT);",
+            quote_identifier(encoded_name),
+            id_column_definition(backend),
+            timestamp_column_type(backend),
+            UPDATED_AT_COLUMN
+        ))
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Add the `__updated_at__` column to project tables created before it existed
+    ///
+    /// Project tables are not covered by the migrations, so they are checked every time the
+    /// database is opened. Datapoints in the old tables keep a NULL change time, which is read as
+    /// the time they were added.
+    async fn add_updated_at_columns(pool: &AnyPool, backend: Backend) -> Result<(), Error> {
+        let tables: Vec<String> = sqlx::query_scalar("SELECT encoded_name FROM projects")
+            .fetch_all(pool)
+            .await?;
+
+        for table in tables {
+            let exists: i64 = sqlx::query_scalar(&table_count_query(backend))
+                .bind(&table)
+                .fetch_one(pool)
+                .await?;
+            let columns: i64 = sqlx::query_scalar(&column_count_query(backend))
+                .bind(&table)
+                .bind(UPDATED_AT_COLUMN)
+                .fetch_one(pool)
+                .await?;
+
+            if exists > 0 && columns == 0 {
+                sqlx::query(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} BIGINT",
+                    quote_identifier(&table),
+                    UPDATED_AT_COLUMN
+                ))
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Insert a project inte the index.
+    ///
+    /// MySQL has no `RETURNING`, there the new row is selected again by its id, which only the
+    /// inserting connection knows.
+    async fn insert_project(
+        conn: &mut AnyConnection,
+        name: &str,
+        encoded: &str,
+        timestamp: i64,
+        backend: Backend,
+    ) -> Result<RawProject, sqlx::Error> {
+        let returning = match backend {
+            Backend::MySql => String::new(),
+            _ => format!("RETURNING {PROJECT_COLUMNS}"),
+        };
+        let query = backend.sql(&format!(
+            r#"
+            INSERT INTO
+                projects
+                (name, encoded_name, created_at)
+            VALUES
+                (?, ?, ?)
+            {returning}
+            "#,
+        ));
+        let insert = sqlx::query(&query).bind(name).bind(encoded).bind(timestamp);
+
+        let row = match backend {
+            Backend::MySql => {
+                insert.execute(&mut *conn).await?;
+                sqlx::query(&format!(
+                    "SELECT {PROJECT_COLUMNS} FROM projects WHERE id = LAST_INSERT_ID()"
+                ))
+                .fetch_one(conn)
+                .await?
+            }
+            _ => insert.fetch_one(conn).await?,
+        };
+
+        RawProject::from_row(&row)
+    }
+}
+
+/// Query counting the tables with the name bound to it, 1 if the table exists
+fn table_count_query(backend: Backend) -> String {
+    backend.sql(match backend {
+        Backend::Sqlite => "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+        Backend::Postgres => {
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = ?"
+        }
+        Backend::MySql => {
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = ?"
+        }
+    })
+}
+
+/// Query counting the columns of a table with the name bound to it, 1 if the column exists
+fn column_count_query(backend: Backend) -> String {
+    backend.sql(match backend {
+        Backend::Sqlite => "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+        Backend::Postgres => {
+            "SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = ? AND column_name = ?"
+        }
+        Backend::MySql => {
+            "SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = ? AND column_name = ?"
+        }
+    })
+}
+
+/// Migrations for the project index on the given backend
+fn migrator(backend: Backend) -> &'static Migrator {
+    match backend {
+        Backend::Sqlite => &MIGRATOR,
+        Backend::Postgres => &POSTGRES_MIGRATOR,
+        Backend::MySql => &MYSQL_MIGRATOR,
+    }
+}
+
+/// Whether an error is Postgres aborting a transaction because of a serialization conflict
+fn is_serialization_failure(error: &Error) -> bool {
+    match error {
+        Error::Sqlx(sqlx::Error::Database(e)) => e.code().as_deref() == Some("40001"),
+        _ => false,
+    }
+}
+
+/// Runs an operation, retrying it while SQLite is locked by another connection
+///
+/// Creating a table or column locks the whole schema of a shared in-memory database, so
+/// concurrent writes fail until it is done. The operation has to be safe to run again after a
+/// failed attempt, like a single transaction.
+pub(crate) async fn retry_locked<T, F, Fut>(mut operation: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut attempts = 0;
+
+    loop {
+        match operation().await {
+            Err(e) if attempts < MAX_LOCKED_RETRIES && is_locked(&e) => {
+                attempts += 1;
+                tokio::time::sleep(LOCKED_RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether an error is SQLite being busy or locked by another connection
+///
+/// Includes SQLITE_LOCKED_SHAREDCACHE, which shared in-memory databases return while another
+/// connection changes the schema.
+fn is_locked(error: &Error) -> bool {
+    match error {
+        Error::Sqlx(sqlx::Error::Database(e)) => {
+            matches!(e.code().as_deref(), Some("5" | "6" | "262"))
+        }
+        _ => false,
+    }
+}
+
+/// Whether an error is the violation of a unique constraint, like a taken project name
+fn is_unique_violation(error: &Error) -> bool {
+    match error {
+        Error::Sqlx(sqlx::Error::Database(e)) => e.is_unique_violation(),
+        _ => false,
+    }
+}
+
+/// Encode a project name, rejecting names that would collide with the tables of the index
+pub(crate) fn encode_project_name(name: &str) -> Result<String, Error> {
+    let encoded = encode_identifier(name)?;
+    if RESERVED_TABLES
+        .iter()
+        .any(|table| table.eq_ignore_ascii_case(&encoded))
+    {
+        return Err(Error::Reserved(name.to_string()));
+    }
+
+    Ok(encoded)
+}
+
+#[cfg(test)]
+pub mod methods {
+    use std::{collections::HashMap, time::Duration};
+
+    use chrono::{TimeDelta, Utc};
+
+    use sqlx::Row;
+
+    use crate::{
+        pool::IsolationLevel,
+        project::{DataType, Project, DEFAULT_MAX_COLUMN_NAME_LEN},
+        Database, Error, PoolConfig,
+    };
+
+    #[tokio::test]
+    async fn create_memory_database() {
+        create_mem_db("create_db").await;
+    }
+
+    #[tokio::test]
+    async fn ready() {
+        let db = create_mem_db("ready").await;
+        assert!(db.ready().await.unwrap());
+
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert!(!db.ready().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn migration_status() {
+        let db = create_mem_db("migration_status").await;
+
+        let migrations = db.migration_status().await.unwrap();
+        assert_eq!(migrations.len(), super::MIGRATOR.iter().count());
+        assert!(migrations.windows(2).all(|m| m[0].version < m[1].version));
+        for migration in &migrations {
+            assert!(migration.applied, "{} not applied", migration.description);
+            assert_eq!(migration.checksum_valid, Some(true));
+        }
+
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = X'00' WHERE version = (SELECT MIN(version) FROM _sqlx_migrations)")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let migrations = db.migration_status().await.unwrap();
+        let last = migrations.last().unwrap();
+        assert!(!last.applied);
+        assert_eq!(last.checksum_valid, None);
+        assert_eq!(migrations[0].checksum_valid, Some(false));
+    }
+
+    #[tokio::test]
+    async fn add_updated_at_columns() {
+        let url = "sqlite:file:add_updated_at_columns?mode=memory&cache=shared";
+        let db = Database::new(url).await.unwrap();
+        let project = db.create("foo").await;
+
+        // A project table from before the column existed
+        sqlx::query("ALTER TABLE foo DROP COLUMN __updated_at__")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let reopened = Database::new(url).await.unwrap();
+        let project = reopened.get_project(&project.name).await.unwrap().unwrap();
+
+        let data = HashMap::from([("bar".to_string(), "baz".to_string())]);
+        let added = project.add_datapoint(data).await.unwrap();
+        assert_eq!(project.changes_since(added).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn schema_present() {
+        let url = "sqlite:file:schema_present_fresh?mode=memory&cache=shared";
+
+        // Keeps the shared in-memory database alive between connections
+        sqlx::any::install_default_drivers();
+        let _fresh = sqlx::AnyPool::connect(url).await.unwrap();
+        assert!(!Database::schema_present(url).await.unwrap());
+
+        let url = "sqlite:file:schema_present_migrated?mode=memory&cache=shared";
+        let _db = Database::new(url).await.unwrap();
+        assert!(Database::schema_present(url).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn idle_timeout_applied() {
+        let url = "sqlite:file:idle_timeout?mode=memory";
+        let config = PoolConfig {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..PoolConfig::for_url(url)
+        };
+        let db = Database::with_options(url, config)
+            .await
+            .expect("Database should be created");
+
+        assert_eq!(
+            db.pool.options().get_idle_timeout(),
+            Some(Duration::from_millis(50))
+        );
+    }
+
+    #[tokio::test]
+    async fn pool_size_applied() {
+        let url = "sqlite:file:pool_size?mode=memory";
+        let config = PoolConfig {
+            max_connections: 4,
+            min_connections: 2,
+            acquire_timeout: Duration::from_secs(5),
+            ..PoolConfig::for_url(url)
+        };
+        let db = Database::with_options(url, config)
+            .await
+            .expect("Database should be created");
+
+        let options = db.pool.options();
+        assert_eq!(options.get_max_connections(), 4);
+        assert_eq!(options.get_min_connections(), 2);
+        assert_eq!(options.get_acquire_timeout(), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn sqlite_ignores_isolation_level() {
+        let url = "sqlite:file:sqlite_isolation?mode=memory";
+        let config = PoolConfig {
+            isolation_level: Some(IsolationLevel::Serializable),
+            ..PoolConfig::for_url(url)
+        };
+        let db = Database::with_options(url, config)
+            .await
+            .expect("Database should be created");
+
+        assert_eq!(db.isolation_level, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Postgres database in FKIT_TEST_POSTGRES_URL"]
+    async fn postgres_isolation_level() {
+        let url = postgres_url();
+
+        let config = PoolConfig {
+            isolation_level: Some(IsolationLevel::Serializable),
+            ..PoolConfig::for_url(&url)
+        };
+        let db = Database::with_options(&url, config)
+            .await
+            .expect("Database should be created");
+
+        let row = sqlx::query("SHOW transaction_isolation")
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+        let level: String = row.try_get(0).unwrap();
+
+        assert_eq!(level, IsolationLevel::Serializable.as_postgres_str());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Postgres database in FKIT_TEST_POSTGRES_URL"]
+    async fn postgres_round_trip() {
+        let url = postgres_url();
 
-        Ok(())
+        let db = Database::new(&url)
+            .await
+            .expect("Database should be created");
+        assert!(db.ready().await.unwrap());
+
+        // Upper case letters and keywords only work when identifiers are quoted
+        let name = format!("Select_{}", std::process::id());
+        let project = db.create(&name).await;
+
+        project
+            .create_column("Order", DataType::Integer)
+            .await
+            .unwrap();
+        project
+            .create_column("price", DataType::Decimal)
+            .await
+            .unwrap();
+
+        let data = HashMap::from([
+            ("Order".to_string(), "5000000000".to_string()),
+            ("price".to_string(), "1.25".to_string()),
+            ("note".to_string(), "first".to_string()),
+        ]);
+        project.add_datapoint_typed(data).await.unwrap();
+
+        let columns: Vec<String> = project
+            .get_columns()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(columns, ["Order", "price", "note"]);
+
+        let (id, data) = project.get_data_with_ids().await.unwrap().remove(0);
+        assert_eq!(data["Order"], Some("5000000000".to_string()));
+        assert_eq!(data["price"], Some("1.25".to_string()));
+
+        let data = HashMap::from([("note".to_string(), "second".to_string())]);
+        project.update_datapoint(id, data).await.unwrap();
+        let changes = project
+            .changes_since(Utc::now() - TimeDelta::try_hours(1).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(changes[0].values["note"], Some("second".to_string()));
+
+        assert!(project
+            .rename_column("note", "Group")
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            project.get_data().await.unwrap()[0]["Group"],
+            Some("second".to_string())
+        );
+
+        assert!(project.delete_datapoint(id).await.unwrap());
+        assert!(db.delete_project(&name).await.unwrap());
     }
 
-    /// Insert a project inte the index.
-    async fn insert_project(
-        &self,
-        name: &str,
-        encoded: &str,
-        timestamp: i64,
-    ) -> Result<RawProject, sqlx::Error> {
-        sqlx::query_as(
-            r#"
-            INSERT INTO
-                projects
-                (name, encoded_name, created_at)
-            VALUES
-                (?, ?, ?)
-            RETURNING
-                *
-            "#,
+    #[tokio::test]
+    #[ignore = "needs a Postgres database in FKIT_TEST_POSTGRES_URL"]
+    async fn postgres_timestamp_type() {
+        let url = postgres_url();
+
+        let db = Database::new(&url)
+            .await
+            .expect("Database should be created");
+
+        let name = format!("timestamps_{}", std::process::id());
+        let project = db.create(&name).await;
+
+        let column_type: String = sqlx::query_scalar(
+            "SELECT data_type FROM information_schema.columns WHERE table_name = $1 AND column_name = '__timestamp__'",
         )
-        .bind(name)
-        .bind(encoded)
-        .bind(timestamp)
-        .fetch_one(&self.pool)
+        .bind(&name)
+        .fetch_one(&db.pool)
         .await
+        .unwrap();
+        assert_eq!(column_type, "timestamp with time zone");
+
+        let data = HashMap::from([("note".to_string(), "first".to_string())]);
+        let timestamp = project.add_datapoint(data).await.unwrap();
+
+        let datapoints = project.get_data_with_timestamps().await.unwrap();
+        assert_eq!(datapoints[0].timestamp, timestamp);
+
+        let between = project
+            .get_data_between(timestamp, timestamp + TimeDelta::try_seconds(1).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(between.len(), 1);
+        assert_eq!(project.changes_since(timestamp).await.unwrap().len(), 1);
+        let later = timestamp + TimeDelta::try_seconds(1).unwrap();
+        assert!(project.changes_since(later).await.unwrap().is_empty());
+
+        assert!(db.delete_project(&name).await.unwrap());
     }
-}
 
-#[cfg(test)]
-pub mod methods {
-    use crate::{project::Project, Database};
+    /// Inserts through every write path that binds parameters and reads the rows back
+    async fn insert_and_read(db: &Database, name: &str) {
+        let project = db.create(name).await;
+
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        let data = HashMap::from([("count".to_string(), "1".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+
+        let rows = vec![
+            HashMap::from([("count".to_string(), "2".to_string())]),
+            HashMap::from([("label".to_string(), "three".to_string())]),
+        ];
+        assert_eq!(project.add_datapoints(rows).await.unwrap(), 2);
+
+        let names: Vec<String> = project
+            .get_columns()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["count", "label"]);
+
+        // Rows added in the same second have no fixed order
+        let mut data: Vec<(Option<String>, Option<String>)> = project
+            .get_data()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|mut row| (row.remove("count").flatten(), row.remove("label").flatten()))
+            .collect();
+        data.sort();
+        assert_eq!(
+            data,
+            [
+                (None, Some("three".to_string())),
+                (Some("1".to_string()), None),
+                (Some("2".to_string()), None),
+            ]
+        );
+
+        assert!(db.delete_project(name).await.unwrap());
+    }
 
     #[tokio::test]
-    async fn create_memory_database() {
-        create_mem_db("create_db").await;
+    async fn insert_sqlite() {
+        let db = create_mem_db("insert_sqlite").await;
+        insert_and_read(&db, "insert").await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Postgres database in FKIT_TEST_POSTGRES_URL"]
+    async fn insert_postgres() {
+        let url = postgres_url();
+
+        let db = Database::new(&url)
+            .await
+            .expect("Database should be created");
+        insert_and_read(&db, &format!("insert_{}", std::process::id())).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MySQL database in FKIT_TEST_MYSQL_URL"]
+    async fn insert_mysql() {
+        let url = mysql_url();
+
+        let db = Database::new(&url)
+            .await
+            .expect("Database should be created");
+        insert_and_read(&db, &format!("insert_{}", std::process::id())).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MySQL database in FKIT_TEST_MYSQL_URL"]
+    async fn mysql_columns() {
+        let url = mysql_url();
+
+        let db = Database::new(&url)
+            .await
+            .expect("Database should be created");
+        let name = format!("columns_{}", std::process::id());
+        let project = db.create(&name).await;
+
+        let column = project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        assert_eq!(column.name, "count");
+
+        // Both need more than MySQL's INTEGER and FLOAT can hold
+        let data = HashMap::from([
+            ("count".to_string(), "5000000000".to_string()),
+            ("ratio".to_string(), "0.1".to_string()),
+        ]);
+        project.add_datapoint_typed(data).await.unwrap();
+
+        let renamed = project.rename_column("count", "total").await.unwrap();
+        assert_eq!(renamed.map(|c| c.name), Some("total".to_string()));
+        let changed = project
+            .change_column_type("total", DataType::Text)
+            .await
+            .unwrap();
+        assert_eq!(changed.column_type, DataType::Text);
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data[0]["total"], Some("5000000000".to_string()));
+        let ratio = data[0]["ratio"]
+            .as_deref()
+            .map(|v| v.parse::<f64>().unwrap());
+        assert_eq!(ratio, Some(0.1));
+
+        assert!(db.delete_project(&name).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn checkpoint_wal() {
+        let path = std::env::temp_dir().join("fkit_checkpoint_wal.db");
+        for suffix in ["", "-wal", "-shm"] {
+            std::fs::remove_file(format!("{}{}", path.display(), suffix)).ok();
+        }
+
+        let db = Database::new(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .expect("Database should be created");
+        sqlx::query("PRAGMA journal_mode=WAL")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let project = db.create("foo").await;
+        for i in 0..10 {
+            let data = HashMap::from([("count".to_string(), i.to_string())]);
+            project.add_datapoint(data).await.unwrap();
+        }
+
+        let checkpoint = db.checkpoint().await.unwrap().unwrap();
+        assert!(!checkpoint.busy);
+        assert!(checkpoint.log_frames >= 0);
+        assert_eq!(checkpoint.log_frames, checkpoint.checkpointed_frames);
+        assert_eq!(project.get_data().await.unwrap().len(), 10);
+
+        db.pool.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            std::fs::remove_file(format!("{}{}", path.display(), suffix)).ok();
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_database_keeps_idle_connections() {
+        let db = create_mem_db("memory_idle_timeout").await;
+
+        assert_eq!(db.pool.options().get_idle_timeout(), None);
     }
 
     #[tokio::test]
@@ -219,13 +1905,101 @@ pub mod methods {
         assert_eq!(project.encoded, "foo");
     }
 
+    #[tokio::test]
+    async fn create_project_and_add_datapoint() {
+        let db = create_mem_db("create_project_and_add_datapoint").await;
+        let project = db.create_project("foo").await.unwrap();
+
+        let before = Utc::now().timestamp();
+        let data = HashMap::from([("count".to_string(), "1".to_string())]);
+        let added = project.add_datapoint(data).await.unwrap();
+
+        let project = db.get_project("foo").await.unwrap().unwrap();
+        let data = project.get_data_with_timestamps().await.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].timestamp, added);
+        assert!(data[0].timestamp.timestamp() >= before);
+        assert_eq!(data[0].values["count"], Some("1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn create_project_with_invalid_columns() {
+        let db = create_mem_db("create_project_with_invalid_columns").await;
+
+        let columns = [
+            ("short".to_string(), DataType::Text),
+            ("a".repeat(DEFAULT_MAX_COLUMN_NAME_LEN + 1), DataType::Text),
+        ];
+        let error = db.create_project_with_columns("foo", &columns).await;
+        assert!(matches!(error, Err(Error::ColumnNameTooLong { .. })));
+
+        // Nothing is left behind, so the project can be created again
+        assert!(db.get_project("foo").await.unwrap().is_none());
+
+        // Columns failing after others were already added to the new table roll back as well
+        let reserved = [
+            ("short".to_string(), DataType::Text),
+            ("__id__".to_string(), DataType::Text),
+        ];
+        let error = db.create_project_with_columns("foo", &reserved).await;
+        assert!(matches!(error, Err(Error::Reserved(_))));
+        assert!(db.get_project("foo").await.unwrap().is_none());
+
+        db.create_project_with_columns("foo", &columns[..1])
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn create_projects_weird_name() {
         let db = create_mem_db("create_project_weird").await;
         let project = db.create("foo/bar").await;
 
         assert_eq!(project.name, "foo/bar");
-        assert_eq!(project.encoded, "foobar");
+        assert_eq!(project.encoded, "foobar_c7a71bed");
+    }
+
+    #[tokio::test]
+    async fn create_project_without_allowed_characters() {
+        let db = create_mem_db("create_project_without_allowed").await;
+
+        let error = db.create_project("@@@").await.unwrap_err();
+        assert!(matches!(error, Error::EmptyEncodedName(_)));
+        assert!(error.to_string().contains("'@@@'"));
+
+        assert!(db.get_all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_project_with_reserved_name() {
+        let db = create_mem_db("create_project_with_reserved_name").await;
+
+        for name in ["projects", "Columns", "_sqlx_migrations"] {
+            let error = db.create_project(name).await.unwrap_err();
+            assert!(matches!(error, Error::Reserved(_)));
+        }
+
+        assert!(db.get_all().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_existing_project() {
+        let db = create_mem_db("create_existing_project").await;
+        db.create("foo").await;
+
+        let error = db.create_project("foo").await.unwrap_err();
+        assert!(matches!(error, Error::ProjectExists(ref name) if name == "foo"));
+        assert_eq!(db.get_all().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn create_projects_colliding_names() {
+        let db = create_mem_db("create_projects_colliding").await;
+        let safe = db.create("ab").await;
+        let stripped = db.create("a!b").await;
+
+        assert_ne!(safe.encoded, stripped.encoded);
+        assert_eq!(db.get_all().await.len(), 2);
     }
 
     #[tokio::test]
@@ -258,12 +2032,365 @@ pub mod methods {
         assert_eq!(projects.len(), 2);
     }
 
+    #[tokio::test]
+    async fn projects_with_column() {
+        let db = create_mem_db("projects_with_column").await;
+        let foo = db.create("foo").await;
+        let bar = db.create("bar").await;
+        let baz = db.create("baz").await;
+        let archived = db.create("archived").await;
+
+        foo.create("temperature").await;
+        bar.create("humidity").await;
+        bar.create("temperature").await;
+        baz.create("humidity").await;
+        archived.create("temperature").await;
+        db.archive_project("archived").await.unwrap();
+
+        let projects = db.projects_with_column("temperature").await.unwrap();
+        assert_eq!(projects, vec!["bar".to_string(), "foo".to_string()]);
+
+        let projects = db.projects_with_column("pressure").await.unwrap();
+        assert!(projects.is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_or_create_project() {
+        let db = create_mem_db("get_or_create_project").await;
+
+        let created = db.get_or_create_project("foo").await.unwrap();
+        let fetched = db.get_or_create_project("foo").await.unwrap();
+
+        assert_eq!(created.id, fetched.id);
+        assert_eq!(db.get_all().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn get_or_create_project_concurrent() {
+        let db = create_shared_mem_db("get_or_create_project_concurrent").await;
+
+        let first = tokio::spawn({
+            let db = db.clone();
+            async move { db.get_or_create_project("foo").await }
+        });
+        let second = tokio::spawn({
+            let db = db.clone();
+            async move { db.get_or_create_project("foo").await }
+        });
+
+        let first = first
+            .await
+            .unwrap()
+            .expect("Project should be fetched or created");
+        let second = second
+            .await
+            .unwrap()
+            .expect("Project should be fetched or created");
+
+        assert_eq!(first.id, second.id);
+        assert_eq!(db.get_all().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_atomic() {
+        let db = create_mem_db("add_datapoints_atomic").await;
+        let project = db.create("foo").await;
+        project
+            .add_datapoint(HashMap::from([("count".to_string(), "1".to_string())]))
+            .await
+            .unwrap();
+
+        let row = |count: &str, note: &str| {
+            HashMap::from([
+                ("count".to_string(), count.to_string()),
+                ("note".to_string(), note.to_string()),
+            ])
+        };
+
+        // The last row fails, so neither the rows nor the new column are kept
+        let rows = vec![row("2", "a"), row("3", "b"), row("many", "c")];
+        match db.add_datapoints_atomic("foo", &rows).await {
+            Err(Error::InvalidRows(invalid)) => assert_eq!(invalid[0].row, 2),
+            other => panic!("Expected invalid rows, got {:?}", other),
+        }
+        assert_eq!(project.get_data().await.unwrap().len(), 1);
+        assert_eq!(project.get_columns().await.unwrap().len(), 1);
+
+        let rows = vec![row("2", "a"), row("3", "b")];
+        assert_eq!(db.add_datapoints_atomic("foo", &rows).await.unwrap(), 2);
+        assert_eq!(project.get_data().await.unwrap().len(), 3);
+        assert_eq!(project.get_columns().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_atomic_new_project() {
+        let db = create_mem_db("add_datapoints_atomic_new_project").await;
+
+        // The project is created before the column name of the last row is rejected
+        let rows = vec![
+            HashMap::from([("count".to_string(), "1".to_string())]),
+            HashMap::from([("a".repeat(DEFAULT_MAX_COLUMN_NAME_LEN + 1), "2".to_string())]),
+        ];
+        match db.add_datapoints_atomic("foo", &rows).await {
+            Err(Error::ColumnNameTooLong { .. }) => {}
+            other => panic!("Expected a column name error, got {:?}", other),
+        }
+        assert!(db.get_project("foo").await.unwrap().is_none());
+
+        let inserted = db.add_datapoints_atomic("foo", &rows[..1]).await.unwrap();
+        assert_eq!(inserted, 1);
+        let project = db.get_project("foo").await.unwrap().unwrap();
+        assert_eq!(project.get_data().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_atomic_concurrent() {
+        let db = create_shared_mem_db("add_datapoints_atomic_concurrent").await;
+
+        let tasks: Vec<_> = (0..4)
+            .map(|i| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    let rows = vec![HashMap::from([("count".to_string(), i.to_string())])];
+                    db.add_datapoints_atomic("foo", &rows).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap(), 1);
+        }
+
+        assert_eq!(db.get_all().await.len(), 1);
+        let project = db.get_project("foo").await.unwrap().unwrap();
+        assert_eq!(project.get_data().await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn duplicate_project() {
+        let db = create_mem_db("duplicate_project").await;
+        let foo = db.create("foo").await;
+
+        for value in ["a", "b"] {
+            let mut data = HashMap::new();
+            data.insert("boo".to_string(), value.to_string());
+            foo.add_datapoint(data).await.unwrap();
+        }
+
+        let bar = db.duplicate_project("foo", "bar").await.unwrap();
+
+        let foo_columns = foo.get_columns().await.unwrap();
+        let bar_columns = bar.get_columns().await.unwrap();
+        assert_eq!(bar_columns.len(), foo_columns.len());
+        for (foo_column, bar_column) in foo_columns.iter().zip(&bar_columns) {
+            assert_eq!(foo_column.name, bar_column.name);
+            assert_eq!(foo_column.column_type, bar_column.column_type);
+            assert_eq!(bar_column.project_id, bar.id);
+        }
+
+        assert_eq!(foo.get_data().await.unwrap(), bar.get_data().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn duplicate_project_collision() {
+        let db = create_mem_db("duplicate_project_collision").await;
+        db.create("foo").await;
+        db.create("bar").await;
+
+        let result = db.duplicate_project("foo", "bar").await;
+        assert!(matches!(result, Err(Error::ProjectExists(_))));
+
+        let result = db.duplicate_project("baz", "qux").await;
+        assert!(matches!(result, Err(Error::ProjectNotFound(_))));
+
+        assert_eq!(db.get_all().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn get_stale_projects() {
+        let db = create_mem_db("get_stale_projects").await;
+        let foo = db.create("foo").await;
+        db.create("bar").await;
+
+        // Pretend both projects were created an hour ago
+        let hour_ago = Utc::now() - TimeDelta::try_hours(1).unwrap();
+        sqlx::query("UPDATE projects SET created_at = ?")
+            .bind(hour_ago.timestamp())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("boo".to_string(), "baz".to_string());
+        foo.add_datapoint(data).await.unwrap();
+
+        let cutoff = Utc::now() - TimeDelta::try_minutes(1).unwrap();
+        let stale = db.get_stale_projects(cutoff).await.unwrap();
+
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].name, "bar");
+        assert!(stale[0].last_datapoint_at.is_none());
+
+        let foo = db.get("foo").await.unwrap();
+        assert!(foo.last_datapoint_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn project_cache() {
+        let db = create_mem_db("project_cache").await;
+        db.create("foo").await;
+        let cached = db.get("foo").await.unwrap();
+
+        // Changes behind the back of the cache are not seen until it is cleared
+        sqlx::query("UPDATE projects SET created_at = 0")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.get("foo").await.unwrap().created_at, cached.created_at);
+
+        db.clear_cache();
+        let cached = db.get("foo").await.unwrap();
+        assert_eq!(cached.created_at.timestamp(), 0);
+
+        // Datapoints added through a cached project keep it up to date
+        let data = HashMap::from([("boo".to_string(), "baz".to_string())]);
+        let added = cached.add_datapoint(data).await.unwrap();
+        assert_eq!(db.get("foo").await.unwrap().last_datapoint_at, Some(added));
+
+        let frozen = db.freeze_schema("foo").await.unwrap().unwrap();
+        assert!(frozen.schema_frozen);
+        assert!(db.get("foo").await.unwrap().schema_frozen);
+
+        db.archive_project("foo").await.unwrap();
+        assert!(db.get_project("foo").await.unwrap().is_none());
+        db.restore_project("foo").await.unwrap();
+
+        assert!(db.delete_project("foo").await.unwrap());
+        assert!(db.get_project("foo").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn table_verification() {
+        let db = create_mem_db("table_verification").await;
+        db.create("foo").await;
+        db.create("bar").await;
+
+        sqlx::query("UPDATE projects SET encoded_name = 'missing' WHERE name = 'foo'")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let verified = db.clone().with_table_verification(true);
+        assert!(verified.get_project("bar").await.unwrap().is_some());
+        match verified.get_project("foo").await {
+            Err(Error::SchemaMismatch { name, encoded }) => {
+                assert_eq!(name, "foo");
+                assert_eq!(encoded, "missing");
+            }
+            other => panic!("Expected a schema mismatch, got {:?}", other),
+        }
+
+        // Without verification the drift goes unnoticed until the table is used
+        let project = db.get_project("foo").await.unwrap().unwrap();
+        assert!(project.get_data().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn project_cache_disabled() {
+        let db = create_mem_db("project_cache_disabled")
+            .await
+            .with_project_cache(false);
+        db.create("foo").await;
+        db.get("foo").await.unwrap();
+
+        sqlx::query("UPDATE projects SET created_at = 0")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.get("foo").await.unwrap().created_at.timestamp(), 0);
+    }
+
+    #[tokio::test]
+    async fn delete_project() {
+        let db = create_mem_db("delete_project").await;
+        let project = db.create("foo").await;
+        db.create("bar").await;
+
+        let mut data = HashMap::new();
+        data.insert("baz".to_string(), "qux".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        assert!(db.delete_project("foo").await.unwrap());
+        assert!(db.get("foo").await.is_none());
+        assert_eq!(db.get_all().await.len(), 1);
+
+        // Nothing is left behind, so the name can be used again
+        let project = db.create("foo").await;
+        assert!(project.get_all().await.is_empty());
+        assert!(project.get_data().await.unwrap().is_empty());
+
+        assert!(!db.delete_project("missing").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn archive_and_restore_project() {
+        let db = create_mem_db("archive_and_restore_project").await;
+        let project = db.create("foo").await;
+        db.create("bar").await;
+
+        let mut data = HashMap::new();
+        data.insert("baz".to_string(), "qux".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        let archived = db.archive_project("foo").await.unwrap().unwrap();
+        assert!(archived.deleted_at.is_some());
+
+        // Hidden by default
+        assert!(db.get("foo").await.is_none());
+        assert_eq!(db.get_all().await.len(), 1);
+
+        // But still there when asked for
+        assert!(db.find_project("foo", true).await.unwrap().is_some());
+        assert_eq!(db.find_projects(true).await.unwrap().len(), 2);
+
+        // Archiving twice does nothing
+        assert!(db.archive_project("foo").await.unwrap().is_none());
+
+        let restored = db.restore_project("foo").await.unwrap().unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert_eq!(db.get_all().await.len(), 2);
+
+        let data = restored.get_data().await.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0]["baz"], Some("qux".to_string()));
+
+        assert!(db.restore_project("foo").await.unwrap().is_none());
+    }
+
+    /// Url of the database the ignored Postgres tests run against
+    fn postgres_url() -> String {
+        std::env::var("FKIT_TEST_POSTGRES_URL")
+            .expect("FKIT_TEST_POSTGRES_URL should point to a Postgres database")
+    }
+
+    fn mysql_url() -> String {
+        std::env::var("FKIT_TEST_MYSQL_URL")
+            .expect("FKIT_TEST_MYSQL_URL should point to a MySQL database")
+    }
+
     pub async fn create_mem_db(name: &str) -> Database {
         Database::new(&format!("sqlite:file:{}?mode=memory", name))
             .await
             .expect("Database should be created")
     }
 
+    /// In-memory database where every pooled connection sees the same data, needed when
+    /// multiple connections are used at once
+    pub async fn create_shared_mem_db(name: &str) -> Database {
+        Database::new(&format!("sqlite:file:{}?mode=memory&cache=shared", name))
+            .await
+            .expect("Database should be created")
+    }
+
     impl Database {
         pub async fn create(&self, name: &str) -> Project {
             self.create_project(name)
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    project::{DataType, EXTRA_COLUMN},
+    Database, Error,
+};
+
+/// Options for [Database::export_all]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Leave out the ids of projects and datapoints, which only mean something in the database
+    /// they were exported from
+    pub omit_ids: bool,
+}
+
+/// Everything in a single project, as written by [Database::export_all]
+///
+/// Projects are matched by name when imported, ids are only informational.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectDump {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub name: String,
+    pub schema_frozen: bool,
+
+    /// Columns in creation order
+    pub columns: Vec<ColumnDump>,
+
+    /// Datapoints, oldest first
+    pub datapoints: Vec<DatapointDump>,
+}
+
+/// A column of a [ProjectDump]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnDump {
+    pub name: String,
+
+    /// Name of the data type, like `integer`
+    pub column_type: String,
+}
+
+/// A datapoint of a [ProjectDump]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatapointDump {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+
+    /// Time the datapoint was added, in seconds since the unix epoch
+    pub timestamp: i64,
+
+    /// Values keyed by column name, None if the value is NULL
+    pub values: HashMap<String, Option<String>>,
+}
+
+impl Database {
+    /// Export every active project with its columns and datapoints
+    ///
+    /// The result can be serialized and read back into any database with
+    /// [Database::import_all]. Set [ExportOptions::omit_ids] when merging exports of several
+    /// databases, their ids collide and are not used by the import anyway.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, dump::ExportOptions};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:export_all?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let data = HashMap::from([("bar".to_string(), "baz".to_string())]);
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let options = ExportOptions { omit_ids: true };
+    /// let dump = db.export_all(&options).await?;
+    /// assert_eq!(dump[0].name, "foo");
+    /// assert_eq!(dump[0].datapoints[0].id, None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_all(&self, options: &ExportOptions) -> Result<Vec<ProjectDump>, Error> {
+        let mut projects = self.get_projects().await?;
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut dump = Vec::with_capacity(projects.len());
+        for project in projects {
+            let columns = project
+                .get_columns()
+                .await?
+                .into_iter()
+                .map(|c| ColumnDump {
+                    name: c.name,
+                    column_type: c.column_type.as_api_str().to_string(),
+                })
+                .collect();
+
+            let datapoints = project
+                .get_data_with_timestamps()
+                .await?
+                .into_iter()
+                .map(|d| DatapointDump {
+                    id: (!options.omit_ids).then_some(d.id),
+                    timestamp: d.timestamp.timestamp(),
+                    values: d.values,
+                })
+                .collect();
+
+            dump.push(ProjectDump {
+                id: (!options.omit_ids).then_some(project.id),
+                name: project.name,
+                schema_frozen: project.schema_frozen,
+                columns,
+                datapoints,
+            });
+        }
+
+        Ok(dump)
+    }
+
+    /// Import projects written by [Database::export_all], adding their datapoints
+    ///
+    /// Projects are matched by name, missing ones are created and existing ones get the
+    /// datapoints added to them, so exports of several databases can be merged into one. Every
+    /// datapoint gets a new id and keeps its timestamp. Each project is imported in a single
+    /// transaction, see [crate::Project::add_datapoints].
+    ///
+    /// # Returns
+    /// The number of imported datapoints. [Error::InvalidDump] if a column has an unknown data
+    /// type
+    pub async fn import_all(&self, dump: &[ProjectDump]) -> Result<usize, Error> {
+        let mut imported = 0;
+
+        for project_dump in dump {
+            let columns = project_dump
+                .columns
+                .iter()
+                .map(|c| {
+                    let data_type = c
+                        .column_type
+                        .parse::<DataType>()
+                        .map_err(|e| Error::InvalidDump(format!("Column '{}': {}", c.name, e)))?;
+                    Ok((c.name.clone(), data_type))
+                })
+                .collect::<Result<Vec<(String, DataType)>, Error>>()?;
+
+            // The column holding collapsed sparse columns is reserved, it is added separately
+            let (extra, columns): (Vec<_>, Vec<_>) = columns
+                .into_iter()
+                .partition(|(name, _)| name == EXTRA_COLUMN);
+
+            let project = self.get_or_create_project(&project_dump.name).await?;
+            project.get_or_create_columns(&columns).await?;
+            if !extra.is_empty() {
+                project.get_or_create_extra_column().await?;
+            }
+
+            let (timestamps, rows): (Vec<i64>, Vec<HashMap<String, String>>) = project_dump
+                .datapoints
+                .iter()
+                .map(|d| {
+                    let values = d
+                        .values
+                        .iter()
+                        .filter_map(|(name, value)| Some((name.clone(), value.clone()?)))
+                        .collect();
+                    (d.timestamp, values)
+                })
+                .unzip();
+            imported += project.add_datapoints_at(rows, &timestamps).await?;
+
+            if project_dump.schema_frozen {
+                self.freeze_schema(&project_dump.name).await?;
+            }
+        }
+
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::database::methods::create_mem_db;
+
+    use super::ExportOptions;
+
+    #[tokio::test]
+    async fn merge_exports() {
+        let options = ExportOptions { omit_ids: true };
+        let mut dumps = Vec::new();
+
+        for name in ["merge_exports_a", "merge_exports_b"] {
+            let db = create_mem_db(name).await;
+            let project = db.create("foo").await;
+            for i in 0..2 {
+                let data = HashMap::from([("source".to_string(), format!("{}_{}", name, i))]);
+                project.add_datapoint(data).await.unwrap();
+            }
+            db.create("only_in_one").await;
+
+            let with_ids = db.export_all(&ExportOptions::default()).await.unwrap();
+            assert_eq!(with_ids[0].datapoints[0].id, Some(1));
+
+            let dump = db.export_all(&options).await.unwrap();
+            assert!(dump.iter().all(|p| p.id.is_none()));
+            dumps.push(dump);
+        }
+
+        let merged = create_mem_db("merge_exports").await;
+        for dump in &dumps {
+            assert_eq!(merged.import_all(dump).await.unwrap(), 2);
+        }
+
+        // One project per name, with the datapoints of both databases under fresh ids
+        assert_eq!(merged.get_all().await.len(), 2);
+        let project = merged.get("foo").await.unwrap();
+        let data = project.get_data_with_ids().await.unwrap();
+        assert_eq!(data.len(), 4);
+
+        let mut ids: Vec<i64> = data.iter().map(|(id, _)| *id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 4);
+
+        let mut sources: Vec<String> = data
+            .into_iter()
+            .filter_map(|(_, values)| values["source"].clone())
+            .collect();
+        sources.sort();
+        assert_eq!(
+            sources,
+            [
+                "merge_exports_a_0",
+                "merge_exports_a_1",
+                "merge_exports_b_0",
+                "merge_exports_b_1"
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use crate::validation::{RowError, ValidationErrors};
+
+/// Errors returned by the database crate
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The query itself failed
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+
+    /// The migrations could not be applied
+    #[error(transparent)]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+
+    /// No project with the name exists
+    #[error("Project {0} not found")]
+    ProjectNotFound(String),
+
+    /// A name has no letter, digit or underscore, so it can not be encoded as an identifier
+    #[error("Name '{0}' needs at least one letter, digit or underscore")]
+    EmptyEncodedName(String),
+
+    /// A name is reserved for internal use
+    #[error("Name '{0}' is reserved")]
+    Reserved(String),
+
+    /// A project with the same name, or one that encodes to the same identifier, already exists
+    #[error("Project {0} already exists")]
+    ProjectExists(String),
+
+    /// A column was added to a project whose schema is frozen
+    #[error("Schema of project {0} is frozen, no new columns can be added")]
+    SchemaFrozen(String),
+
+    /// A column was used in a comparison that only makes sense for numbers
+    #[error("Column {0} is not numeric")]
+    NotNumeric(String),
+
+    /// A column with the same name, or one that encodes to the same identifier, already exists
+    #[error("Column {0} already exists")]
+    ColumnExists(String),
+
+    /// The project has no column with the name
+    #[error("Column {0} not found")]
+    ColumnNotFound(String),
+
+    /// The encoded name of a column is longer than the configured maximum, in bytes
+    #[error("Column name {name} is longer than {max} bytes")]
+    ColumnNameTooLong { name: String, max: usize },
+
+    /// No datapoint with the row id exists in the project
+    #[error("Datapoint {0} not found")]
+    DatapointNotFound(i64),
+
+    /// One or more values of a datapoint are not valid
+    #[error("{0}")]
+    Validation(ValidationErrors),
+
+    /// One or more rows of a batch of datapoints are not valid
+    #[error("Invalid values in {} rows", .0.len())]
+    InvalidRows(Vec<RowError>),
+
+    /// A write was rejected because the database is at its configured size limit, in bytes
+    #[error("Database has reached its size limit of {max} bytes")]
+    QuotaExceeded { max: u64 },
+
+    /// A raw value is larger than the configured maximum, in bytes, see
+    /// [crate::Database::with_max_blob_bytes]
+    #[error("Value of column {column} is {size} bytes, the limit is {max} bytes")]
+    ValueTooLarge {
+        column: String,
+        size: usize,
+        max: usize,
+    },
+
+    /// A CSV import could not be parsed
+    #[error("Invalid CSV: {0}")]
+    Csv(String),
+
+    /// The table a project is stored in does not exist, see
+    /// [crate::Database::with_table_verification]
+    #[error("Table {encoded} of project {name} does not exist")]
+    SchemaMismatch { name: String, encoded: String },
+
+    /// An export read by [crate::Database::import_all] is not valid
+    #[error("Invalid export: {0}")]
+    InvalidDump(String),
+}
@@ -1,6 +1,22 @@
 
 mod database;
+mod cache;
 pub mod utils;
 pub mod project;
+pub mod csv;
+pub mod dump;
+pub mod pool;
+mod parquet_export;
+pub mod value;
+mod backend;
+pub mod error;
+pub mod validation;
+pub mod stats;
+pub mod transform;
+pub mod tokens;
 
-pub use database::Database;
+pub use database::{Checkpoint, Database, MigrationInfo};
+pub use project::Project;
+pub use backend::Backend;
+pub use pool::PoolConfig;
+pub use error::Error;
@@ -0,0 +1,233 @@
+use std::{io::Write, sync::Arc};
+
+use arrow::{
+    array::{
+        ArrayRef, BinaryBuilder, Float64Builder, Int64Builder, StringBuilder, TimestampSecondArray,
+    },
+    datatypes::{DataType as ArrowType, Field, Schema, TimeUnit},
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+use sqlx::Row;
+
+use crate::{
+    project::{typed_column_list, DataType, Project},
+    value::Value,
+    Error,
+};
+
+impl DataType {
+    /// Arrow type used for columns of this type when exporting to Parquet
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// # use arrow::datatypes::DataType as ArrowType;
+    /// assert_eq!(DataType::Integer.to_arrow(), ArrowType::Int64);
+    /// ```
+    pub fn to_arrow(&self) -> ArrowType {
+        match self {
+            DataType::Text | DataType::Decimal => ArrowType::Utf8,
+            DataType::Integer | DataType::BigInteger => ArrowType::Int64,
+            DataType::Float => ArrowType::Float64,
+            DataType::Raw => ArrowType::Binary,
+        }
+    }
+}
+
+impl Project {
+    /// Export all datapoints of the project as Parquet
+    ///
+    /// The first column is `__timestamp__`, the time the datapoint was added, followed by every
+    /// project column using the arrow type from [DataType::to_arrow]. Values that were stored
+    /// with a different type than their column are written as NULL. Decimals are written as
+    /// text to keep their exact value.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:to_parquet?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut buffer = Vec::new();
+    /// project.to_parquet(&mut buffer).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn to_parquet<W: Write + Send>(&self, writer: W) -> Result<(), Error> {
+        let columns = self.get_columns().await?;
+
+        let query = format!(
+            "SELECT {} FROM {}",
+            typed_column_list(&columns, self.backend()),
+            self.table()
+        );
+        let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
+
+        // Timestamp column
+        let mut fields = Vec::with_capacity(columns.len() + 1);
+        let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len() + 1);
+
+        let timestamps = rows
+            .iter()
+            .map(|row| row.try_get::<i64, _>(0))
+            .collect::<Result<Vec<i64>, sqlx::Error>>()?;
+
+        fields.push(Field::new(
+            "__timestamp__",
+            ArrowType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+            false,
+        ));
+        arrays.push(Arc::new(
+            TimestampSecondArray::from(timestamps).with_timezone("UTC"),
+        ));
+
+        // Project columns
+        for (index, column) in columns.iter().enumerate() {
+            let values = rows
+                .iter()
+                .map(|row| Value::read(row, index + 1, column.column_type))
+                .collect::<Result<Vec<Option<Value>>, sqlx::Error>>()?;
+
+            fields.push(Field::new(
+                &column.name,
+                column.column_type.to_arrow(),
+                true,
+            ));
+            arrays.push(build_array(column.column_type, values));
+        }
+
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new(schema.clone(), arrays).map_err(export_error)?;
+
+        let mut writer = ArrowWriter::try_new(writer, schema, None).map_err(export_error)?;
+        writer.write(&batch).map_err(export_error)?;
+        writer.close().map_err(export_error)?;
+
+        Ok(())
+    }
+}
+
+/// Build an arrow array for a column of the given type
+fn build_array(data_type: DataType, values: Vec<Option<Value>>) -> ArrayRef {
+    match data_type {
+        DataType::Text => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    Some(Value::Text(text)) => builder.append_value(text),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Decimal => {
+            let mut builder = StringBuilder::new();
+            for value in values {
+                match value {
+                    Some(Value::Decimal(decimal)) => builder.append_value(decimal.to_string()),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Integer | DataType::BigInteger => {
+            let mut builder = Int64Builder::new();
+            for value in values {
+                match value {
+                    Some(Value::Integer(integer)) => builder.append_value(integer),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Float => {
+            let mut builder = Float64Builder::new();
+            for value in values {
+                match value {
+                    Some(Value::Float(float)) => builder.append_value(float),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+        DataType::Raw => {
+            let mut builder = BinaryBuilder::new();
+            for value in values {
+                match value {
+                    Some(Value::Raw(raw)) => builder.append_value(raw),
+                    _ => builder.append_null(),
+                }
+            }
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+/// Wrap an arrow or parquet error so it can be returned alongside database errors
+fn export_error<E>(error: E) -> sqlx::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    sqlx::Error::Io(std::io::Error::other(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use arrow::datatypes::DataType as ArrowType;
+    use bytes::Bytes;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::{database::methods::create_mem_db, project::DataType};
+
+    #[tokio::test]
+    async fn round_trip() {
+        let db = create_mem_db("parquet_round_trip").await;
+        let project = db.create("foo").await;
+
+        project.create("name").await;
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        project
+            .create_column("ratio", DataType::Float)
+            .await
+            .unwrap();
+
+        for (name, count, ratio) in [("a", "1", "0.5"), ("b", "2", "1.5")] {
+            let mut data = HashMap::new();
+            data.insert("name".to_string(), name.to_string());
+            data.insert("count".to_string(), count.to_string());
+            data.insert("ratio".to_string(), ratio.to_string());
+            project.add_datapoint(data).await.unwrap();
+        }
+
+        let mut buffer = Vec::new();
+        project.to_parquet(&mut buffer).await.unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(Bytes::from(buffer)).unwrap();
+        let schema = builder.schema().clone();
+
+        let fields: Vec<(&str, &ArrowType)> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type()))
+            .collect();
+        assert_eq!(fields.len(), 4);
+        assert_eq!(fields[1], ("name", &ArrowType::Utf8));
+        assert_eq!(fields[2], ("count", &ArrowType::Int64));
+        assert_eq!(fields[3], ("ratio", &ArrowType::Float64));
+
+        let rows: usize = builder
+            .build()
+            .unwrap()
+            .map(|batch| batch.unwrap().num_rows())
+            .sum();
+        assert_eq!(rows, 2);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::Backend;
+
+/// How long SQLite file connections can stay idle before they are closed, releasing the file
+/// and WAL so that external tools can checkpoint
+pub const DEFAULT_SQLITE_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Most connections the pool opens, unless configured otherwise
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 99;
+
+/// How long to wait for a free connection before giving up, unless configured otherwise
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many times a transaction is retried after a serialization failure
+pub const MAX_SERIALIZATION_RETRIES: usize = 3;
+
+/// How many times creating a project or column is retried while SQLite has the schema locked
+pub const MAX_LOCKED_RETRIES: usize = 10;
+
+/// How long to wait before retrying a write that found SQLite locked
+pub const LOCKED_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Transaction isolation level used by every connection in the pool
+///
+/// Only applied to Postgres, SQLite transactions are always serializable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationLevel {
+    ReadCommitted,
+    Serializable,
+}
+
+/// Settings for the connection pool of a [crate::Database]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Most connections open at the same time
+    pub max_connections: u32,
+
+    /// Connections kept open even when idle
+    pub min_connections: u32,
+
+    /// How long to wait for a free connection before a query fails
+    pub acquire_timeout: Duration,
+
+    /// How long a connection can be idle before it is closed, None keeps idle connections open
+    pub idle_timeout: Option<Duration>,
+
+    /// Isolation level set on every new connection, None keeps the server default
+    pub isolation_level: Option<IsolationLevel>,
+}
+
+impl IsolationLevel {
+    /// Statement setting this isolation level for the rest of a Postgres session
+    pub fn session_statement(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => {
+                "SET SESSION CHARACTERISTICS AS TRANSACTION ISOLATION LEVEL READ COMMITTED"
+            }
+            IsolationLevel::Serializable => {
+                "SET SESSION CHARACTERISTICS AS TRANSACTION ISOLATION LEVEL SERIALIZABLE"
+            }
+        }
+    }
+
+    /// Name as reported by Postgres in `transaction_isolation`
+    pub fn as_postgres_str(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "read committed",
+            IsolationLevel::Serializable => "serializable",
+        }
+    }
+}
+
+impl FromStr for IsolationLevel {
+    type Err = String;
+
+    /// Parses the config name of an isolation level
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::pool::IsolationLevel;
+    /// assert_eq!("serializable".parse(), Ok(IsolationLevel::Serializable));
+    /// assert!("dirty".parse::<IsolationLevel>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read_committed" => Ok(IsolationLevel::ReadCommitted),
+            "serializable" => Ok(IsolationLevel::Serializable),
+            other => Err(format!(
+                "Unknown isolation level '{}', expected 'read_committed' or 'serializable'",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsolationLevel::ReadCommitted => write!(f, "read_committed"),
+            IsolationLevel::Serializable => write!(f, "serializable"),
+        }
+    }
+}
+
+impl PoolConfig {
+    /// Default pool settings for a database url
+    ///
+    /// SQLite files reap idle connections after [DEFAULT_SQLITE_IDLE_TIMEOUT]. In-memory
+    /// databases never do, since the data is lost once the last connection closes.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::{PoolConfig, pool::DEFAULT_SQLITE_IDLE_TIMEOUT};
+    /// let file = PoolConfig::for_url("sqlite://./foo.db");
+    /// assert_eq!(file.idle_timeout, Some(DEFAULT_SQLITE_IDLE_TIMEOUT));
+    ///
+    /// let memory = PoolConfig::for_url("sqlite:file:foo?mode=memory");
+    /// assert_eq!(memory.idle_timeout, None);
+    /// ```
+    pub fn for_url(url: &str) -> PoolConfig {
+        let idle_timeout = match Backend::from_url(url) {
+            Some(Backend::Sqlite) if !Backend::is_memory_url(url) => {
+                Some(DEFAULT_SQLITE_IDLE_TIMEOUT)
+            }
+            _ => None,
+        };
+
+        PoolConfig {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            min_connections: 0,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+            idle_timeout,
+            isolation_level: None,
+        }
+    }
+}
@@ -1,11 +1,52 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use chrono::{DateTime, Utc};
-use sqlx::{prelude::FromRow, AnyPool, Column as column, Row as row};
+use sqlx::{any::AnyRow, prelude::FromRow, AnyConnection, AnyPool, Row as row};
 
-use crate::utils::sql_encode;
+use crate::{
+    cache::ProjectCache,
+    database::retry_locked,
+    utils::{encode_identifier, quote_identifier},
+    validation::{RowError, ValidationErrors, RULE_OUT_OF_RANGE, RULE_SCHEMA_FROZEN, RULE_TYPE},
+    value::Value,
+    Backend, Error,
+};
 
-/// A bare-bones representation of a project
+/// Columns every project table needs, these can never be dropped
+pub const REQUIRED_COLUMNS: &[&str] = &[TIMESTAMP_COLUMN, ID_COLUMN, UPDATED_AT_COLUMN];
+
+/// Time a datapoint was added
+pub const TIMESTAMP_COLUMN: &str = "__timestamp__";
+
+/// Auto-incrementing id of every datapoint, the primary key of the project table
+pub const ID_COLUMN: &str = "__id__";
+
+/// Time a datapoint was last changed, NULL until it is changed for the first time
+pub const UPDATED_AT_COLUMN: &str = "__updated_at__";
+
+/// Column holding the values of collapsed sparse columns as a json object, see
+/// [Project::collapse_sparse_columns]
+pub const EXTRA_COLUMN: &str = "__extra__";
+
+/// Longest encoded column name in bytes unless configured otherwise, Postgres truncates
+/// identifiers after 63 bytes
+pub const DEFAULT_MAX_COLUMN_NAME_LEN: usize = 63;
+
+/// Most parameters bound to a single query, the lowest limit of the supported databases
+const MAX_BIND_PARAMETERS: usize = 999;
+
+/// Columns of the project index selected into a [RawProject]
+///
+/// The Any driver can not decode NULL integers on SQLite, so the nullable timestamps are
+/// selected as 0 instead.
+pub(crate) const PROJECT_COLUMNS: &str = "name, encoded_name, created_at, id, \
+    COALESCE(last_datapoint_at, 0) AS last_datapoint_at, schema_frozen, \
+    COALESCE(deleted_at, 0) AS deleted_at";
+
+/// A bare-bones representation of a project, selected with [PROJECT_COLUMNS]
 #[derive(FromRow, Debug, Clone, PartialEq, Eq)]
 pub struct RawProject {
     pub name: String,
@@ -13,6 +54,11 @@ pub struct RawProject {
     pub encoded: String,
     pub created_at: i64,
     pub id: i64,
+    /// 0 if the project has no datapoints
+    pub last_datapoint_at: i64,
+    pub schema_frozen: i64,
+    /// 0 if the project is not archived
+    pub deleted_at: i64,
 }
 
 #[derive(FromRow, Debug, Clone, PartialEq, Eq)]
@@ -27,7 +73,7 @@ pub struct RawColumn {
 #[derive(Debug, Clone)]
 pub struct Project {
     /// generic sqlx connection pool
-    pool: AnyPool,
+    pub(crate) pool: AnyPool,
 
     /// Project id in the database
     pub id: i64,
@@ -40,6 +86,28 @@ pub struct Project {
 
     /// Time the project was created
     pub created_at: DateTime<Utc>,
+
+    /// Time the last datapoint was added, None if the project has no datapoints
+    pub last_datapoint_at: Option<DateTime<Utc>>,
+
+    /// Whether new columns are rejected, at the time the project was fetched
+    pub schema_frozen: bool,
+
+    /// Time the project was archived, None if it is active
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// Longest encoded column name in bytes, see [crate::Database::with_max_column_name_len]
+    pub(crate) max_column_name_len: usize,
+
+    /// Largest database size in bytes that still accepts writes, see
+    /// [crate::Database::with_max_db_bytes]
+    pub(crate) max_db_bytes: Option<u64>,
+
+    /// Largest raw value in bytes, see [crate::Database::with_max_blob_bytes]
+    pub(crate) max_blob_bytes: Option<usize>,
+
+    /// Cache of the database the project was fetched from, None if caching is disabled
+    pub(crate) cache: Option<ProjectCache>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -48,6 +116,7 @@ pub enum DataType {
     Integer,
     BigInteger,
     Float,
+    Decimal,
     Raw,
 }
 
@@ -60,6 +129,19 @@ pub struct Column {
     pub created_at: DateTime<Utc>,
 }
 
+/// A single datapoint of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Datapoint {
+    /// Row id of the datapoint, used to change it with [Project::update_datapoint]
+    pub id: i64,
+
+    /// Time the datapoint was added
+    pub timestamp: DateTime<Utc>,
+
+    /// Values keyed by column name, None if the value is NULL
+    pub values: HashMap<String, Option<String>>,
+}
+
 impl Project {
     /// Convert a RawProject to a Project
     ///
@@ -70,22 +152,46 @@ impl Project {
         let created_at =
             DateTime::from_timestamp(raw.created_at, 0).unwrap_or_default();
 
+        let last_datapoint_at = Some(raw.last_datapoint_at)
+            .filter(|timestamp| *timestamp != 0)
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0));
+
         Some(Project {
             pool,
             created_at,
+            last_datapoint_at,
+            schema_frozen: raw.schema_frozen != 0,
+            deleted_at: Some(raw.deleted_at)
+                .filter(|timestamp| *timestamp != 0)
+                .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
             id: raw.id,
             name: raw.name,
             encoded: raw.encoded,
+            max_column_name_len: DEFAULT_MAX_COLUMN_NAME_LEN,
+            max_db_bytes: None,
+            max_blob_bytes: None,
+            cache: None,
         })
     }
 
-    /// Get all columns for this project
+    /// Backend the project is stored in
+    pub(crate) fn backend(&self) -> Backend {
+        Backend::from_url(self.pool.connect_options().database_url.as_str())
+            .unwrap_or(Backend::Sqlite)
+    }
+
+    /// Quoted name of the project table, for use in queries
+    pub(crate) fn table(&self) -> String {
+        quote_identifier(&self.encoded)
+    }
+
+    /// Get all columns for this project, in the order they were created
     ///
     /// # Example
     /// ```rust
     /// # use database::Database;
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:get_columns?mode=memory").await?;
     ///
     /// db.create_project("foo").await?;
@@ -96,15 +202,48 @@ impl Project {
     /// # }
     /// ```
     ///
-    pub async fn get_columns(&self) -> Result<Vec<Column>, sqlx::Error> {
+    pub async fn get_columns(&self) -> Result<Vec<Column>, Error> {
+        let Some(cache) = &self.cache else {
+            return self.fetch_columns().await;
+        };
+
+        if let Some(columns) = cache.columns(self.id) {
+            return Ok(columns);
+        }
+
+        let generation = cache.column_generation();
+        let columns = self.fetch_columns().await?;
+        cache.insert_columns(self.id, &columns, generation);
+        Ok(columns)
+    }
+
+    /// Fetch the columns from the database, bypassing the cache
+    pub(crate) async fn fetch_columns(&self) -> Result<Vec<Column>, Error> {
+        let mut conn = self.pool.acquire().await?;
+        self.fetch_columns_on(&mut conn).await
+    }
+
+    /// Like [Project::fetch_columns], using the given connection
+    pub(crate) async fn fetch_columns_on(
+        &self,
+        conn: &mut AnyConnection,
+    ) -> Result<Vec<Column>, Error> {
+        // Columns created in the same second are in insertion order, only SQLite has a rowid
+        let order = match self.backend() {
+            Backend::Sqlite => "rowid",
+            Backend::Postgres | Backend::MySql => "id",
+        };
+
         // Fetch and deserialize
-        let raw: Vec<RawColumn> = sqlx::query_as(
+        let raw: Vec<RawColumn> = sqlx::query_as(&self.backend().sql(&format!(
             r#"
-            SELECT * FROM columns WHERE project_id = $1
+            SELECT * FROM columns WHERE project_id = ?
+            ORDER BY created_at, {}
             "#,
-        )
+            order
+        )))
         .bind(self.id)
-        .fetch_all(&self.pool)
+        .fetch_all(conn)
         .await?;
 
         let mut columns = Vec::with_capacity(raw.len());
@@ -118,11 +257,15 @@ impl Project {
 
     /// Creates a new column for a given project with a given name
     ///
+    /// Fails with [Error::SchemaFrozen] if the schema of the project has been frozen since, and
+    /// with [Error::ColumnNameTooLong] if the encoded name is longer than the maximum. Names of
+    /// internal columns such as `__id__` fail with [Error::Reserved].
+    ///
     /// # Examples
     /// ```rust
     /// # use database::{Database, project::DataType};
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
+    /// # async fn test() -> Result<(), database::Error>{
     /// let db = Database::new("sqlite:file:create_column?mode=memory").await?;
     /// let project = db.create_project("foo").await?;
     ///
@@ -130,351 +273,4167 @@ impl Project {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn create_column(
+    pub async fn create_column(&self, name: &str, column_type: DataType) -> Result<Column, Error> {
+        if self.is_schema_frozen().await? {
+            return Err(Error::SchemaFrozen(self.name.clone()));
+        }
+
+        let encoded_name = self.encode_column_name(name)?;
+
+        // Add the column and its metadata together, so a concurrent create of the same column
+        // waits for this one and then fails instead of seeing half a column
+        let mut tx = self.pool.begin().await?;
+        self.add_column_on(&mut tx, &encoded_name, column_type)
+            .await?;
+        let raw_column = self
+            .insert_column_on(&mut tx, name, &encoded_name, column_type)
+            .await?;
+        tx.commit().await?;
+        self.invalidate_columns();
+
+        Ok(Column::from_raw(raw_column)?)
+    }
+
+    /// Adds columns to the table and the metadata of a project using the given connection,
+    /// without touching the column cache
+    ///
+    /// Used to create columns in the same transaction as their project or their data. Repeated
+    /// names are only created once.
+    ///
+    /// # Returns
+    /// The created columns
+    pub(crate) async fn create_columns_on(
         &self,
-        name: &str,
-        column_type: DataType,
-    ) -> Result<Column, sqlx::Error> {
-        let encoded_name = sql_encode(name).unwrap_or_else(|e| e);
+        conn: &mut AnyConnection,
+        columns: &[(String, DataType)],
+    ) -> Result<Vec<Column>, Error> {
+        let mut names = HashSet::with_capacity(columns.len());
+        let mut created = Vec::with_capacity(columns.len());
+        for (name, column_type) in columns {
+            if !names.insert(name) {
+                continue;
+            }
 
-        self.add_column(&encoded_name, column_type).await?;
-        let raw_column = self.insert_column(name, &encoded_name, column_type).await?;
+            let encoded_name = self.encode_column_name(name)?;
+            self.add_column_on(conn, &encoded_name, *column_type)
+                .await?;
+            let raw = self
+                .insert_column_on(conn, name, &encoded_name, *column_type)
+                .await?;
+            created.push(Column::from_raw(raw)?);
+        }
 
-        Column::from_raw(raw_column)
+        Ok(created)
     }
 
-    /// Alters the table of a given project to add a new column with the given name
+    /// Deletes a column, dropping it from the project table along with its metadata
     ///
     /// # Examples
     /// ```rust
     /// # use database::{Database, project::DataType};
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error>{
-    /// let db = Database::new("sqlite:file:create_column?mode=memory").await?;
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:delete_column?mode=memory").await?;
     /// let project = db.create_project("foo").await?;
     ///
-    /// project.add_column("bar", DataType::Text).await?;
+    /// project.create_column("bar", DataType::Text).await?;
+    /// project.delete_column("bar").await?;
+    ///
+    /// assert!(project.get_columns().await?.is_empty());
     /// # Ok(())
     /// # }
-    /// ````
-    /// -- Table schema is now:
-    /// CREATE TABLE foo (__timestamp__ INTEGER NOT NULL, bar TEXT);
-    pub async fn add_column(
-        &self,
-        encoded_name: &str,
-        column_type: DataType,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(&format!(
-            r#"
-            ALTER TABLE {} ADD COLUMN {} {}
-            "#,
-            &self.encoded,
-            &encoded_name,
-            column_type.to_sql()
+    /// ```
+    ///
+    /// SQLite versions before 3.35 can not drop columns, there the table is rebuilt without it.
+    ///
+    /// # Returns
+    /// [Error::ColumnNotFound] if the project has no column with the name, or an error if the
+    /// query failed
+    pub async fn delete_column(&self, name: &str) -> Result<(), Error> {
+        let (deleted, remaining): (Vec<Column>, Vec<Column>) = self
+            .get_columns()
+            .await?
+            .into_iter()
+            .partition(|c| c.name == name);
+        let column = deleted
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::ColumnNotFound(name.to_string()))?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let dropped = sqlx::query(&format!(
+            "ALTER TABLE {} DROP COLUMN {}",
+            self.table(),
+            column.quoted()
         ))
-        .execute(&self.pool)
+        .execute(&mut *tx)
+        .await;
+
+        match dropped {
+            Ok(_) => {}
+            Err(e) if self.backend() == Backend::Sqlite && is_syntax_error(&e) => {
+                self.rebuild_table(&mut tx, &remaining).await?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        sqlx::query(
+            &self
+                .backend()
+                .sql("DELETE FROM columns WHERE project_id = ? AND name = ?"),
+        )
+        .bind(self.id)
+        .bind(name)
+        .execute(&mut *tx)
         .await?;
 
+        tx.commit().await?;
+        self.invalidate_columns();
         Ok(())
     }
 
-    /// Inserts the column into the columns table of the database
+    /// Renames a column, keeping its data
     ///
     /// # Examples
     /// ```rust
     /// # use database::{Database, project::DataType};
     /// # tokio_test::block_on(test()).unwrap();
-    /// # async fn test() -> Result<(), sqlx::Error> {
-    /// let db = Database::new("sqlite:file:create_column?mode=memory").await?;
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:rename_column?mode=memory").await?;
     /// let project = db.create_project("foo").await?;
     ///
-    /// project.insert_column("bar", "bar", DataType::Text).await?;
+    /// project.create_column("bar", DataType::Text).await?;
+    /// let column = project.rename_column("bar", "baz").await?.unwrap();
+    ///
+    /// assert_eq!(column.name, "baz");
     /// # Ok(())
     /// # }
-    pub async fn insert_column(
-        &self,
-        name: &str,
-        encoded_name: &str,
-        column_type: DataType,
-    ) -> Result<RawColumn, sqlx::Error> {
-        let created_at = Utc::now().timestamp();
-        sqlx::query_as(
+    /// ```
+    ///
+    /// # Returns
+    /// The renamed column, None if the project has no column with the name.
+    /// Fails with [Error::ColumnExists] if another column already uses the new name or its
+    /// encoded form, and with [Error::Reserved] if the new name is reserved.
+    pub async fn rename_column(&self, name: &str, new_name: &str) -> Result<Option<Column>, Error> {
+        let columns = self.get_columns().await?;
+        let Some(column) = columns.iter().find(|c| c.name == name) else {
+            return Ok(None);
+        };
+
+        let encoded_name = self.encode_column_name(new_name)?;
+        let taken = columns
+            .iter()
+            .filter(|c| c.name != name)
+            .any(|c| c.name == new_name || c.encoded == encoded_name);
+        if taken {
+            return Err(Error::ColumnExists(new_name.to_string()));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        if encoded_name != column.encoded {
+            sqlx::query(&format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {}",
+                self.table(),
+                column.quoted(),
+                quote_identifier(&encoded_name)
+            ))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(&self.backend().sql(
             r#"
-            INSERT INTO columns 
-            VALUES (?, ?, ?, ?, ?)
-            RETURNING *
+            UPDATE columns SET name = ?, encoded = ?
+            WHERE project_id = ? AND name = ?
             "#,
-        )
+        ))
+        .bind(new_name)
+        .bind(&encoded_name)
         .bind(self.id)
         .bind(name)
-        .bind(encoded_name)
-        .bind(column_type.to_sql())
-        .bind(created_at)
-        .fetch_one(&self.pool)
-        .await
+        .execute(&mut *tx)
+        .await?;
+        let raw = self.select_column_on(&mut tx, new_name).await?;
+
+        tx.commit().await?;
+        self.invalidate_columns();
+
+        Ok(Some(Column::from_raw(raw)?))
     }
 
-    /// Adds a datapoint to the project
-    pub async fn add_datapoint(&self, data: HashMap<String, String>) -> Result<(), sqlx::Error> {
-        let mut keys = Vec::with_capacity(data.len());
-        let mut values = Vec::with_capacity(data.len());
+    /// Changes the type of a column, converting the values it holds
+    ///
+    /// Every value is checked against the new type first, see [Project::find_type_violations].
+    /// If any can not be converted nothing is changed. The check and the change run in the same
+    /// transaction. SQLite can not change the type of a column, there the table is rebuilt with
+    /// the values cast to the new type.
+    ///
+    /// MySQL commits `ALTER TABLE` right away, so there the check is not isolated from the
+    /// change. A value that can not be converted and is added in between fails the change, and
+    /// the column keeps its type.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:change_column_type?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    /// project.create_column("bar", DataType::Text).await?;
+    ///
+    /// let data = HashMap::from([("bar".to_string(), "42".to_string())]);
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let column = project.change_column_type("bar", DataType::Integer).await?;
+    /// assert_eq!(column.column_type, DataType::Integer);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// [Error::ColumnNotFound] if the project has no column with the name, and
+    /// [Error::Validation] listing every datapoint with a value that is not valid for the new
+    /// type
+    pub async fn change_column_type(
+        &self,
+        name: &str,
+        new_type: DataType,
+    ) -> Result<Column, Error> {
+        let mut tx = self.pool.begin().await?;
 
-        for (key, value) in data.iter() {
-            keys.push(key.to_string());
-            values.push(value.to_string());
+        let columns = self.fetch_columns_on(&mut tx).await?;
+        let Some(column) = columns.iter().find(|c| c.name == name) else {
+            tx.rollback().await?;
+            return Err(Error::ColumnNotFound(name.to_string()));
+        };
+        if column.column_type == new_type {
+            tx.rollback().await?;
+            return Ok(column.clone());
         }
 
-        // make sure all of the columns exist
-        let columns = self.get_or_create_columns(&keys).await?;
-        let names: Vec<String> = vec!["__timestamp__"]
-            .into_iter()
-            .map(|x| x.to_string())
-            .chain(columns.iter().map(|c| c.encoded.clone()))
-            .collect();
+        let mut errors = ValidationErrors::default();
+        for violation in self
+            .find_type_violations_on(&mut tx, name, new_type)
+            .await?
+        {
+            errors.push(
+                name,
+                RULE_TYPE,
+                format!(
+                    "Datapoint {} has the value '{}', which is not of type {}",
+                    violation.id,
+                    violation.value,
+                    new_type.to_sql()
+                ),
+            );
+        }
+        if let Err(errors) = errors.into_result() {
+            tx.rollback().await?;
+            return Err(Error::Validation(errors));
+        }
 
-        let query = self.generate_query(&names);
-        let now = Utc::now().timestamp();
+        let backend = self.backend();
+        let sql_type = new_type.to_sql_for(backend);
 
-        values
-            .iter()
-            .fold(sqlx::query(&query).bind(now), |query, value| query.bind(value))
-            .execute(&self.pool)
-            .await?;
+        match backend {
+            Backend::Sqlite => {
+                let changed: Vec<Column> = columns
+                    .iter()
+                    .map(|c| match c.name == name {
+                        true => Column {
+                            column_type: new_type,
+                            ..c.clone()
+                        },
+                        false => c.clone(),
+                    })
+                    .collect();
+                let select = std::iter::once("__timestamp__".to_string())
+                    .chain(changed.iter().map(|c| match c.name == name {
+                        true => format!("CAST({} AS {})", c.quoted(), sql_type),
+                        false => c.quoted(),
+                    }))
+                    .collect::<Vec<String>>()
+                    .join(",");
 
-        Ok(())
-    }
+                self.rebuild_table_from(&mut tx, &changed, &select).await?;
+            }
+            Backend::Postgres => {
+                // Casts between text and bytes would use the escaped form of the bytes
+                let value = match column.column_type {
+                    DataType::Raw => format!("convert_from({}, 'UTF8')", column.quoted()),
+                    _ => column.quoted(),
+                };
+                let converted = match new_type {
+                    DataType::Raw => format!("convert_to(CAST({} AS TEXT), 'UTF8')", value),
+                    _ => format!("CAST({} AS {})", value, sql_type),
+                };
 
-    /// All datapoints from the project
-    pub async fn get_data(&self) -> Result<Vec<HashMap<String, Option<String>>>, sqlx::Error> {
-        let query = format!(
+                sqlx::query(&format!(
+                    "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}",
+                    self.table(),
+                    column.quoted(),
+                    sql_type,
+                    converted
+                ))
+                .execute(&mut *tx)
+                .await?;
+            }
+            Backend::MySql => {
+                sqlx::query(&format!(
+                    "ALTER TABLE {} MODIFY COLUMN {} {}",
+                    self.table(),
+                    column.quoted(),
+                    sql_type
+                ))
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        sqlx::query(&backend.sql(
             r#"
-            SELECT * FROM {}
+            UPDATE columns SET column_type = ?
+            WHERE project_id = ? AND name = ?
             "#,
-            self.encoded
-        );
+        ))
+        .bind(new_type.to_sql())
+        .bind(self.id)
+        .bind(name)
+        .execute(&mut *tx)
+        .await?;
+        let raw = self.select_column_on(&mut tx, name).await?;
 
-        let data = sqlx::query(&query)
-            .fetch_all(&self.pool)
-            .await?
-            .into_iter()
-            .map(|row| {
-                let mut map = HashMap::new();
-                for column in row.columns() {
-                    if column.name() == "__timestamp__" {
-                        continue;
-                    }
-                    map.insert(column.name().to_string(), row.get(column.ordinal()));
-                }
-                map
-            })
-            .collect();
+        tx.commit().await?;
+        self.invalidate_columns();
 
-        Ok(data)
+        Ok(Column::from_raw(raw)?)
     }
 
-    /// Generate sql query for inserting data into the project table
-    fn generate_query(&self, encoded_names: &[String]) -> String {
-        format!(
-            r#"
-            INSERT INTO {} ({})
-            VALUES ({})
-            "#,
-            self.encoded,
-            encoded_names.join(","),
-            encoded_names
-                .iter()
-                .map(|_| "?")
-                .collect::<Vec<&str>>()
-                .join(",")
-        )
-    }
+    /// Adds the [EXTRA_COLUMN] if the project does not have it yet
+    ///
+    /// Its name is reserved, so it can not be created through [Project::create_column]. Used to
+    /// restore projects whose sparse columns were collapsed.
+    pub(crate) async fn get_or_create_extra_column(&self) -> Result<(), Error> {
+        if self
+            .get_columns()
+            .await?
+            .iter()
+            .any(|c| c.name == EXTRA_COLUMN)
+        {
+            return Ok(());
+        }
 
-    /// Will verify that all the given keys correspond with a column in the database, creating any
-    /// columns that do not exist. Returning an array of columns, guaranteed to be in the same
-    /// order as the keys
-    async fn get_or_create_columns(&self, keys: &[String]) -> Result<Vec<Column>, sqlx::Error> {
-        // Get existing columns
-        let pre = self.get_columns().await?;
-        let mut columns = HashMap::with_capacity(pre.len());
+        let mut tx = self.pool.begin().await?;
+        self.add_column_on(&mut tx, EXTRA_COLUMN, DataType::Text)
+            .await?;
+        self.insert_column_on(&mut tx, EXTRA_COLUMN, EXTRA_COLUMN, DataType::Text)
+            .await?;
+        tx.commit().await?;
+        self.invalidate_columns();
 
-        // Insert all columns into a hashmap for easy access
-        // and faster lookup
-        pre.into_iter().for_each(|c| {
-            columns.insert(c.name.clone(), c);
-        });
+        Ok(())
+    }
 
-        let mut result = Vec::with_capacity(keys.len());
+    /// Remove the cached columns of the project after changing them
+    pub(crate) fn invalidate_columns(&self) {
+        if let Some(cache) = &self.cache {
+            cache.remove_columns(self.id);
+        }
+    }
 
-        // Check if the columns exist, if not create them
-        // Add all columns to result vector in the same order as the keys
-        for key in keys {
-            match columns.remove(key) {
-                Some(c) => result.push(c),
-                None => {
-                    let column = self.create_column(key, DataType::Text).await?;
-                    result.push(column);
-                }
-            }
+    /// Encode a column name, checking that it fits in the maximum identifier length
+    ///
+    /// Names that encode to one of the columns managed by fkit itself, the [REQUIRED_COLUMNS]
+    /// and the [EXTRA_COLUMN], are rejected with [Error::Reserved].
+    fn encode_column_name(&self, name: &str) -> Result<String, Error> {
+        let encoded_name = encode_identifier(name)?;
+        if REQUIRED_COLUMNS
+            .iter()
+            .chain([&EXTRA_COLUMN])
+            .any(|reserved| reserved.eq_ignore_ascii_case(&encoded_name))
+        {
+            return Err(Error::Reserved(name.to_string()));
+        }
+        if encoded_name.len() > self.max_column_name_len {
+            return Err(Error::ColumnNameTooLong {
+                name: name.to_string(),
+                max: self.max_column_name_len,
+            });
         }
 
-        Ok(result)
+        Ok(encoded_name)
     }
-}
 
-impl Column {
-    /// Convert a RawColumn to a Column
+    /// Recreates the project table with only the given columns, copying over their data
     ///
-    /// # Returns
-    /// Ok(Column) if the conversion was successful
-    /// Err(sqlx::Error::Decode) if conversion failed
-    pub fn from_raw(raw: RawColumn) -> Result<Column, sqlx::Error> {
-        let created_at = DateTime::from_timestamp(raw.created_at, 0)
-            .ok_or_else(|| sqlx::Error::Decode("Invalid timestamp".into()))?;
+    /// Only used on SQLite, where older versions have no other way to drop a column. The rebuilt
+    /// table always has an [ID_COLUMN], tables created before it existed get one holding their
+    /// rowids.
+    async fn rebuild_table(
+        &self,
+        conn: &mut AnyConnection,
+        columns: &[Column],
+    ) -> Result<(), sqlx::Error> {
+        self.rebuild_table_from(conn, columns, &column_list(columns))
+            .await
+    }
 
-        Ok(Column {
-            column_type: DataType::from_sql(&raw.column_type)
-                .ok_or_else(|| sqlx::Error::Decode("Invalid datatype".into()))?,
-            name: raw.name,
-            encoded: raw.encoded,
-            project_id: raw.project_id,
-            created_at,
-        })
+    /// Like [Project::rebuild_table], but filling the columns from a select list, which starts
+    /// with the timestamp followed by an expression for every column
+    async fn rebuild_table_from(
+        &self,
+        conn: &mut AnyConnection,
+        columns: &[Column],
+        select: &str,
+    ) -> Result<(), sqlx::Error> {
+        let rebuilt = quote_identifier(&format!("{}__rebuild", self.encoded));
+        let definitions: String = columns
+            .iter()
+            .map(|c| {
+                format!(
+                    ", {} {}",
+                    c.quoted(),
+                    c.column_type.to_sql_for(Backend::Sqlite)
+                )
+            })
+            .collect();
+        let list = column_list(columns);
+
+        sqlx::query(&format!(
+            "CREATE TABLE {} ({}, __timestamp__ BIGINT NOT NULL, {} BIGINT{})",
+            rebuilt,
+            id_column_definition(Backend::Sqlite),
+            UPDATED_AT_COLUMN,
+            definitions
+        ))
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO {} ({}, {}, {}) SELECT rowid, {2}, {} FROM {}",
+            rebuilt,
+            ID_COLUMN,
+            UPDATED_AT_COLUMN,
+            list,
+            select,
+            self.table()
+        ))
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(&format!("DROP TABLE {}", self.table()))
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(&format!(
+            "ALTER TABLE {} RENAME TO {}",
+            rebuilt,
+            self.table()
+        ))
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
     }
-}
 
-impl DataType {
-    /// Convert the data type to a string for SQL
+    /// Number of non-null values in every column, keyed by column name
     ///
-    /// # Example
+    /// # Examples
     /// ```rust
-    /// # use database::project::DataType;
-    /// let data_type = DataType::Text;
-    /// assert_eq!(data_type.to_sql(), "TEXT");
-    /// ```
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:column_usage?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
     ///
-    /// # Returns
-    /// A sql type as a string
-    pub fn to_sql(&self) -> &str {
-        match self {
-            DataType::Text => "TEXT",
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let usage = project.column_usage().await?;
+    /// assert_eq!(usage["bar"], 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn column_usage(&self) -> Result<HashMap<String, i64>, Error> {
+        let columns = self.get_columns().await?;
+        if columns.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let counts = columns
+            .iter()
+            .map(|c| format!("COUNT({})", c.quoted()))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let row = sqlx::query(&format!("SELECT {} FROM {}", counts, self.table()))
+            .fetch_one(&self.pool)
+            .await?;
+
+        let usage = columns
+            .into_iter()
+            .enumerate()
+            .map(|(index, column)| Ok((column.name, row.try_get::<i64, _>(index)?)))
+            .collect::<Result<_, sqlx::Error>>()?;
+
+        Ok(usage)
+    }
+
+    /// Drops every column that does not hold a single value
+    ///
+    /// Useful for cleaning up columns that were created by mistake, like typos in query keys.
+    /// Required columns are never dropped.
+    ///
+    /// # Returns
+    /// Names of the dropped columns
+    pub async fn drop_empty_columns(&self) -> Result<Vec<String>, Error> {
+        let mut empty: Vec<String> = self
+            .column_usage()
+            .await?
+            .into_iter()
+            .filter(|(name, count)| *count == 0 && !REQUIRED_COLUMNS.contains(&name.as_str()))
+            .map(|(name, _)| name)
+            .collect();
+        empty.sort();
+
+        for name in &empty {
+            self.delete_column(name).await?;
+        }
+
+        Ok(empty)
+    }
+
+    /// Names of the columns that hold a value in less than `threshold` of the datapoints,
+    /// sorted by name
+    ///
+    /// These are the columns [Project::collapse_sparse_columns] would collapse, so this can be
+    /// used to preview it. Required columns and the [EXTRA_COLUMN] are never sparse, and nothing
+    /// is sparse in a project without datapoints.
+    pub async fn sparse_columns(&self, threshold: f64) -> Result<Vec<String>, Error> {
+        let datapoints = self.datapoint_count().await?;
+        if datapoints == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut sparse: Vec<String> = self
+            .column_usage()
+            .await?
+            .into_iter()
+            .filter(|(name, count)| {
+                name != EXTRA_COLUMN
+                    && !REQUIRED_COLUMNS.contains(&name.as_str())
+                    && (*count as f64 / datapoints as f64) < threshold
+            })
+            .map(|(name, _)| name)
+            .collect();
+        sparse.sort();
+
+        Ok(sparse)
+    }
+
+    /// Moves every sparse column into a single [EXTRA_COLUMN] and drops the originals
+    ///
+    /// Projects where every datapoint uses different keys end up with lots of columns that are
+    /// mostly NULL. Collapsing them stores the values of every datapoint as a json object keyed
+    /// by column name, typed like in [Project::get_data_json]. NULL values are left out. When
+    /// the extra column already exists the values are added to the object it holds.
+    ///
+    /// Everything happens in a single transaction. Use [Project::sparse_columns] to see what
+    /// would be collapsed without changing anything.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:collapse_sparse_columns?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// project.add_datapoint(HashMap::from([("bar".to_string(), "1".to_string())])).await?;
+    /// project.add_datapoint(HashMap::from([("baz".to_string(), "2".to_string())])).await?;
+    ///
+    /// assert_eq!(project.sparse_columns(0.75).await?, ["bar", "baz"]);
+    /// assert_eq!(project.collapse_sparse_columns(0.75).await?, ["bar", "baz"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// Names of the collapsed columns
+    pub async fn collapse_sparse_columns(&self, threshold: f64) -> Result<Vec<String>, Error> {
+        let names = self.sparse_columns(threshold).await?;
+        if names.is_empty() {
+            return Ok(names);
+        }
+
+        let backend = self.backend();
+        let (sparse, mut remaining): (Vec<Column>, Vec<Column>) = self
+            .get_columns()
+            .await?
+            .into_iter()
+            .partition(|c| names.contains(&c.name));
+        let extra = remaining.iter().find(|c| c.name == EXTRA_COLUMN).cloned();
+
+        let mut tx = self.pool.begin().await?;
+
+        // Read the values first, the extra column may not exist yet
+        let selected = std::iter::once(id_column(backend).to_string())
+            .chain(
+                extra
+                    .iter()
+                    .map(|c| format!("COALESCE({}, '')", c.quoted())),
+            )
+            .chain(sparse.iter().map(|c| typed_column(c, backend)))
+            .collect::<Vec<String>>()
+            .join(",");
+        let any_value = sparse
+            .iter()
+            .map(|c| format!("{} IS NOT NULL", c.quoted()))
+            .collect::<Vec<String>>()
+            .join(" OR ");
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM {} WHERE {}",
+            selected,
+            self.table(),
+            any_value
+        ))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let offset = 1 + extra.is_some() as usize;
+        let mut objects = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let id: i64 = row.try_get(0)?;
+
+            let mut object = serde_json::Map::new();
+            if extra.is_some() {
+                // Selected as an empty string if it is NULL
+                let existing: String = row.try_get(1)?;
+                if !existing.is_empty() {
+                    match serde_json::from_str(&existing) {
+                        Ok(serde_json::Value::Object(existing)) => object = existing,
+                        // Keep anything that is not an object instead of losing it
+                        _ => {
+                            object.insert(EXTRA_COLUMN.to_string(), existing.into());
+                        }
+                    }
+                }
+            }
+
+            for (index, column) in sparse.iter().enumerate() {
+                if let Some(value) = Value::read(row, offset + index, column.column_type)? {
+                    object.insert(column.name.clone(), value.to_json());
+                }
+            }
+
+            objects.push((id, serde_json::Value::Object(object).to_string()));
+        }
+
+        if extra.is_none() {
+            self.add_column_on(&mut tx, EXTRA_COLUMN, DataType::Text)
+                .await?;
+            let raw = self
+                .insert_column_on(&mut tx, EXTRA_COLUMN, EXTRA_COLUMN, DataType::Text)
+                .await?;
+            remaining.push(Column::from_raw(raw)?);
+        }
+
+        let update = backend.sql(&format!(
+            "UPDATE {} SET {} = ? WHERE {} = ?",
+            self.table(),
+            quote_identifier(EXTRA_COLUMN),
+            id_column(backend)
+        ));
+        for (id, object) in objects {
+            sqlx::query(&update)
+                .bind(object)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for column in &sparse {
+            let dropped = sqlx::query(&format!(
+                "ALTER TABLE {} DROP COLUMN {}",
+                self.table(),
+                column.quoted()
+            ))
+            .execute(&mut *tx)
+            .await;
+
+            match dropped {
+                Ok(_) => {}
+                Err(e) if backend == Backend::Sqlite && is_syntax_error(&e) => {
+                    // Rebuilding drops every sparse column at once
+                    self.rebuild_table(&mut tx, &remaining).await?;
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        for column in &sparse {
+            sqlx::query(&backend.sql("DELETE FROM columns WHERE project_id = ? AND name = ?"))
+                .bind(self.id)
+                .bind(&column.name)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        self.invalidate_columns();
+
+        Ok(names)
+    }
+
+    /// Alters the table of a given project to add a new column with the given name
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:create_column?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// project.add_column("bar", DataType::Text).await?;
+    /// # Ok(())
+    /// # }
+    /// ````
+    /// -- Table schema is now:
+    /// CREATE TABLE foo (__id__ INTEGER PRIMARY KEY AUTOINCREMENT, __timestamp__ BIGINT NOT NULL, __updated_at__ BIGINT, bar TEXT);
+    #[tracing::instrument(level = "debug", skip(self), fields(project = %self.encoded))]
+    pub async fn add_column(&self, encoded_name: &str, column_type: DataType) -> Result<(), Error> {
+        let mut conn = self.pool.acquire().await?;
+        self.add_column_on(&mut conn, encoded_name, column_type)
+            .await
+    }
+
+    /// Adds the column to the project table using the given connection
+    async fn add_column_on(
+        &self,
+        conn: &mut AnyConnection,
+        encoded_name: &str,
+        column_type: DataType,
+    ) -> Result<(), Error> {
+        sqlx::query(&format!(
+            r#"
+            ALTER TABLE {} ADD COLUMN {} {}
+            "#,
+            self.table(),
+            quote_identifier(encoded_name),
+            column_type.to_sql_for(self.backend())
+        ))
+        .execute(conn)
+        .await?;
+
+        tracing::debug!("added column");
+        Ok(())
+    }
+
+    /// Inserts the column into the columns table of the database
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error> {
+    /// let db = Database::new("sqlite:file:create_column?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// project.insert_column("bar", "bar", DataType::Text).await?;
+    /// # Ok(())
+    /// # }
+    #[tracing::instrument(level = "debug", skip(self), fields(project = %self.encoded))]
+    pub async fn insert_column(
+        &self,
+        name: &str,
+        encoded_name: &str,
+        column_type: DataType,
+    ) -> Result<RawColumn, Error> {
+        let mut conn = self.pool.acquire().await?;
+        let column = self
+            .insert_column_on(&mut conn, name, encoded_name, column_type)
+            .await;
+        self.invalidate_columns();
+        column
+    }
+
+    /// Inserts the column into the columns table using the given connection, without touching
+    /// the column cache
+    async fn insert_column_on(
+        &self,
+        conn: &mut AnyConnection,
+        name: &str,
+        encoded_name: &str,
+        column_type: DataType,
+    ) -> Result<RawColumn, Error> {
+        let created_at = Utc::now().timestamp();
+        let backend = self.backend();
+        let returning = match backend {
+            Backend::MySql => "",
+            _ => "RETURNING *",
+        };
+        let query = backend.sql(&format!(
+            r#"
+            INSERT INTO columns
+                (project_id, name, encoded, column_type, created_at)
+            VALUES
+                (?, ?, ?, ?, ?)
+            {returning}
+            "#,
+        ));
+        let insert = sqlx::query(&query)
+            .bind(self.id)
+            .bind(name)
+            .bind(encoded_name)
+            .bind(column_type.to_sql())
+            .bind(created_at);
+
+        // MySQL has no RETURNING, the id of the new row is only known to this connection
+        let row = match backend {
+            Backend::MySql => {
+                insert.execute(&mut *conn).await?;
+                sqlx::query("SELECT * FROM columns WHERE id = LAST_INSERT_ID()")
+                    .fetch_one(conn)
+                    .await?
+            }
+            _ => insert.fetch_one(conn).await?,
+        };
+        let column = RawColumn::from_row(&row)?;
+
+        tracing::debug!(created_at, "inserted column");
+        Ok(column)
+    }
+
+    /// Reads the metadata of a column using the given connection, to see changes made in a
+    /// transaction that has not been committed yet
+    async fn select_column_on(
+        &self,
+        conn: &mut AnyConnection,
+        name: &str,
+    ) -> Result<RawColumn, Error> {
+        let column = sqlx::query_as(
+            &self
+                .backend()
+                .sql("SELECT * FROM columns WHERE project_id = ? AND name = ?"),
+        )
+        .bind(self.id)
+        .bind(name)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(column)
+    }
+
+    /// Adds a datapoint to the project
+    ///
+    /// Missing columns are created with the type inferred from their first value, see
+    /// [DataType::infer]. Existing columns keep their type.
+    ///
+    /// Also updates the last activity time of the project, in the same transaction as the insert
+    ///
+    /// # Returns
+    /// The timestamp the datapoint was stored under
+    pub async fn add_datapoint(
+        &self,
+        data: HashMap<String, String>,
+    ) -> Result<DateTime<Utc>, Error> {
+        self.add_datapoint_with_types(data, &HashMap::new()).await
+    }
+
+    /// Adds a datapoint to the project, creating missing columns with the given types
+    ///
+    /// Columns without a type hint have their type inferred from the value. The hints are only
+    /// used when a column is created, existing columns keep their type.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:add_datapoint_with_types?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("zip".to_string(), "01234".to_string());
+    ///
+    /// let mut types = HashMap::new();
+    /// types.insert("zip".to_string(), DataType::Text);
+    /// project.add_datapoint_with_types(data, &types).await?;
+    ///
+    /// assert_eq!(project.get_columns().await?[0].column_type, DataType::Text);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn add_datapoint_with_types(
+        &self,
+        data: HashMap<String, String>,
+        types: &HashMap<String, DataType>,
+    ) -> Result<DateTime<Utc>, Error> {
+        self.check_quota().await?;
+        let (keys, values): (Vec<String>, Vec<String>) = data.into_iter().unzip();
+        let column_types = column_types(&keys, &values, types);
+
+        // Concurrent requests adding columns lock the schema on SQLite, every step is retried
+        retry_locked(|| async {
+            // make sure all of the columns exist
+            let columns = self.get_or_create_columns(&column_types).await?;
+
+            Ok(self
+                .insert_datapoint(&columns, values.iter().cloned().map(Value::Text).collect())
+                .await?)
+        })
+        .await
+    }
+
+    /// Adds a datapoint to the project, parsing every value as the type of its column
+    ///
+    /// Missing columns are created like in [Project::add_datapoint]. Decimals are parsed and
+    /// stored exactly. Every value is validated before anything is inserted, and all violations
+    /// are returned together as [Error::Validation].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType, value::Value};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:add_datapoint_typed?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    /// project.create_column("count", DataType::Integer).await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("count".to_string(), "42".to_string());
+    /// project.add_datapoint_typed(data).await?;
+    ///
+    /// let data = project.get_data_typed().await?;
+    /// assert_eq!(data[0]["count"], Some(Value::Integer(42)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The timestamp the datapoint was stored under.
+    /// Error if any value is not valid for its column or the query failed, and
+    /// [Error::ValueTooLarge] if a raw value is larger than the configured limit
+    pub async fn add_datapoint_typed(
+        &self,
+        data: HashMap<String, String>,
+    ) -> Result<DateTime<Utc>, Error> {
+        self.check_quota().await?;
+        let (keys, values): (Vec<String>, Vec<String>) = data.into_iter().unzip();
+
+        // Concurrent requests adding columns lock the schema on SQLite, every step is retried
+        retry_locked(|| async {
+            self.validate_datapoint(&keys, &values)
+                .await?
+                .into_result()
+                .map_err(Error::Validation)?;
+
+            let columns = self
+                .get_or_create_columns(&column_types(&keys, &values, &HashMap::new()))
+                .await?;
+
+            let values = self.parse_values(&columns, &values)?;
+
+            Ok(self.insert_datapoint(&columns, values).await?)
+        })
+        .await
+    }
+
+    /// Parses a value as the type of its column, every value written to a project table goes
+    /// through here
+    ///
+    /// # Returns
+    /// None if the text is not valid for the type of the column, [Error::ValueTooLarge] if it
+    /// is a raw value larger than the configured limit
+    fn parse_value(&self, column: &Column, text: &str) -> Result<Option<Value>, Error> {
+        let value = Value::parse(text, column.column_type);
+
+        if let (Some(Value::Raw(bytes)), Some(max)) = (&value, self.max_blob_bytes) {
+            if bytes.len() > max {
+                return Err(Error::ValueTooLarge {
+                    column: column.name.clone(),
+                    size: bytes.len(),
+                    max,
+                });
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Parses the values of a datapoint as the types of their columns, in the same order
+    ///
+    /// Values are validated against the columns that existed before, but a column created
+    /// concurrently can still end up with another type. Those values fail with
+    /// [Error::Validation] instead of being left out of the row.
+    fn parse_values(&self, columns: &[Column], values: &[String]) -> Result<Vec<Value>, Error> {
+        let mut errors = ValidationErrors::default();
+        let mut parsed = Vec::with_capacity(values.len());
+        for (column, value) in columns.iter().zip(values) {
+            match self.parse_value(column, value)? {
+                Some(value) => parsed.push(value),
+                None => errors.push(
+                    &column.name,
+                    RULE_TYPE,
+                    format!("Expected a value of type {}", column.column_type.to_sql()),
+                ),
+            }
+        }
+
+        errors.into_result().map_err(Error::Validation)?;
+        Ok(parsed)
+    }
+
+    /// Changes the values of an existing datapoint, found by its row id
+    ///
+    /// Values are validated and missing columns are created like in
+    /// [Project::add_datapoint_typed]. Columns that are left out keep their value, and the
+    /// timestamp of the datapoint is not changed. The time of the change is stored separately,
+    /// see [Project::changes_since].
+    ///
+    /// # Arguments
+    /// * `id` Row id of the datapoint, see [Project::get_data_with_ids]
+    /// * `data` New values keyed by column name
+    ///
+    /// # Returns
+    /// [Error::DatapointNotFound] if the project has no datapoint with the id, nothing is
+    /// created in that case
+    pub async fn update_datapoint(
+        &self,
+        id: i64,
+        data: HashMap<String, String>,
+    ) -> Result<(), Error> {
+        self.check_quota().await?;
+        let exists: i64 = sqlx::query_scalar(&self.backend().sql(&format!(
+            "SELECT COUNT(*) FROM {} WHERE {} = ?",
+            self.table(),
+            id_column(self.backend())
+        )))
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+        if exists == 0 {
+            return Err(Error::DatapointNotFound(id));
+        }
+
+        let (keys, values): (Vec<String>, Vec<String>) = data.into_iter().unzip();
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        self.validate_datapoint(&keys, &values)
+            .await?
+            .into_result()
+            .map_err(Error::Validation)?;
+
+        let columns = self
+            .get_or_create_columns(&column_types(&keys, &values, &HashMap::new()))
+            .await?;
+
+        let backend = self.backend();
+        let assignments = columns
+            .iter()
+            .map(|c| match (c.column_type, backend) {
+                (DataType::Decimal, Backend::Postgres) => {
+                    format!("{} = CAST(? AS NUMERIC)", c.quoted())
+                }
+                _ => format!("{} = ?", c.quoted()),
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        let query = backend.sql(&format!(
+            "UPDATE {} SET {}, {} = ? WHERE {} = ?",
+            self.table(),
+            assignments,
+            UPDATED_AT_COLUMN,
+            id_column(backend)
+        ));
+
+        let result = self
+            .parse_values(&columns, &values)?
+            .into_iter()
+            .fold(sqlx::query(&query), |query, value| value.bind(query))
+            .bind(Utc::now().timestamp())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        match result.rows_affected() {
+            // Deleted since it was checked
+            0 => Err(Error::DatapointNotFound(id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes a single datapoint, found by its row id
+    ///
+    /// Ids are never handed out again, even after the newest datapoint is deleted.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:delete_datapoint?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let (id, _) = project.get_data_with_ids().await?[0];
+    /// assert!(project.delete_datapoint(id).await?);
+    /// assert!(!project.delete_datapoint(id).await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// true if the datapoint was deleted, false if it does not exist
+    pub async fn delete_datapoint(&self, id: i64) -> Result<bool, Error> {
+        let result = sqlx::query(&self.backend().sql(&format!(
+            "DELETE FROM {} WHERE {} = ?",
+            self.table(),
+            id_column(self.backend())
+        )))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Check every key and value of a datapoint against the current columns
+    async fn validate_datapoint(
+        &self,
+        keys: &[String],
+        values: &[String],
+    ) -> Result<ValidationErrors, Error> {
+        let types = self.column_type_map().await?;
+        let frozen = self.is_schema_frozen().await?;
+
+        Ok(check_values(&types, frozen, keys.iter().zip(values)))
+    }
+
+    /// Checks a batch of datapoints against the schema of the project, without inserting them
+    ///
+    /// Rows are checked like [Project::add_datapoint_typed] would, where a new column gets its
+    /// type from the first row that has it. There are no required columns, since the timestamp
+    /// is always set by the database.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, Error};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:validate_rows?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let rows = vec![
+    ///     HashMap::from([("count".to_string(), "1".to_string())]),
+    ///     HashMap::from([("count".to_string(), "many".to_string())]),
+    /// ];
+    ///
+    /// match project.validate_rows(&rows).await {
+    ///     Err(Error::InvalidRows(errors)) => assert_eq!(errors[0].row, 1),
+    ///     _ => panic!("Second row should be invalid"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// [Error::InvalidRows] listing every invalid row, or an error if the query failed
+    pub async fn validate_rows(&self, rows: &[HashMap<String, String>]) -> Result<(), Error> {
+        let types = self.column_type_map().await?;
+        let frozen = self.is_schema_frozen().await?;
+
+        check_rows(types, frozen, rows)
+    }
+
+    /// Type of every current column, keyed by column name
+    async fn column_type_map(&self) -> Result<HashMap<String, DataType>, Error> {
+        Ok(self
+            .get_columns()
+            .await?
+            .into_iter()
+            .map(|c| (c.name, c.column_type))
+            .collect())
+    }
+
+    /// Rejects writes with [Error::QuotaExceeded] once the database has reached its size limit
+    ///
+    /// Only SQLite is checked, where the size is the number of pages times the page size.
+    async fn check_quota(&self) -> Result<(), Error> {
+        if self.max_db_bytes.is_none() {
+            return Ok(());
+        }
+
+        let mut conn = self.pool.acquire().await?;
+        self.check_quota_on(&mut conn).await
+    }
+
+    /// Like [Project::check_quota], using the given connection
+    async fn check_quota_on(&self, conn: &mut AnyConnection) -> Result<(), Error> {
+        let Some(max) = self.max_db_bytes else {
+            return Ok(());
+        };
+        if self.backend() != Backend::Sqlite {
+            return Ok(());
+        }
+
+        let size: i64 = sqlx::query_scalar(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(conn)
+        .await?;
+
+        if size as u64 >= max {
+            return Err(Error::QuotaExceeded { max });
+        }
+
+        Ok(())
+    }
+
+    /// Whether the schema is frozen right now, rather than when the project was fetched
+    pub(crate) async fn is_schema_frozen(&self) -> Result<bool, sqlx::Error> {
+        let mut conn = self.pool.acquire().await?;
+        self.is_schema_frozen_on(&mut conn).await
+    }
+
+    /// Like [Project::is_schema_frozen], using the given connection
+    pub(crate) async fn is_schema_frozen_on(
+        &self,
+        conn: &mut AnyConnection,
+    ) -> Result<bool, sqlx::Error> {
+        let frozen: i64 = sqlx::query_scalar(
+            &self
+                .backend()
+                .sql("SELECT schema_frozen FROM projects WHERE id = ?"),
+        )
+        .bind(self.id)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(frozen != 0)
+    }
+
+    /// Adds many datapoints to the project in a single transaction
+    ///
+    /// Every row is validated like [Project::validate_rows] first, so an invalid row fails the
+    /// whole batch with [Error::InvalidRows]. The columns needed by all rows are then created
+    /// once, with their type inferred like in [Project::add_datapoint], and the rows are inserted
+    /// with multi-row inserts. Either every row is inserted or none are.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:add_datapoints?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let rows = (0..3)
+    ///     .map(|i| HashMap::from([("bar".to_string(), i.to_string())]))
+    ///     .collect();
+    /// assert_eq!(project.add_datapoints(rows).await?, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The number of inserted rows
+    pub async fn add_datapoints(&self, rows: Vec<HashMap<String, String>>) -> Result<usize, Error> {
+        let timestamps = vec![Utc::now().timestamp(); rows.len()];
+        self.add_datapoints_at(rows, &timestamps).await
+    }
+
+    /// Like [Project::add_datapoints], but every row is stored under its own timestamp
+    ///
+    /// Used to bring back datapoints that were added earlier, like when importing an export.
+    /// The last activity time of the project is still set to now.
+    pub(crate) async fn add_datapoints_at(
+        &self,
+        rows: Vec<HashMap<String, String>>,
+        timestamps: &[i64],
+    ) -> Result<usize, Error> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        self.write_datapoints(rows, timestamps, false).await
+    }
+
+    /// Replaces every datapoint of the project with the given rows, in a single transaction
+    ///
+    /// Rows are validated and missing columns created like in [Project::add_datapoints]. If any
+    /// row is invalid the existing data is kept. Useful for syncing a project with a snapshot
+    /// of some other source, running it again with the same rows gives the same data.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:replace_all_data?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let data = HashMap::from([("bar".to_string(), "old".to_string())]);
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let rows = vec![HashMap::from([("bar".to_string(), "new".to_string())])];
+    /// project.replace_all_data(rows).await?;
+    ///
+    /// assert_eq!(project.get_data().await?[0]["bar"], Some("new".to_string()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The number of inserted rows
+    pub async fn replace_all_data(
+        &self,
+        rows: Vec<HashMap<String, String>>,
+    ) -> Result<usize, Error> {
+        let timestamps = vec![Utc::now().timestamp(); rows.len()];
+        self.write_datapoints(rows, &timestamps, true).await
+    }
+
+    /// Inserts the rows under their timestamps in a single transaction, first deleting every
+    /// existing datapoint if `replace` is set
+    async fn write_datapoints(
+        &self,
+        rows: Vec<HashMap<String, String>>,
+        timestamps: &[i64],
+        replace: bool,
+    ) -> Result<usize, Error> {
+        self.check_quota().await?;
+        self.validate_rows(&rows).await?;
+
+        let columns = self.get_or_create_columns(&row_keys(&rows)).await?;
+
+        let now = Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+
+        if replace {
+            sqlx::query(&format!("DELETE FROM {}", self.table()))
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        self.insert_rows(&mut tx, &columns, &rows, timestamps, now)
+            .await?;
+
+        tx.commit().await?;
+        self.touch_cache(now);
+
+        Ok(rows.len())
+    }
+
+    /// Adds many datapoints like [Project::add_datapoints], but everything is written with the
+    /// given connection, including the missing columns
+    ///
+    /// Nothing is committed, so rolling back the transaction the connection belongs to also
+    /// removes the new columns. `columns` and `frozen` are the schema of the project to check
+    /// the rows against. The caches are not touched, they have to be invalidated once the
+    /// transaction is committed.
+    ///
+    /// # Returns
+    /// The number of inserted rows
+    pub(crate) async fn write_datapoints_on(
+        &self,
+        conn: &mut AnyConnection,
+        rows: &[HashMap<String, String>],
+        columns: Vec<Column>,
+        frozen: bool,
+    ) -> Result<usize, Error> {
+        self.check_quota_on(&mut *conn).await?;
+
+        let types = columns
+            .iter()
+            .map(|c| (c.name.clone(), c.column_type))
+            .collect();
+        check_rows(types, frozen, rows)?;
+
+        let keys = row_keys(rows);
+        let mut existing: HashMap<String, Column> =
+            columns.into_iter().map(|c| (c.name.clone(), c)).collect();
+
+        // Reject names that are too long before any column is created
+        for (key, _) in &keys {
+            if !existing.contains_key(key) {
+                self.encode_column_name(key)?;
+            }
+        }
+
+        let mut columns = Vec::with_capacity(keys.len());
+        for (key, column_type) in keys {
+            let column = match existing.remove(&key) {
+                Some(column) => column,
+                None => {
+                    let encoded_name = self.encode_column_name(&key)?;
+                    self.add_column_on(&mut *conn, &encoded_name, column_type)
+                        .await?;
+                    let raw = self
+                        .insert_column_on(&mut *conn, &key, &encoded_name, column_type)
+                        .await?;
+                    Column::from_raw(raw)?
+                }
+            };
+            columns.push(column);
+        }
+
+        let now = Utc::now().timestamp();
+        let timestamps = vec![now; rows.len()];
+        self.insert_rows(conn, &columns, rows, &timestamps, now)
+            .await?;
+
+        Ok(rows.len())
+    }
+
+    /// Creates the missing ones of the given columns and adds the rows, all in one transaction
+    ///
+    /// Rows are checked against the schema including the new columns, a row that fails removes
+    /// the new columns again. Used by imports that know the types of their columns.
+    ///
+    /// # Returns
+    /// The number of inserted rows
+    pub(crate) async fn add_datapoints_with_columns(
+        &self,
+        columns: &[(String, DataType)],
+        rows: &[HashMap<String, String>],
+    ) -> Result<usize, Error> {
+        let mut tx = self.pool.begin().await?;
+        let written = async {
+            let mut existing = self.fetch_columns_on(&mut tx).await?;
+            let frozen = self.is_schema_frozen_on(&mut tx).await?;
+
+            let missing: Vec<(String, DataType)> = columns
+                .iter()
+                .filter(|(name, _)| existing.iter().all(|c| c.name != *name))
+                .cloned()
+                .collect();
+            if !missing.is_empty() {
+                if frozen {
+                    return Err(Error::SchemaFrozen(self.name.clone()));
+                }
+                existing.extend(self.create_columns_on(&mut tx, &missing).await?);
+            }
+
+            match rows.is_empty() {
+                true => Ok(0),
+                false => {
+                    self.write_datapoints_on(&mut tx, rows, existing, frozen)
+                        .await
+                }
+            }
+        }
+        .await;
+
+        // Roll back right away, SQLite keeps the schema locked until a dropped transaction's
+        // connection is used again
+        let inserted = match written {
+            Ok(inserted) => inserted,
+            Err(e) => {
+                tx.rollback().await?;
+                return Err(e);
+            }
+        };
+        tx.commit().await?;
+
+        self.invalidate_columns();
+        if inserted > 0 {
+            self.touch_cache(Utc::now().timestamp());
+        }
+
+        Ok(inserted)
+    }
+
+    /// Inserts the rows under their timestamps with multi-row inserts and sets the last activity
+    /// time of the project to `now`
+    async fn insert_rows(
+        &self,
+        conn: &mut AnyConnection,
+        columns: &[Column],
+        rows: &[HashMap<String, String>],
+        timestamps: &[i64],
+        now: i64,
+    ) -> Result<(), Error> {
+        // Stay below the bind parameter limit, every row also binds its timestamp
+        let chunk_size = (MAX_BIND_PARAMETERS / (columns.len() + 1)).max(1);
+
+        for (chunk, timestamps) in rows.chunks(chunk_size).zip(timestamps.chunks(chunk_size)) {
+            let query = self.generate_query(columns, chunk.len());
+
+            let mut insert = sqlx::query(&query);
+            for (row, timestamp) in chunk.iter().zip(timestamps) {
+                insert = insert.bind(*timestamp);
+                for column in columns {
+                    // Validated above, values of a column created concurrently with another
+                    // type are stored as text
+                    insert = match row.get(&column.name) {
+                        Some(value) => self
+                            .parse_value(column, value)?
+                            .unwrap_or_else(|| Value::Text(value.clone()))
+                            .bind(insert),
+                        None => insert.bind(None::<String>),
+                    };
+                }
+            }
+
+            insert.execute(&mut *conn).await?;
+        }
+
+        sqlx::query(
+            &self
+                .backend()
+                .sql("UPDATE projects SET last_datapoint_at = ? WHERE id = ?"),
+        )
+        .bind(now)
+        .bind(self.id)
+        .execute(conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Inserts the values into the given columns, which must be in the same order, and updates
+    /// the last activity time of the project in the same transaction
+    async fn insert_datapoint(
+        &self,
+        columns: &[Column],
+        values: Vec<Value>,
+    ) -> Result<DateTime<Utc>, sqlx::Error> {
+        let now = Utc::now().timestamp();
+
+        let mut tx = self.pool.begin().await?;
+        self.insert_row(&mut tx, columns, values, now).await?;
+
+        sqlx::query(
+            &self
+                .backend()
+                .sql("UPDATE projects SET last_datapoint_at = ? WHERE id = ?"),
+        )
+        .bind(now)
+        .bind(self.id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.touch_cache(now);
+
+        Ok(DateTime::from_timestamp(now, 0).unwrap_or_default())
+    }
+
+    /// Keep the time of the last datapoint of the cached project up to date
+    fn touch_cache(&self, timestamp: i64) {
+        if let Some(cache) = &self.cache {
+            cache.touch(&self.name, timestamp);
+        }
+    }
+
+    /// Inserts a single row added at the given time, without touching the project itself
+    async fn insert_row(
+        &self,
+        conn: &mut AnyConnection,
+        columns: &[Column],
+        values: Vec<Value>,
+        timestamp: i64,
+    ) -> Result<(), sqlx::Error> {
+        let query = self.generate_query(columns, 1);
+
+        values
+            .into_iter()
+            .fold(sqlx::query(&query).bind(timestamp), |query, value| {
+                value.bind(query)
+            })
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// All datapoints from the project, keyed by column name
+    ///
+    /// Values are read as the data type of their column and returned in their text form, see
+    /// [Project::get_data_typed] for the values themselves.
+    pub async fn get_data(&self) -> Result<Vec<HashMap<String, Option<String>>>, Error> {
+        Ok(self
+            .get_data_with_timestamps()
+            .await?
+            .into_iter()
+            .map(|datapoint| datapoint.values)
+            .collect())
+    }
+
+    /// All datapoints from the project, keyed by column name, along with their row id
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_with_ids?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let (id, values) = &project.get_data_with_ids().await?[0];
+    /// assert_eq!(values["bar"], Some("baz".to_string()));
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "qux".to_string());
+    /// project.update_datapoint(*id, data).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_data_with_ids(
+        &self,
+    ) -> Result<Vec<(i64, HashMap<String, Option<String>>)>, Error> {
+        Ok(self
+            .get_data_with_timestamps()
+            .await?
+            .into_iter()
+            .map(|datapoint| (datapoint.id, datapoint.values))
+            .collect())
+    }
+
+    /// All datapoints from the project along with the time they were added
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_with_timestamps?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let datapoints = project.get_data_with_timestamps().await?;
+    /// assert_eq!(datapoints[0].values["bar"], Some("baz".to_string()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_data_with_timestamps(&self) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let query = format!(
+            r#"
+            SELECT {}, {} FROM {}
+            "#,
+            typed_column_list(&columns, self.backend()),
+            id_column(self.backend()),
+            self.table()
+        );
+
+        sqlx::query(&query)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// A page of datapoints from the project, oldest first
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_paged?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// for i in 0..5 {
+    ///     let mut data = HashMap::new();
+    ///     data.insert("bar".to_string(), i.to_string());
+    ///     project.add_datapoint(data).await?;
+    /// }
+    ///
+    /// let page = project.get_data_paged(2, 4).await?;
+    /// assert_eq!(page.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `limit` Largest number of datapoints to return
+    /// * `offset` Number of datapoints to skip
+    pub async fn get_data_paged(&self, limit: i64, offset: i64) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let query = self.backend().sql(&format!(
+            r#"
+            SELECT {0}, {1} FROM {2}
+            ORDER BY __timestamp__, {1}
+            LIMIT ? OFFSET ?
+            "#,
+            typed_column_list(&columns, self.backend()),
+            id_column(self.backend()),
+            self.table()
+        ));
+
+        sqlx::query(&query)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// Datapoints added within a time range, oldest first
+    ///
+    /// The range includes `from` and excludes `to`, so consecutive ranges never overlap. A range
+    /// where `from` is not before `to` is empty. Datapoints are stored with second precision.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # use chrono::{Duration, Utc};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_between?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// let added = project.add_datapoint(data).await?;
+    ///
+    /// let datapoints = project
+    ///     .get_data_between(added, added + Duration::seconds(1))
+    ///     .await?;
+    /// assert_eq!(datapoints.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_data_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let query = self.backend().sql(&format!(
+            r#"
+            SELECT {0}, {1} FROM {2}
+            WHERE __timestamp__ >= {3} AND __timestamp__ < {3}
+            ORDER BY __timestamp__, {1}
+            "#,
+            typed_column_list(&columns, self.backend()),
+            id_column(self.backend()),
+            self.table(),
+            timestamp_placeholder(self.backend())
+        ));
+
+        sqlx::query(&query)
+            .bind(ceil_timestamp(from))
+            .bind(ceil_timestamp(to))
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// A page of the datapoints added within a time range, oldest first
+    ///
+    /// The range is the same as in [Project::get_data_between], the number of datapoints in it
+    /// is returned by [Project::datapoint_count_between].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # use chrono::Duration;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_between_paged?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut added = Vec::new();
+    /// for i in 0..3 {
+    ///     let mut data = HashMap::new();
+    ///     data.insert("bar".to_string(), i.to_string());
+    ///     added.push(project.add_datapoint(data).await?);
+    /// }
+    ///
+    /// let page = project
+    ///     .get_data_between_paged(added[0], added[2] + Duration::seconds(1), 2, 1)
+    ///     .await?;
+    /// assert_eq!(page.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `limit` Largest number of datapoints to return
+    /// * `offset` Number of datapoints in the range to skip
+    pub async fn get_data_between_paged(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let query = self.backend().sql(&format!(
+            r#"
+            SELECT {0}, {1} FROM {2}
+            WHERE __timestamp__ >= {3} AND __timestamp__ < {3}
+            ORDER BY __timestamp__, {1}
+            LIMIT ? OFFSET ?
+            "#,
+            typed_column_list(&columns, self.backend()),
+            id_column(self.backend()),
+            self.table(),
+            timestamp_placeholder(self.backend())
+        ));
+
+        sqlx::query(&query)
+            .bind(ceil_timestamp(from))
+            .bind(ceil_timestamp(to))
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// A page of the datapoints with a value of a numeric column within a range, oldest first
+    ///
+    /// Both bounds are inclusive and either can be left out for a range that is open on that
+    /// side. Datapoints without a value for the column are never in the range. Decimals are
+    /// stored as text on SQLite, there they are compared as floats. `between` further limits the
+    /// datapoints to a time range like in [Project::get_data_between]. The number of datapoints
+    /// in the ranges is returned by [Project::datapoint_count_range].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_range?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    /// project.create_column("bar", DataType::Integer).await?;
+    ///
+    /// for i in 0..5 {
+    ///     let mut data = HashMap::new();
+    ///     data.insert("bar".to_string(), i.to_string());
+    ///     project.add_datapoint(data).await?;
+    /// }
+    ///
+    /// let (min, max) = (Some("1".to_string()), Some("3".to_string()));
+    /// let datapoints = project
+    ///     .get_data_range("bar", min.clone(), max.clone(), None, 2, 0)
+    ///     .await?;
+    /// assert_eq!(datapoints.len(), 2);
+    /// assert_eq!(project.datapoint_count_range("bar", min, max, None).await?, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `limit` Largest number of datapoints to return
+    /// * `offset` Number of datapoints in the ranges to skip
+    ///
+    /// # Errors
+    /// [Error::ColumnNotFound] if the project has no column with the name,
+    /// [Error::NotNumeric] if the column can not be compared as numbers and
+    /// [Error::Validation] if a bound is not a value of the column's type
+    pub async fn get_data_range(
+        &self,
+        column: &str,
+        min: Option<String>,
+        max: Option<String>,
+        between: Option<(DateTime<Utc>, DateTime<Utc>)>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let (conditions, values) = self.range_filter(&columns, column, min, max, between)?;
+
+        let backend = self.backend();
+        let query = backend.sql(&format!(
+            r#"
+            SELECT {0}, {1} FROM {2}
+            WHERE {3}
+            ORDER BY __timestamp__, {1}
+            LIMIT ? OFFSET ?
+            "#,
+            typed_column_list(&columns, backend),
+            id_column(backend),
+            self.table(),
+            conditions
+        ));
+
+        let mut query = sqlx::query(&query);
+        for value in values {
+            query = value.bind(query);
+        }
+
+        query
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// Conditions selecting the datapoints in the ranges of [Project::get_data_range], along
+    /// with the values bound to them in order
+    pub(crate) fn range_filter(
+        &self,
+        columns: &[Column],
+        column: &str,
+        min: Option<String>,
+        max: Option<String>,
+        between: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<(String, Vec<Value>), Error> {
+        let target = columns
+            .iter()
+            .find(|c| c.name == column)
+            .ok_or_else(|| Error::ColumnNotFound(column.to_string()))?;
+
+        if !target.column_type.is_numeric() {
+            return Err(Error::NotNumeric(column.to_string()));
+        }
+
+        let mut errors = ValidationErrors::default();
+        let mut bounds = Vec::new();
+        for (bound, operator) in [(min, ">="), (max, "<=")] {
+            let Some(bound) = bound else {
+                continue;
+            };
+
+            match Value::parse(&bound, target.column_type) {
+                Some(value) => bounds.push((value, operator)),
+                None => errors.push(
+                    column,
+                    RULE_TYPE,
+                    format!("Expected a bound of type {}", target.column_type.to_sql()),
+                ),
+            }
+        }
+        errors.into_result().map_err(Error::Validation)?;
+
+        let backend = self.backend();
+        let (compared, placeholder) = match (target.column_type, backend) {
+            (DataType::Decimal, Backend::Sqlite) => (
+                format!("CAST({} AS REAL)", target.quoted()),
+                "CAST(? AS REAL)",
+            ),
+            (DataType::Decimal, Backend::Postgres) => (target.quoted(), "CAST(? AS NUMERIC)"),
+            _ => (target.quoted(), "?"),
+        };
+        let mut conditions = vec![format!("{} IS NOT NULL", target.quoted())];
+        let mut values = Vec::new();
+        for (value, operator) in bounds {
+            conditions.push(format!("{} {} {}", compared, operator, placeholder));
+            values.push(value);
+        }
+
+        if let Some((from, to)) = between {
+            conditions.push(format!(
+                "__timestamp__ >= {0} AND __timestamp__ < {0}",
+                timestamp_placeholder(backend)
+            ));
+            values.push(Value::Integer(ceil_timestamp(from)));
+            values.push(Value::Integer(ceil_timestamp(to)));
+        }
+
+        Ok((conditions.join(" AND "), values))
+    }
+
+    /// Datapoints added or changed at or after a time, least recently changed first
+    ///
+    /// Meant for pulling only what changed since the last pull. Datapoints are stored with
+    /// second precision, so datapoints added or changed in the same second as `since` are
+    /// included, even if they were already pulled. Deduplicate them by id, otherwise changes
+    /// made right after a pull in the same second would be lost. Deleted datapoints are not
+    /// reported.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # use chrono::Duration;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:changes_since?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// let added = project.add_datapoint(data).await?;
+    ///
+    /// assert_eq!(project.changes_since(added).await?.len(), 1);
+    /// assert!(project.changes_since(added + Duration::seconds(1)).await?.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let id = id_column(self.backend());
+        let query = self.backend().sql(&format!(
+            r#"
+            SELECT {}, {} FROM {}
+            WHERE __timestamp__ >= {4} OR {3} >= ?
+            ORDER BY COALESCE({3}, {5}), {1}
+            "#,
+            typed_column_list(&columns, self.backend()),
+            id,
+            self.table(),
+            UPDATED_AT_COLUMN,
+            timestamp_placeholder(self.backend()),
+            timestamp_seconds(self.backend())
+        ));
+
+        sqlx::query(&query)
+            .bind(since.timestamp())
+            .bind(since.timestamp())
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// A page of the datapoints added or changed at or after a time, least recently changed
+    /// first
+    ///
+    /// The datapoints are the same as in [Project::changes_since], the number of them is
+    /// returned by [Project::change_count_since].
+    ///
+    /// # Arguments
+    /// * `limit` Largest number of datapoints to return
+    /// * `offset` Number of changed datapoints to skip
+    pub async fn changes_since_paged(
+        &self,
+        since: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Datapoint>, Error> {
+        let columns = self.get_columns().await?;
+        let query = self.backend().sql(&format!(
+            r#"
+            SELECT {}, {} FROM {}
+            WHERE __timestamp__ >= {4} OR {3} >= ?
+            ORDER BY COALESCE({3}, {5}), {1}
+            LIMIT ? OFFSET ?
+            "#,
+            typed_column_list(&columns, self.backend()),
+            id_column(self.backend()),
+            self.table(),
+            UPDATED_AT_COLUMN,
+            timestamp_placeholder(self.backend()),
+            timestamp_seconds(self.backend())
+        ));
+
+        sqlx::query(&query)
+            .bind(since.timestamp())
+            .bind(since.timestamp())
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| Datapoint::from_row(row, &columns))
+            .collect()
+    }
+
+    /// All datapoints from the project with every value read as the type of its column
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType, value::Value};
+    /// # use std::collections::HashMap;
+    /// # use rust_decimal::Decimal;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_typed?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    /// project.create_column("price", DataType::Decimal).await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("price".to_string(), "19.99".to_string());
+    /// project.add_datapoint_typed(data).await?;
+    ///
+    /// let data = project.get_data_typed().await?;
+    /// assert_eq!(data[0]["price"], Some(Value::Decimal(Decimal::new(1999, 2))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_data_typed(&self) -> Result<Vec<HashMap<String, Option<Value>>>, Error> {
+        let columns = self.get_columns().await?;
+        let query = format!(
+            r#"
+            SELECT {} FROM {}
+            "#,
+            typed_column_list(&columns, self.backend()),
+            self.table()
+        );
+
+        sqlx::query(&query)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| {
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(index, column)| {
+                        let value = Value::read(row, index + 1, column.column_type)?;
+                        Ok((column.name.clone(), value))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// All datapoints from the project as a json array of objects, with the values typed by
+    /// their column
+    ///
+    /// Values are converted with [Value::to_json], so integers and floats are numbers and raw
+    /// data is base64. NULL values are null.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # use std::collections::HashMap;
+    /// # use serde_json::json;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_json?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    /// project.create_column("count", DataType::Integer).await?;
+    ///
+    /// let data = HashMap::from([("count".to_string(), "42".to_string())]);
+    /// project.add_datapoint(data).await?;
+    ///
+    /// assert_eq!(project.get_data_json().await?, json!([{ "count": 42 }]));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_data_json(&self) -> Result<serde_json::Value, Error> {
+        let rows = self
+            .get_data_typed()
+            .await?
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|(name, value)| {
+                        let value = value.map_or(serde_json::Value::Null, |v| v.to_json());
+                        (name, value)
+                    })
+                    .collect::<serde_json::Map<String, serde_json::Value>>()
+                    .into()
+            })
+            .collect();
+
+        Ok(serde_json::Value::Array(rows))
+    }
+
+    /// Generate sql query for inserting datapoints into the given columns of the project table
+    ///
+    /// Every row binds its timestamp followed by a value for every column. Postgres will not
+    /// implicitly convert the text that decimals are bound as, so those placeholders are cast.
+    fn generate_query(&self, columns: &[Column], rows: usize) -> String {
+        let backend = self.backend();
+        let placeholders = std::iter::once(timestamp_placeholder(backend))
+            .chain(columns.iter().map(|c| match (c.column_type, backend) {
+                (DataType::Decimal, Backend::Postgres) => "CAST(? AS NUMERIC)",
+                _ => "?",
+            }))
+            .collect::<Vec<&str>>()
+            .join(",");
+
+        backend.sql(&format!(
+            r#"
+            INSERT INTO {} ({})
+            VALUES {}
+            "#,
+            self.table(),
+            column_list(columns),
+            vec![format!("({})", placeholders); rows].join(",")
+        ))
+    }
+
+    /// Will verify that all the given keys correspond with a column in the database, creating any
+    /// columns that do not exist with the type they are paired with. Returning an array of
+    /// columns, guaranteed to be in the same order as the keys
+    pub(crate) async fn get_or_create_columns(
+        &self,
+        keys: &[(String, DataType)],
+    ) -> Result<Vec<Column>, Error> {
+        // Get existing columns, from the database if the cache is missing any of them, since
+        // they may have been created since the cache was filled
+        let mut pre = self.get_columns().await?;
+        let missing = keys
+            .iter()
+            .any(|(key, _)| pre.iter().all(|c| c.name != *key));
+        if missing && self.cache.is_some() {
+            self.invalidate_columns();
+            pre = self.get_columns().await?;
+        }
+        let mut columns = HashMap::with_capacity(pre.len());
+
+        // Insert all columns into a hashmap for easy access
+        // and faster lookup
+        pre.into_iter().for_each(|c| {
+            columns.insert(c.name.clone(), c);
+        });
+
+        // Reject names that are too long before any column is created
+        for (key, _) in keys {
+            if !columns.contains_key(key) {
+                self.encode_column_name(key)?;
+            }
+        }
+
+        let mut result = Vec::with_capacity(keys.len());
+
+        // Check if the columns exist, if not create them
+        // Add all columns to result vector in the same order as the keys
+        for (key, column_type) in keys {
+            match columns.remove(key) {
+                Some(c) => result.push(c),
+                None => result.push(self.create_or_fetch_column(key, *column_type).await?),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Creates a column, or fetches it if another request created it in the meantime
+    async fn create_or_fetch_column(
+        &self,
+        name: &str,
+        column_type: DataType,
+    ) -> Result<Column, Error> {
+        match self.create_column(name, column_type).await {
+            Ok(column) => Ok(column),
+            Err(e) => {
+                self.invalidate_columns();
+                self.get_columns()
+                    .await?
+                    .into_iter()
+                    .find(|c| c.name == name)
+                    .ok_or(e)
+            }
+        }
+    }
+}
+
+impl Column {
+    /// Convert a RawColumn to a Column
+    ///
+    /// # Returns
+    /// Ok(Column) if the conversion was successful
+    /// Err(sqlx::Error::Decode) if conversion failed
+    pub fn from_raw(raw: RawColumn) -> Result<Column, sqlx::Error> {
+        let created_at = DateTime::from_timestamp(raw.created_at, 0)
+            .ok_or_else(|| sqlx::Error::Decode("Invalid timestamp".into()))?;
+
+        Ok(Column {
+            column_type: DataType::from_sql(&raw.column_type)
+                .ok_or_else(|| sqlx::Error::Decode("Invalid datatype".into()))?,
+            name: raw.name,
+            encoded: raw.encoded,
+            project_id: raw.project_id,
+            created_at,
+        })
+    }
+
+    /// Quoted name of the column, for use in queries
+    pub(crate) fn quoted(&self) -> String {
+        quote_identifier(&self.encoded)
+    }
+}
+
+impl Datapoint {
+    /// Read a datapoint from a row selected with [typed_column_list] followed by the [id_column]
+    fn from_row(row: &AnyRow, columns: &[Column]) -> Result<Datapoint, Error> {
+        let timestamp = DateTime::from_timestamp(row.try_get(0)?, 0)
+            .ok_or_else(|| sqlx::Error::Decode("Invalid timestamp".into()))?;
+
+        let mut values = HashMap::with_capacity(columns.len());
+        for (index, column) in columns.iter().enumerate() {
+            let value = Value::read(row, index + 1, column.column_type)?;
+            values.insert(column.name.clone(), value.map(|v| v.to_string()));
+        }
+
+        let id = row.try_get(columns.len() + 1)?;
+
+        Ok(Datapoint {
+            id,
+            timestamp,
+            values,
+        })
+    }
+}
+
+/// Pairs every key with the type its column should be created with, the type hint if there is
+/// one or the type inferred from its value otherwise
+fn column_types(
+    keys: &[String],
+    values: &[String],
+    types: &HashMap<String, DataType>,
+) -> Vec<(String, DataType)> {
+    keys.iter()
+        .zip(values)
+        .map(|(key, value)| {
+            let column_type = types
+                .get(key)
+                .copied()
+                .unwrap_or_else(|| DataType::infer(value));
+            (key.clone(), column_type)
+        })
+        .collect()
+}
+
+/// Union of the keys of all rows, typed by the first value seen for each
+fn row_keys(rows: &[HashMap<String, String>]) -> Vec<(String, DataType)> {
+    let mut keys: Vec<(String, DataType)> = Vec::new();
+    for row in rows {
+        let mut row: Vec<(&String, &String)> = row.iter().collect();
+        row.sort();
+        for (key, value) in row {
+            if !keys.iter().any(|(k, _)| k == key) {
+                keys.push((key.clone(), DataType::infer(value)));
+            }
+        }
+    }
+
+    keys
+}
+
+/// Checks a batch of datapoints for a project that does not exist yet
+///
+/// Like [Project::validate_rows] on a project without columns, so every column gets its type
+/// from the first row that has it and the other rows have to match it.
+///
+/// # Example
+/// ```rust
+/// # use database::{project::validate_new_rows, Error};
+/// # use std::collections::HashMap;
+/// let rows = vec![
+///     HashMap::from([("count".to_string(), "1".to_string())]),
+///     HashMap::from([("count".to_string(), "many".to_string())]),
+/// ];
+///
+/// match validate_new_rows(&rows) {
+///     Err(Error::InvalidRows(errors)) => assert_eq!(errors[0].row, 1),
+///     _ => panic!("Second row should be invalid"),
+/// }
+/// ```
+pub fn validate_new_rows(rows: &[HashMap<String, String>]) -> Result<(), Error> {
+    check_rows(HashMap::new(), false, rows)
+}
+
+/// Checks every row against the column types, where a new column gets its type from the first
+/// row that has it
+fn check_rows(
+    mut types: HashMap<String, DataType>,
+    frozen: bool,
+    rows: &[HashMap<String, String>],
+) -> Result<(), Error> {
+    let mut invalid = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let errors = check_values(&types, frozen, row.iter());
+
+        if !frozen {
+            let mut row: Vec<(&String, &String)> = row.iter().collect();
+            row.sort();
+            for (key, value) in row {
+                types
+                    .entry(key.clone())
+                    .or_insert_with(|| DataType::infer(value));
+            }
+        }
+
+        if let Err(errors) = errors.into_result() {
+            invalid.push(RowError { row: index, errors });
+        }
+    }
+
+    match invalid.is_empty() {
+        true => Ok(()),
+        false => Err(Error::InvalidRows(invalid)),
+    }
+}
+
+/// Checks the values of a datapoint against the types of the existing columns
+///
+/// Keys without a column are only invalid if the schema is frozen. Numbers that are too large for
+/// their column are reported separately from values of the wrong type.
+fn check_values<'a>(
+    types: &HashMap<String, DataType>,
+    frozen: bool,
+    data: impl Iterator<Item = (&'a String, &'a String)>,
+) -> ValidationErrors {
+    let mut errors = ValidationErrors::default();
+    for (key, value) in data {
+        match types.get(key) {
+            Some(data_type) => {
+                if is_out_of_range(value, *data_type) {
+                    errors.push(
+                        key,
+                        RULE_OUT_OF_RANGE,
+                        format!(
+                            "Value for {} is out of range for {}, use {} instead",
+                            key,
+                            data_type.to_sql(),
+                            match data_type {
+                                DataType::Float => "TEXT",
+                                _ => "DECIMAL or TEXT",
+                            }
+                        ),
+                    );
+                } else if Value::parse(value, *data_type).is_none() {
+                    errors.push(
+                        key,
+                        RULE_TYPE,
+                        format!("Expected a value of type {}", data_type.to_sql()),
+                    );
+                }
+            }
+            None if frozen => errors.push(
+                key,
+                RULE_SCHEMA_FROZEN,
+                "Column does not exist and the schema is frozen",
+            ),
+            None => {}
+        }
+    }
+
+    errors
+}
+
+/// Whether a value is a number that can not be stored in a column of the data type
+///
+/// Integer columns hold 64 bit integers, so longer runs of digits overflow them. Float columns
+/// only hold finite numbers, which rules out `inf`, `NaN` and anything that rounds to infinity.
+fn is_out_of_range(value: &str, data_type: DataType) -> bool {
+    match data_type {
+        DataType::Integer | DataType::BigInteger => {
+            let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+            !digits.is_empty()
+                && digits.bytes().all(|b| b.is_ascii_digit())
+                && value.parse::<i64>().is_err()
+        }
+        DataType::Float => value.parse::<f64>().is_ok_and(|float| !float.is_finite()),
+        _ => false,
+    }
+}
+
+/// Unix timestamp of a time rounded up to whole seconds, the precision datapoints are stored with
+pub(crate) fn ceil_timestamp(time: DateTime<Utc>) -> i64 {
+    match time.timestamp_subsec_nanos() {
+        0 => time.timestamp(),
+        _ => time.timestamp() + 1,
+    }
+}
+
+/// Whether the database rejected the query as invalid SQL, which is how SQLite reports
+/// statements its version does not support
+fn is_syntax_error(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Database(db) => db.message().contains("syntax error"),
+        _ => false,
+    }
+}
+
+/// Comma separated list of the timestamp followed by the given columns, for inserting or copying
+/// whole datapoints of a project table. Selected datapoints need [typed_column_list] instead
+pub(crate) fn column_list(columns: &[Column]) -> String {
+    std::iter::once("__timestamp__".to_string())
+        .chain(columns.iter().map(Column::quoted))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Column the id of a datapoint is selected from
+///
+/// On SQLite this is the rowid, which the [ID_COLUMN] is an alias of, so tables created before
+/// the id column was added keep working.
+pub(crate) fn id_column(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Sqlite => "rowid",
+        Backend::Postgres | Backend::MySql => ID_COLUMN,
+    }
+}
+
+/// Type of the `__timestamp__` column of a project table
+///
+/// Postgres stores a native `TIMESTAMPTZ`, so the tables work with other SQL tooling. SQLite has
+/// no timestamp type, and the `TIMESTAMP` of MySQL can not hold times after 2038, so both store
+/// unix seconds.
+pub(crate) fn timestamp_column_type(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Postgres => "TIMESTAMPTZ",
+        Backend::Sqlite | Backend::MySql => "BIGINT",
+    }
+}
+
+/// The `__timestamp__` of a datapoint as unix seconds, whatever type the column has
+pub(crate) fn timestamp_seconds(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Postgres => "CAST(EXTRACT(EPOCH FROM __timestamp__) AS BIGINT)",
+        Backend::Sqlite | Backend::MySql => "__timestamp__",
+    }
+}
+
+/// Placeholder for a value of the `__timestamp__` column, bound as unix seconds
+pub(crate) fn timestamp_placeholder(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Postgres => "TO_TIMESTAMP(?)",
+        Backend::Sqlite | Backend::MySql => "?",
+    }
+}
+
+/// Definition of the [ID_COLUMN] when creating a project table
+pub(crate) fn id_column_definition(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Sqlite => "__id__ INTEGER PRIMARY KEY AUTOINCREMENT",
+        Backend::Postgres => "__id__ BIGSERIAL PRIMARY KEY",
+        Backend::MySql => "__id__ BIGINT AUTO_INCREMENT PRIMARY KEY",
+    }
+}
+
+/// Like [column_list], but decimal columns are cast to text where the backend returns them in
+/// a form the generic driver can not decode, and the timestamp is always unix seconds
+///
+/// Integer columns are cast to text on SQLite, the generic driver reads SQLite integers as 32
+/// bits and would truncate anything larger.
+pub(crate) fn typed_column_list(columns: &[Column], backend: Backend) -> String {
+    std::iter::once(format!("{} AS __timestamp__", timestamp_seconds(backend)))
+        .chain(columns.iter().map(|c| typed_column(c, backend)))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// A single column as selected by [typed_column_list]
+pub(crate) fn typed_column(column: &Column, backend: Backend) -> String {
+    match (column.column_type, backend) {
+        (DataType::Decimal, Backend::Postgres) => {
+            format!("CAST({0} AS TEXT) AS {0}", column.quoted())
+        }
+        (DataType::Decimal, Backend::MySql) => format!("CAST({0} AS CHAR) AS {0}", column.quoted()),
+        (DataType::Integer | DataType::BigInteger, Backend::Sqlite) => {
+            format!("CAST({0} AS TEXT) AS {0}", column.quoted())
+        }
+        _ => column.quoted(),
+    }
+}
+
+impl DataType {
+    /// Convert the data type to a string for SQL
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// let data_type = DataType::Text;
+    /// assert_eq!(data_type.to_sql(), "TEXT");
+    /// ```
+    ///
+    /// # Returns
+    /// A sql type as a string
+    pub fn to_sql(&self) -> &str {
+        match self {
+            DataType::Text => "TEXT",
             DataType::Raw => "BLOB",
             DataType::Integer => "INTEGER",
             DataType::BigInteger => "BIGINT",
             DataType::Float => "FLOAT",
+            DataType::Decimal => "DECIMAL",
+        }
+    }
+
+    /// Convert the data type to the name used in api responses, dumps and CSV headers
+    ///
+    /// Every name parses back into the same data type.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// assert_eq!(DataType::Raw.as_api_str(), "raw");
+    /// assert_eq!(DataType::Raw.as_api_str().parse(), Ok(DataType::Raw));
+    /// ```
+    ///
+    /// # Returns
+    /// The lowercase name of the data type
+    pub fn as_api_str(&self) -> &'static str {
+        match self {
+            DataType::Text => "text",
+            DataType::Integer => "integer",
+            DataType::BigInteger => "bigint",
+            DataType::Float => "float",
+            DataType::Decimal => "decimal",
+            DataType::Raw => "raw",
+        }
+    }
+
+    /// Whether values of the data type are numbers, which can be compared by size
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// assert!(DataType::Decimal.is_numeric());
+    /// assert!(!DataType::Text.is_numeric());
+    /// ```
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            DataType::Integer | DataType::BigInteger | DataType::Float | DataType::Decimal
+        )
+    }
+
+    /// Column type used for the data type in project tables of the given backend
+    ///
+    /// [DataType::to_sql] is what gets stored in the column metadata, this is the type the
+    /// table column is actually created with. SQLite stores decimals as TEXT, since its NUMERIC
+    /// affinity would turn them into floats and lose precision. Integers are always 64 bit and
+    /// floats always double precision, MySQL's FLOAT only has single precision.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::{project::DataType, Backend};
+    /// assert_eq!(DataType::Decimal.to_sql_for(Backend::Postgres), "NUMERIC");
+    /// assert_eq!(DataType::Decimal.to_sql_for(Backend::Sqlite), "TEXT");
+    /// assert_eq!(DataType::Text.to_sql_for(Backend::Postgres), "TEXT");
+    /// assert_eq!(DataType::Float.to_sql_for(Backend::MySql), "DOUBLE");
+    /// ```
+    pub fn to_sql_for(&self, backend: Backend) -> &str {
+        match (self, backend) {
+            (DataType::Decimal, Backend::Sqlite) => "TEXT",
+            (DataType::Decimal, Backend::Postgres) => "NUMERIC",
+            (DataType::Decimal, Backend::MySql) => "DECIMAL(65, 30)",
+            (DataType::Integer, Backend::Postgres | Backend::MySql) => "BIGINT",
+            (DataType::Float, Backend::MySql) => "DOUBLE",
+            (DataType::Raw, Backend::Postgres) => "BYTEA",
+            _ => self.to_sql(),
+        }
+    }
+
+    /// Guess the data type of a value
+    ///
+    /// Values that parse as an integer are integers, then finite floats, everything else is
+    /// text.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// assert_eq!(DataType::infer("42"), DataType::Integer);
+    /// assert_eq!(DataType::infer("4.2"), DataType::Float);
+    /// assert_eq!(DataType::infer("forty two"), DataType::Text);
+    /// assert_eq!(DataType::infer("NaN"), DataType::Text);
+    /// ```
+    pub fn infer(value: &str) -> DataType {
+        if value.parse::<i64>().is_ok() {
+            DataType::Integer
+        } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+            DataType::Float
+        } else {
+            DataType::Text
+        }
+    }
+
+    /// Convert a SQL type keyword to a data type
+    ///
+    /// Unlike [DataType::from_sql] this is case insensitive, ignores precision arguments and
+    /// accepts common synonyms, like the backend specific names from [DataType::to_sql_for].
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// assert_eq!(DataType::from_keyword("numeric(10, 2)"), Some(DataType::Decimal));
+    /// assert_eq!(DataType::from_keyword("BYTEA"), Some(DataType::Raw));
+    /// assert_eq!(DataType::from_keyword("NOT_A_TYPE"), None);
+    /// ```
+    ///
+    /// # Returns
+    /// DataType if the keyword is a known sql type or None otherwise
+    pub fn from_keyword(keyword: &str) -> Option<DataType> {
+        let keyword = keyword.split('(').next()?.trim().to_ascii_uppercase();
+
+        match keyword.as_str() {
+            "TEXT" | "VARCHAR" | "CHAR" => Some(DataType::Text),
+            "INTEGER" | "INT" => Some(DataType::Integer),
+            "BIGINT" => Some(DataType::BigInteger),
+            "FLOAT" | "REAL" | "DOUBLE" | "DOUBLE PRECISION" => Some(DataType::Float),
+            "DECIMAL" | "NUMERIC" => Some(DataType::Decimal),
+            "BLOB" | "BYTEA" => Some(DataType::Raw),
+            _ => None,
+        }
+    }
+
+    /// Convert a string from SQL to a data type
+    ///
+    /// If the string is not a valid data type, return None
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// let data_type = DataType::from_sql("TEXT");
+    /// assert_eq!(data_type, Some(DataType::Text));
+    /// ```
+    ///
+    /// ```rust
+    /// # use database::project::DataType;
+    /// let data_type = DataType::from_sql("NOT_A_TYPE");
+    /// assert_eq!(data_type, None);
+    /// ```
+    ///
+    /// # Returns
+    /// DataType if the string is a valid sql type or None otherwise
+    pub fn from_sql(s: &str) -> Option<DataType> {
+        match s {
+            "TEXT" => Some(DataType::Text),
+            "BLOB" => Some(DataType::Raw),
+            "INTEGER" => Some(DataType::Integer),
+            "BIGINT" => Some(DataType::BigInteger),
+            "FLOAT" => Some(DataType::Float),
+            "DECIMAL" => Some(DataType::Decimal),
+            _ => None,
+        }
+    }
+}
+
+/// Type names accepted by [DataType::from_str], listed in parse errors
+const DATA_TYPE_NAMES: &str =
+    "text, string, int, integer, bigint, biginteger, float, double, decimal, numeric, blob, raw";
+
+impl FromStr for DataType {
+    type Err = String;
+
+    /// Parse a user facing type name, like the ones accepted by the api
+    ///
+    /// Names are case insensitive and include common aliases, like `string` for text or
+    /// `double` for float.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::project::DataType;
+    /// assert_eq!("integer".parse(), Ok(DataType::Integer));
+    /// assert_eq!("String".parse(), Ok(DataType::Text));
+    /// assert!("number".parse::<DataType>().is_err());
+    /// ```
+    ///
+    /// # Returns
+    /// The data type, or an error listing the valid names
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" | "string" => Ok(DataType::Text),
+            "int" | "integer" => Ok(DataType::Integer),
+            "bigint" | "biginteger" => Ok(DataType::BigInteger),
+            "float" | "double" => Ok(DataType::Float),
+            "decimal" | "numeric" => Ok(DataType::Decimal),
+            "blob" | "raw" => Ok(DataType::Raw),
+            _ => Err(format!(
+                "Unknown data type '{}', expected one of: {}",
+                s, DATA_TYPE_NAMES
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod methods {
+    use std::collections::HashMap;
+
+    use chrono::DateTime;
+    use serde_json::json;
+
+    use crate::{
+        database::methods::{create_mem_db, create_shared_mem_db},
+        project::DataType,
+        validation::{RULE_OUT_OF_RANGE, RULE_SCHEMA_FROZEN, RULE_TYPE},
+        value::Value,
+        Error,
+    };
+
+    use super::{Column, Datapoint, Project, RawColumn, DEFAULT_MAX_COLUMN_NAME_LEN, EXTRA_COLUMN};
+
+    #[test]
+    fn parse_data_type() {
+        let cases = [
+            ("text", DataType::Text),
+            ("STRING", DataType::Text),
+            ("int", DataType::Integer),
+            ("Integer", DataType::Integer),
+            ("bigint", DataType::BigInteger),
+            ("float", DataType::Float),
+            ("double", DataType::Float),
+            ("decimal", DataType::Decimal),
+            ("blob", DataType::Raw),
+            ("raw", DataType::Raw),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(name.parse::<DataType>(), Ok(expected), "{}", name);
+        }
+
+        let error = "number".parse::<DataType>().unwrap_err();
+        assert!(error.contains("number"));
+        assert!(error.contains("integer"));
+    }
+
+    #[tokio::test]
+    async fn create_column() {
+        let db = create_mem_db("create_column").await;
+        let project = db.create("foo").await;
+        let column = project.create("boo").await;
+
+        let columns = project.get_all().await;
+        assert_eq!(columns.len(), 1);
+
+        assert_eq!(column.name, "boo");
+        assert_eq!(column.encoded, "boo");
+    }
+
+    #[tokio::test]
+    async fn create_columns() {
+        let db = create_mem_db("create_columns").await;
+        let project = db.create("foo").await;
+
+        project.create("boo").await;
+        project.create("bar").await;
+
+        let columns = project.get_all().await;
+        assert_eq!(columns.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn column_name_too_long() {
+        let db = create_mem_db("column_name_too_long").await;
+        let project = db.create("foo").await;
+
+        let name = "a".repeat(DEFAULT_MAX_COLUMN_NAME_LEN + 1);
+        let error = project.create_column(&name, DataType::Text).await;
+        assert!(matches!(error, Err(Error::ColumnNameTooLong { .. })));
+
+        let keys = vec![
+            ("short".to_string(), DataType::Text),
+            (name, DataType::Text),
+        ];
+        let error = project.get_or_create_columns(&keys).await;
+        assert!(matches!(error, Err(Error::ColumnNameTooLong { .. })));
+        assert!(project.get_all().await.is_empty());
+
+        let db = db.with_max_column_name_len(5);
+        let project = db.get("foo").await.unwrap();
+        project
+            .create_column("short", DataType::Text)
+            .await
+            .unwrap();
+        let error = project.rename_column("short", "longer").await;
+        assert!(matches!(error, Err(Error::ColumnNameTooLong { .. })));
+    }
+
+    #[tokio::test]
+    async fn reserved_column_names() {
+        let db = create_mem_db("reserved_column_names").await;
+        let project = db.create("foo").await;
+
+        for name in [
+            "__id__",
+            "__timestamp__",
+            "__updated_at__",
+            "__extra__",
+            "__ID__",
+        ] {
+            let error = project.create_column(name, DataType::Text).await;
+            assert!(matches!(error, Err(Error::Reserved(_))), "{}", name);
+        }
+
+        project.create_column("bar", DataType::Text).await.unwrap();
+        let error = project.rename_column("bar", "__id__").await;
+        assert!(matches!(error, Err(Error::Reserved(_))));
+        assert_eq!(project.get_all().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn column_cache() {
+        let db = create_mem_db("column_cache").await;
+        let project = db.create("foo").await;
+        project.create("a").await;
+        assert_eq!(project.get_all().await.len(), 1);
+
+        // Changes through any copy of the project are seen by all of them
+        let other = db.get("foo").await.unwrap();
+        other.create("b").await;
+        assert_eq!(project.get_all().await.len(), 2);
+        other.rename_column("b", "c").await.unwrap();
+        assert_eq!(project.get_all().await[1].name, "c");
+        other.delete_column("c").await.unwrap();
+        assert_eq!(project.get_all().await.len(), 1);
+
+        // Columns created behind the back of the cache are found instead of created again
+        let uncached = db.clone().with_project_cache(false);
+        uncached.get("foo").await.unwrap().create("d").await;
+        assert_eq!(project.get_all().await.len(), 1);
+
+        let data = HashMap::from([("d".to_string(), "x".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+        assert_eq!(project.get_all().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn max_db_bytes() {
+        let db = create_mem_db("max_db_bytes")
+            .await
+            .with_max_db_bytes(Some(64 * 1024));
+        let project = db.create("foo").await;
+
+        let mut rejected = false;
+        for _ in 0..100 {
+            let data = HashMap::from([("data".to_string(), "x".repeat(4096))]);
+            match project.add_datapoint(data).await {
+                Ok(_) => {}
+                Err(Error::QuotaExceeded { max }) => {
+                    assert_eq!(max, 64 * 1024);
+                    rejected = true;
+                    break;
+                }
+                Err(error) => panic!("unexpected error: {}", error),
+            }
+        }
+        assert!(rejected);
+
+        // Every write is rejected once over the limit
+        let data = HashMap::from([("data".to_string(), "x".to_string())]);
+        let error = project.add_datapoint(data).await;
+        assert!(matches!(error, Err(Error::QuotaExceeded { .. })));
+    }
+
+    #[tokio::test]
+    async fn max_blob_bytes() {
+        let db = create_mem_db("max_blob_bytes")
+            .await
+            .with_max_blob_bytes(Some(16));
+        let project = db.create("foo").await;
+        project.create_column("blob", DataType::Raw).await.unwrap();
+
+        let blob = |size: usize| HashMap::from([("blob".to_string(), "x".repeat(size))]);
+
+        let error = project.add_datapoint_typed(blob(17)).await.unwrap_err();
+        match error {
+            Error::ValueTooLarge { column, size, max } => {
+                assert_eq!(column, "blob");
+                assert_eq!(size, 17);
+                assert_eq!(max, 16);
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+        assert_eq!(project.datapoint_count().await.unwrap(), 0);
+
+        project.add_datapoint_typed(blob(16)).await.unwrap();
+        assert_eq!(project.datapoint_count().await.unwrap(), 1);
+
+        // Every other write path is capped as well
+        let too_large = |error: Result<usize, Error>| {
+            assert!(matches!(error, Err(Error::ValueTooLarge { size: 17, .. })));
+        };
+        too_large(project.add_datapoints(vec![blob(17)]).await);
+        too_large(project.replace_all_data(vec![blob(17)]).await);
+        too_large(db.add_datapoints_atomic("foo", &[blob(17)]).await);
+
+        let id = project.get_data_with_ids().await.unwrap()[0].0;
+        let error = project.update_datapoint(id, blob(17)).await;
+        assert!(matches!(error, Err(Error::ValueTooLarge { .. })));
+        assert_eq!(project.datapoint_count().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn insert_column_fields() {
+        let db = create_mem_db("insert_column_fields").await;
+        db.create("other").await;
+        let project = db.create("foo").await;
+
+        let inserted = project
+            .insert_column("Bar", "bar", DataType::Float)
+            .await
+            .unwrap();
+
+        let raw: RawColumn = sqlx::query_as("SELECT * FROM columns WHERE name = 'Bar'")
+            .fetch_one(&project.pool)
+            .await
+            .unwrap();
+        assert_eq!(raw, inserted);
+
+        assert_eq!(raw.project_id, project.id);
+        assert_eq!(raw.name, "Bar");
+        assert_eq!(raw.encoded, "bar");
+        assert_eq!(raw.column_type, "FLOAT");
+        assert!((raw.created_at - chrono::Utc::now().timestamp()).abs() < 5);
+    }
+
+    #[tokio::test]
+    async fn get_or_create_columns_single() {
+        let db = create_mem_db("get_or_create_columns_single").await;
+        let project = db.create("foo").await;
+
+        let names = vec![("boo".to_string(), DataType::Text)];
+
+        let columns = project.get_or_create_columns(&names).await.unwrap();
+        assert_eq!(columns.len(), 1);
+
+        assert_eq!(columns[0].name, "boo");
+    }
+
+    #[tokio::test]
+    async fn get_or_create_columns_multiple() {
+        let db = create_mem_db("get_or_create_columns_multiple").await;
+        let project = db.create("foo").await;
+
+        let names: Vec<(String, DataType)> = ["boo", "bar", "baz", "foo"]
+            .into_iter()
+            .map(|name| (name.to_string(), DataType::Text))
+            .collect();
+
+        let columns = project.get_or_create_columns(&names).await.unwrap();
+        assert_eq!(columns.len(), 4);
+
+        assert_eq!(columns[0].name, "boo");
+        assert_eq!(columns[1].name, "bar");
+        assert_eq!(columns[2].name, "baz");
+        assert_eq!(columns[3].name, "foo");
+    }
+
+    #[tokio::test]
+    async fn add_single_data() {
+        let db = create_mem_db("add_single_data").await;
+        let project = db.create("foo").await;
+
+        project.create("boo").await;
+
+        let mut data = std::collections::HashMap::new();
+        data.insert("boo".to_string(), "bar".to_string());
+
+        project.add_datapoint(data).await.unwrap();
+        let data = project.get_data().await.unwrap();
+
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].get("boo"), Some(Some("bar".to_string())).as_ref());
+    }
+
+    #[tokio::test]
+    async fn get_data_json() {
+        let db = create_mem_db("get_data_json").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        project
+            .create_column("ratio", DataType::Float)
+            .await
+            .unwrap();
+        project.create("label").await;
+
+        let data = HashMap::from([
+            ("count".to_string(), "42".to_string()),
+            ("ratio".to_string(), "0.5".to_string()),
+            ("label".to_string(), "7".to_string()),
+        ]);
+        project.add_datapoint(data).await.unwrap();
+        let data = HashMap::from([("label".to_string(), "x".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+
+        let data = project.get_data_json().await.unwrap();
+        assert_eq!(
+            data,
+            json!([
+                { "count": 42, "ratio": 0.5, "label": "7" },
+                { "count": null, "ratio": null, "label": "x" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn replace_all_data() {
+        let db = create_mem_db("replace_all_data").await;
+        let project = db.create("foo").await;
+
+        let rows = (0..3)
+            .map(|i| HashMap::from([("count".to_string(), i.to_string())]))
+            .collect();
+        project.add_datapoints(rows).await.unwrap();
+
+        let rows = vec![
+            HashMap::from([("count".to_string(), "10".to_string())]),
+            HashMap::from([("label".to_string(), "new".to_string())]),
+        ];
+        assert_eq!(project.replace_all_data(rows.clone()).await.unwrap(), 2);
+        assert_eq!(project.get_data().await.unwrap().len(), 2);
+        assert_eq!(project.get_all().await.len(), 2);
+
+        // Running it again gives the same data
+        project.replace_all_data(rows).await.unwrap();
+        assert_eq!(project.get_data().await.unwrap().len(), 2);
+
+        // Invalid rows keep the existing data
+        let rows = vec![HashMap::from([("count".to_string(), "many".to_string())])];
+        let error = project.replace_all_data(rows).await;
+        assert!(matches!(error, Err(Error::InvalidRows(_))));
+        assert_eq!(project.get_data().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn integer_round_trip() {
+        let db = create_mem_db("integer_round_trip").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+
+        for count in ["-7", "5000000000"] {
+            let data = HashMap::from([("count".to_string(), count.to_string())]);
+            project.add_datapoint_typed(data).await.unwrap();
+        }
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data[0]["count"], Some("-7".to_string()));
+        assert_eq!(data[1]["count"], Some("5000000000".to_string()));
+
+        let mut counts: Vec<Option<Value>> = project
+            .get_data_typed()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|mut row| row.remove("count").flatten())
+            .collect();
+        counts.sort_by_key(|value| match value {
+            Some(Value::Integer(count)) => *count,
+            _ => i64::MAX,
+        });
+        assert_eq!(
+            counts,
+            [Some(Value::Integer(-7)), Some(Value::Integer(5000000000))]
+        );
+    }
+
+    #[tokio::test]
+    async fn decimal_round_trip() {
+        let db = create_mem_db("decimal_round_trip").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("price", DataType::Decimal)
+            .await
+            .unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("price".to_string(), "3.14159".to_string());
+        project.add_datapoint_typed(data).await.unwrap();
+
+        // More digits than fit in a float
+        let mut data = HashMap::new();
+        data.insert(
+            "price".to_string(),
+            "0.1000000000000000000000000001".to_string(),
+        );
+        project.add_datapoint_typed(data).await.unwrap();
+
+        let data = project.get_data_typed().await.unwrap();
+        assert_eq!(data.len(), 2);
+
+        let prices: Vec<String> = data
+            .iter()
+            .map(|d| match &d["price"] {
+                Some(Value::Decimal(decimal)) => decimal.to_string(),
+                other => panic!("Expected a decimal, got {:?}", other),
+            })
+            .collect();
+        assert!(prices.contains(&"3.14159".to_string()));
+        assert!(prices.contains(&"0.1000000000000000000000000001".to_string()));
+    }
+
+    #[tokio::test]
+    async fn invalid_decimal_rejected() {
+        let db = create_mem_db("invalid_decimal_rejected").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("price", DataType::Decimal)
+            .await
+            .unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("price".to_string(), "three".to_string());
+
+        assert!(project.add_datapoint_typed(data).await.is_err());
+        assert!(project.get_data_typed().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn first_datapoint_defines_types() {
+        let db = create_mem_db("first_datapoint_defines_types").await;
+        let project = db.create("foo").await;
+
+        let mut data = HashMap::new();
+        data.insert("count".to_string(), "1".to_string());
+        data.insert("ratio".to_string(), "0.5".to_string());
+        data.insert("name".to_string(), "a".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        // Later values of another type do not change the column type
+        let mut data = HashMap::new();
+        data.insert("count".to_string(), "many".to_string());
+        data.insert("ratio".to_string(), "2".to_string());
+        data.insert("name".to_string(), "3".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        let types: HashMap<String, DataType> = project
+            .get_all()
+            .await
+            .into_iter()
+            .map(|c| (c.name, c.column_type))
+            .collect();
+        assert_eq!(types["count"], DataType::Integer);
+        assert_eq!(types["ratio"], DataType::Float);
+        assert_eq!(types["name"], DataType::Text);
+
+        let data = project.get_data_typed().await.unwrap();
+        assert_eq!(data.len(), 2);
+        assert!(data.iter().any(|d| d["count"] == Some(Value::Integer(1))));
+        assert!(data.iter().any(|d| d["ratio"] == Some(Value::Float(0.5))));
+    }
+
+    #[tokio::test]
+    async fn type_hints_override_inference() {
+        let db = create_mem_db("type_hints_override_inference").await;
+        let project = db.create("foo").await;
+
+        let mut data = HashMap::new();
+        data.insert("zip".to_string(), "01234".to_string());
+        data.insert("count".to_string(), "5".to_string());
+
+        let mut types = HashMap::new();
+        types.insert("zip".to_string(), DataType::Text);
+        project
+            .add_datapoint_with_types(data, &types)
+            .await
+            .unwrap();
+
+        let types: HashMap<String, DataType> = project
+            .get_all()
+            .await
+            .into_iter()
+            .map(|c| (c.name, c.column_type))
+            .collect();
+        assert_eq!(types["zip"], DataType::Text);
+        assert_eq!(types["count"], DataType::Integer);
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data[0]["zip"], Some("01234".to_string()));
+    }
+
+    #[tokio::test]
+    async fn frozen_schema_rejects_new_columns() {
+        let db = create_mem_db("frozen_schema_rejects_new_columns").await;
+        let project = db.create("foo").await;
+        project.create("bar").await;
+
+        db.freeze_schema("foo").await.unwrap().unwrap();
+
+        // The flag is checked on every insert, not when the project was fetched
+        let mut data = HashMap::new();
+        data.insert("baz".to_string(), "a".to_string());
+        let result = project.add_datapoint(data).await;
+        assert!(matches!(result, Err(Error::SchemaFrozen(name)) if name == "foo"));
+
+        let mut data = HashMap::new();
+        data.insert("bar".to_string(), "a".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        assert_eq!(project.get_all().await.len(), 1);
+        assert_eq!(project.get_data().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn validation_reports_every_field() {
+        let db = create_mem_db("validation_reports_every_field").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        project.create("name").await;
+        db.freeze_schema("foo").await.unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("count".to_string(), "many".to_string());
+        data.insert("name".to_string(), "a".to_string());
+        data.insert("typo".to_string(), "b".to_string());
+
+        let errors = match project.add_datapoint_typed(data).await {
+            Err(Error::Validation(errors)) => errors.errors,
+            other => panic!("Expected validation errors, got {:?}", other),
+        };
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "count");
+        assert_eq!(errors[0].rule, RULE_TYPE);
+        assert_eq!(errors[1].field, "typo");
+        assert_eq!(errors[1].rule, RULE_SCHEMA_FROZEN);
+
+        assert!(project.get_data().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn out_of_range_integer() {
+        let db = create_mem_db("out_of_range_integer").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+
+        let data = HashMap::from([("count".to_string(), "99999999999999999999".to_string())]);
+        let errors = match project.add_datapoint_typed(data).await {
+            Err(Error::Validation(errors)) => errors.errors,
+            other => panic!("Expected validation errors, got {:?}", other),
+        };
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "count");
+        assert_eq!(errors[0].rule, RULE_OUT_OF_RANGE);
+        assert!(errors[0].message.contains("count"));
+        assert!(errors[0].message.contains("DECIMAL"));
+
+        let data = HashMap::from([("count".to_string(), "-9223372036854775808".to_string())]);
+        project.add_datapoint_typed(data).await.unwrap();
+
+        let data = HashMap::from([("count".to_string(), "many".to_string())]);
+        match project.add_datapoint_typed(data).await {
+            Err(Error::Validation(errors)) => assert_eq!(errors.errors[0].rule, RULE_TYPE),
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn out_of_range_float() {
+        let db = create_mem_db("out_of_range_float").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("ratio", DataType::Float)
+            .await
+            .unwrap();
+
+        for value in ["inf", "-inf", "NaN", "1e400"] {
+            let data = HashMap::from([("ratio".to_string(), value.to_string())]);
+            match project.add_datapoint_typed(data).await {
+                Err(Error::Validation(errors)) => {
+                    assert_eq!(errors.errors[0].rule, RULE_OUT_OF_RANGE, "{}", value)
+                }
+                other => panic!("Expected validation errors for {}, got {:?}", value, other),
+            }
+        }
+        assert!(project.get_data().await.unwrap().is_empty());
+
+        let data = HashMap::from([("ratio".to_string(), "1e300".to_string())]);
+        project.add_datapoint_typed(data).await.unwrap();
+
+        assert_eq!(DataType::infer("inf"), DataType::Text);
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_concurrent_columns() {
+        let db = create_shared_mem_db("add_datapoint_concurrent_columns").await;
+        db.create("foo").await;
+
+        // Every datapoint races to create the same new columns
+        let inserts: Vec<_> = (0..8)
+            .map(|i| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    let project = db.get_project("foo").await.unwrap().unwrap();
+                    let data = HashMap::from([
+                        ("count".to_string(), i.to_string()),
+                        ("name".to_string(), format!("n{}", i)),
+                    ]);
+                    project.add_datapoint(data).await
+                })
+            })
+            .collect();
+        for insert in inserts {
+            insert.await.unwrap().expect("Datapoint should be added");
+        }
+
+        let project = db.get_project("foo").await.unwrap().unwrap();
+        assert_eq!(project.get_all().await.len(), 2);
+        assert_eq!(project.get_data().await.unwrap().len(), 8);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints() {
+        let db = create_mem_db("add_datapoints").await;
+        let project = db.create("foo").await;
+
+        let rows = vec![
+            HashMap::from([("a".to_string(), "1".to_string())]),
+            HashMap::from([
+                ("a".to_string(), "2".to_string()),
+                ("b".to_string(), "x".to_string()),
+            ]),
+            HashMap::from([("b".to_string(), "y".to_string())]),
+        ];
+        assert_eq!(project.add_datapoints(rows).await.unwrap(), 3);
+
+        let columns = project.get_all().await;
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].column_type, DataType::Integer);
+        assert_eq!(columns[1].column_type, DataType::Text);
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data.iter().filter(|d| d["a"].is_none()).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_many_chunks() {
+        let db = create_mem_db("add_datapoints_many_chunks").await;
+        let project = db.create("foo").await;
+
+        let rows = (0..2000)
+            .map(|i| {
+                HashMap::from([
+                    ("a".to_string(), i.to_string()),
+                    ("b".to_string(), (i * 2).to_string()),
+                ])
+            })
+            .collect();
+        assert_eq!(project.add_datapoints(rows).await.unwrap(), 2000);
+        assert_eq!(project.datapoint_count().await.unwrap(), 2000);
+    }
+
+    #[tokio::test]
+    async fn add_datapoints_rolls_back() {
+        let db = create_mem_db("add_datapoints_rolls_back").await;
+        let project = db.create("foo").await;
+
+        let rows = vec![
+            HashMap::from([("a".to_string(), "1".to_string())]),
+            HashMap::from([("a".to_string(), "2".to_string())]),
+            HashMap::from([("a".to_string(), "three".to_string())]),
+        ];
+
+        let Err(Error::InvalidRows(invalid)) = project.add_datapoints(rows).await else {
+            panic!("Last row should be invalid");
+        };
+        assert_eq!(invalid[0].row, 2);
+        assert_eq!(project.datapoint_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn delete_column() {
+        let db = create_mem_db("delete_column").await;
+        let project = db.create("foo").await;
+
+        let mut data = HashMap::new();
+        data.insert("keep".to_string(), "a".to_string());
+        data.insert("old".to_string(), "b".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        project.delete_column("old").await.unwrap();
+
+        let names: Vec<String> = project
+            .get_all()
+            .await
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["keep".to_string()]);
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0]["keep"], Some("a".to_string()));
+        assert!(!data[0].contains_key("old"));
+    }
+
+    #[tokio::test]
+    async fn rename_column() {
+        let db = create_mem_db("rename_column").await;
+        let project = db.create("foo").await;
+
+        let mut data = HashMap::new();
+        data.insert("bar".to_string(), "a".to_string());
+        data.insert("taken".to_string(), "b".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        let column = project.rename_column("bar", "baz").await.unwrap().unwrap();
+        assert_eq!(column.name, "baz");
+        assert_eq!(column.encoded, "baz");
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data[0]["baz"], Some("a".to_string()));
+        assert!(!data[0].contains_key("bar"));
+
+        assert!(matches!(
+            project.rename_column("baz", "taken").await,
+            Err(Error::ColumnExists(_))
+        ));
+        assert!(project.rename_column("bar", "qux").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn change_column_type() {
+        let db = create_mem_db("change_column_type").await;
+        let project = db.create("foo").await;
+        project.create("count").await;
+        project.create("name").await;
+
+        for (count, name) in [("1", "a"), ("22", "b")] {
+            let data = HashMap::from([
+                ("count".to_string(), count.to_string()),
+                ("name".to_string(), name.to_string()),
+            ]);
+            project.add_datapoint(data).await.unwrap();
         }
+        let data = HashMap::from([("name".to_string(), "c".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+
+        let column = project
+            .change_column_type("count", DataType::Integer)
+            .await
+            .unwrap();
+        assert_eq!(column.column_type, DataType::Integer);
+        assert_eq!(project.get_all().await[0].column_type, DataType::Integer);
+
+        let mut counts: Vec<Option<Value>> = project
+            .get_data_typed()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|mut d| d.remove("count").unwrap())
+            .collect();
+        counts.sort_by_key(|v| format!("{:?}", v));
+        assert_eq!(
+            counts,
+            vec![None, Some(Value::Integer(1)), Some(Value::Integer(22))]
+        );
+
+        // The other columns and their data are kept
+        let names: Vec<String> = project
+            .get_data()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|mut d| d.remove("name").unwrap())
+            .collect();
+        assert_eq!(names.len(), 3);
+
+        // Values are summed as numbers now
+        let total: i64 = sqlx::query_scalar("SELECT SUM(count) FROM foo")
+            .fetch_one(&project.pool)
+            .await
+            .unwrap();
+        assert_eq!(total, 23);
     }
 
-    /// Convert a string from SQL to a data type
-    ///
-    /// If the string is not a valid data type, return None
-    ///
-    /// # Example
-    /// ```rust
-    /// # use database::project::DataType;
-    /// let data_type = DataType::from_sql("TEXT");
-    /// assert_eq!(data_type, Some(DataType::Text));
-    /// ```
-    ///
-    /// ```rust
-    /// # use database::project::DataType;
-    /// let data_type = DataType::from_sql("NOT_A_TYPE");
-    /// assert_eq!(data_type, None);
-    /// ```
-    ///
-    /// # Returns
-    /// DataType if the string is a valid sql type or None otherwise
-    pub fn from_sql(s: &str) -> Option<DataType> {
-        match s {
-            "TEXT" => Some(DataType::Text),
-            "BLOB" => Some(DataType::Raw),
-            "INTEGER" => Some(DataType::Integer),
-            "BIGINT" => Some(DataType::BigInteger),
-            "FLOAT" => Some(DataType::Float),
-            _ => None,
+    #[tokio::test]
+    async fn change_column_type_invalid_values() {
+        let db = create_mem_db("change_column_type_invalid_values").await;
+        let project = db.create("foo").await;
+        project.create("count").await;
+
+        for count in ["1", "many", "3", "lots"] {
+            let data = HashMap::from([("count".to_string(), count.to_string())]);
+            project.add_datapoint(data).await.unwrap();
+        }
+
+        match project.change_column_type("count", DataType::Integer).await {
+            Err(Error::Validation(errors)) => {
+                assert_eq!(errors.errors.len(), 2);
+                assert!(errors.errors.iter().all(|e| e.rule == RULE_TYPE));
+                assert!(errors.errors[0].message.contains("many"));
+                assert!(errors.errors[1].message.contains("lots"));
+            }
+            other => panic!("Expected a validation error, got {:?}", other),
         }
+
+        // Nothing was changed
+        assert_eq!(project.get_all().await[0].column_type, DataType::Text);
+        let data = project.get_data().await.unwrap();
+        assert!(data.iter().any(|d| d["count"] == Some("many".to_string())));
+
+        assert!(matches!(
+            project
+                .change_column_type("missing", DataType::Integer)
+                .await,
+            Err(Error::ColumnNotFound(_))
+        ));
     }
-}
 
-#[cfg(test)]
-mod methods {
-    use crate::{database::methods::create_mem_db, project::DataType};
+    #[tokio::test]
+    async fn delete_missing_column() {
+        let db = create_mem_db("delete_missing_column").await;
+        let project = db.create("foo").await;
 
-    use super::{Column, Project};
+        assert!(matches!(
+            project.delete_column("bar").await,
+            Err(Error::ColumnNotFound(name)) if name == "bar"
+        ));
+    }
 
     #[tokio::test]
-    async fn create_column() {
-        let db = create_mem_db("create_column").await;
+    async fn rebuild_table() {
+        let db = create_mem_db("rebuild_table").await;
         let project = db.create("foo").await;
-        let column = project.create("boo").await;
 
-        let columns = project.get_all().await;
-        assert_eq!(columns.len(), 1);
+        let mut data = HashMap::new();
+        data.insert("count".to_string(), "1".to_string());
+        data.insert("name".to_string(), "a".to_string());
+        project.add_datapoint(data).await.unwrap();
 
-        assert_eq!(column.name, "boo");
-        assert_eq!(column.encoded, "boo");
+        let remaining: Vec<Column> = project
+            .get_all()
+            .await
+            .into_iter()
+            .filter(|c| c.name == "count")
+            .collect();
+
+        let mut conn = project.pool.acquire().await.unwrap();
+        project.rebuild_table(&mut conn, &remaining).await.unwrap();
+        drop(conn);
+
+        let count: i64 = sqlx::query_scalar("SELECT count FROM foo")
+            .fetch_one(&project.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        assert!(sqlx::query("SELECT name FROM foo")
+            .fetch_one(&project.pool)
+            .await
+            .is_err());
     }
 
     #[tokio::test]
-    async fn create_columns() {
-        let db = create_mem_db("create_columns").await;
+    async fn validate_rows() {
+        let db = create_mem_db("validate_rows").await;
         let project = db.create("foo").await;
 
-        project.create("boo").await;
-        project.create("bar").await;
+        let mut data = HashMap::new();
+        data.insert("count".to_string(), "1".to_string());
+        project.add_datapoint(data).await.unwrap();
 
-        let columns = project.get_all().await;
-        assert_eq!(columns.len(), 2);
+        let rows = vec![
+            HashMap::from([
+                ("count".to_string(), "2".to_string()),
+                ("ratio".to_string(), "0.5".to_string()),
+            ]),
+            HashMap::from([
+                ("count".to_string(), "3".to_string()),
+                ("ratio".to_string(), "half".to_string()),
+            ]),
+            HashMap::from([("count".to_string(), "4".to_string())]),
+        ];
+
+        let Err(Error::InvalidRows(invalid)) = project.validate_rows(&rows).await else {
+            panic!("Second row should be invalid");
+        };
+        assert_eq!(invalid.len(), 1);
+        assert_eq!(invalid[0].row, 1);
+        assert_eq!(invalid[0].errors.errors[0].field, "ratio");
+        assert_eq!(invalid[0].errors.errors[0].rule, RULE_TYPE);
+
+        // Nothing is inserted, not even the new column
+        assert_eq!(project.get_data().await.unwrap().len(), 1);
+        assert_eq!(project.get_all().await.len(), 1);
+
+        project.validate_rows(&rows[..1]).await.unwrap();
     }
 
     #[tokio::test]
-    async fn get_or_create_columns_single() {
-        let db = create_mem_db("get_or_create_columns_single").await;
+    async fn update_datapoint() {
+        let db = create_mem_db("update_datapoint").await;
         let project = db.create("foo").await;
 
-        let names = vec!["boo".to_string()];
+        for count in ["1", "2"] {
+            let data = HashMap::from([("count".to_string(), count.to_string())]);
+            project.add_datapoint(data).await.unwrap();
+        }
+
+        let before = project.get_data_with_timestamps().await.unwrap();
+        let id = before[1].id;
 
-        let columns = project.get_or_create_columns(&names).await.unwrap();
-        assert_eq!(columns.len(), 1);
+        let data = HashMap::from([
+            ("count".to_string(), "3".to_string()),
+            ("note".to_string(), "fixed".to_string()),
+        ]);
+        project.update_datapoint(id, data).await.unwrap();
 
-        assert_eq!(columns[0].name, "boo");
+        let after = project.get_data_with_timestamps().await.unwrap();
+        assert_eq!(after[0].values["count"], before[0].values["count"]);
+        assert_eq!(after[0].values["note"], None);
+        assert_eq!(after[1].id, id);
+        assert_eq!(after[1].timestamp, before[1].timestamp);
+        assert_eq!(after[1].values["count"], Some("3".to_string()));
+        assert_eq!(after[1].values["note"], Some("fixed".to_string()));
+
+        let data = HashMap::from([("count".to_string(), "many".to_string())]);
+        let error = project.update_datapoint(id, data).await;
+        assert!(matches!(error, Err(Error::Validation(_))));
+
+        let data = HashMap::from([("other".to_string(), "1".to_string())]);
+        let error = project.update_datapoint(id + 100, data).await;
+        assert!(matches!(error, Err(Error::DatapointNotFound(_))));
+        assert_eq!(project.get_all().await.len(), 2);
     }
 
     #[tokio::test]
-    async fn get_or_create_columns_multiple() {
-        let db = create_mem_db("get_or_create_columns_multiple").await;
+    async fn delete_datapoint() {
+        let db = create_mem_db("delete_datapoint").await;
         let project = db.create("foo").await;
 
-        let names = vec![
-            "boo".to_string(),
-            "bar".to_string(),
-            "baz".to_string(),
-            "foo".to_string(),
-        ];
+        for count in ["1", "2", "3"] {
+            let data = HashMap::from([("count".to_string(), count.to_string())]);
+            project.add_datapoint(data).await.unwrap();
+        }
 
-        let columns = project.get_or_create_columns(&names).await.unwrap();
-        assert_eq!(columns.len(), 4);
+        let ids: Vec<i64> = project
+            .get_data_with_ids()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
 
-        assert_eq!(columns[0].name, "boo");
-        assert_eq!(columns[1].name, "bar");
-        assert_eq!(columns[2].name, "baz");
-        assert_eq!(columns[3].name, "foo");
+        assert!(project.delete_datapoint(ids[1]).await.unwrap());
+        assert!(!project.delete_datapoint(ids[1]).await.unwrap());
+
+        let data = project.get_data().await.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0]["count"], Some("1".to_string()));
+        assert_eq!(data[1]["count"], Some("3".to_string()));
+
+        // Ids are not handed out again, even after deleting the newest datapoint
+        assert!(project.delete_datapoint(ids[2]).await.unwrap());
+        let data = HashMap::from([("count".to_string(), "4".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+
+        let id = project.get_data_with_ids().await.unwrap()[1].0;
+        assert!(id > ids[2]);
     }
 
     #[tokio::test]
-    async fn add_single_data() {
-        let db = create_mem_db("add_single_data").await;
+    async fn get_data_paged() {
+        let db = create_mem_db("get_data_paged").await;
         let project = db.create("foo").await;
 
-        project.create("boo").await;
+        for i in 0..5 {
+            let mut data = HashMap::new();
+            data.insert("bar".to_string(), i.to_string());
+            project.add_datapoint(data).await.unwrap();
+        }
 
-        let mut data = std::collections::HashMap::new();
-        data.insert("boo".to_string(), "bar".to_string());
+        let page = project.get_data_paged(2, 1).await.unwrap();
+        assert_eq!(page.len(), 2);
+
+        // Datapoints added in the same second keep the order they were added in
+        let ids: Vec<i64> = page.iter().map(|d| d.id).collect();
+        assert!(ids[0] < ids[1]);
+        assert_eq!(page[0].values["bar"], Some("1".to_string()));
+
+        let page = project.get_data_paged(10, 3).await.unwrap();
+        assert_eq!(page.len(), 2);
+
+        assert!(project.get_data_paged(10, 5).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_datapoint_returns_timestamp() {
+        let db = create_mem_db("add_datapoint_returns_timestamp").await;
+        let project = db.create("foo").await;
+
+        let mut data = HashMap::new();
+        data.insert("bar".to_string(), "baz".to_string());
+        let timestamp = project.add_datapoint(data).await.unwrap();
+
+        let datapoints = project.get_data_with_timestamps().await.unwrap();
+        assert_eq!(datapoints[0].timestamp, timestamp);
+    }
+
+    #[tokio::test]
+    async fn get_data_between() {
+        let db = create_mem_db("get_data_between").await;
+        let project = db.create("foo").await;
+
+        for i in 0..3 {
+            let mut data = HashMap::new();
+            data.insert("bar".to_string(), i.to_string());
+            project.add_datapoint(data).await.unwrap();
+        }
+
+        // Spread the datapoints out over three seconds
+        sqlx::query("UPDATE foo SET __timestamp__ = 100 + bar")
+            .execute(&project.pool)
+            .await
+            .unwrap();
+
+        let time = |seconds: i64| DateTime::from_timestamp(seconds, 0).unwrap();
+
+        let data = project
+            .get_data_between(time(100), time(102))
+            .await
+            .unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].timestamp, time(100));
+        assert_eq!(data[1].timestamp, time(101));
+
+        let data = project
+            .get_data_between(time(102), time(200))
+            .await
+            .unwrap();
+        assert_eq!(data.len(), 1);
+
+        let data = project
+            .get_data_between(time(102), time(100))
+            .await
+            .unwrap();
+        assert!(data.is_empty());
+
+        let page = project
+            .get_data_between_paged(time(100), time(200), 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].timestamp, time(101));
+        assert_eq!(
+            project
+                .datapoint_count_between(time(100), time(102))
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn get_data_range() {
+        let db = create_mem_db("get_data_range").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("size", DataType::Integer)
+            .await
+            .unwrap();
+        project
+            .create_column("price", DataType::Decimal)
+            .await
+            .unwrap();
+
+        for (size, price) in [("1", "9.5"), ("2", "10.25"), ("3", "100"), ("4", "2")] {
+            let data = HashMap::from([
+                ("size".to_string(), size.to_string()),
+                ("price".to_string(), price.to_string()),
+            ]);
+            project.add_datapoint(data).await.unwrap();
+        }
+        let data = HashMap::from([("bar".to_string(), "baz".to_string())]);
+        project.add_datapoint(data).await.unwrap();
 
+        let sizes = |data: Vec<Datapoint>| {
+            data.into_iter()
+                .map(|d| d.values["size"].clone().unwrap())
+                .collect::<Vec<String>>()
+        };
+
+        let data = project
+            .get_data_range(
+                "size",
+                Some("2".to_string()),
+                Some("3".to_string()),
+                None,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sizes(data), vec!["2", "3"]);
+
+        let data = project
+            .get_data_range("size", Some("3".to_string()), None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(sizes(data), vec!["3", "4"]);
+
+        // Decimals are compared as numbers, not as text
+        let data = project
+            .get_data_range(
+                "price",
+                Some("9.75".to_string()),
+                Some("50".to_string()),
+                None,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+        assert_eq!(sizes(data), vec!["2"]);
+
+        let all = project
+            .get_data_range("size", None, None, None, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 4);
+
+        // Pages and counts stay within the ranges
+        let page = project
+            .get_data_range("size", None, None, None, 2, 1)
+            .await
+            .unwrap();
+        assert_eq!(sizes(page), vec!["2", "3"]);
+        let count = project
+            .datapoint_count_range("size", Some("2".to_string()), None, None)
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let time = |seconds: i64| DateTime::from_timestamp(seconds, 0).unwrap();
+        sqlx::query("UPDATE foo SET __timestamp__ = 100 + COALESCE(size, 0)")
+            .execute(&project.pool)
+            .await
+            .unwrap();
+        let between = Some((time(102), time(104)));
+        let data = project
+            .get_data_range("size", Some("3".to_string()), None, between, 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(sizes(data), vec!["3"]);
+        let count = project
+            .datapoint_count_range("size", None, None, between)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn get_data_range_rejected() {
+        let db = create_mem_db("get_data_range_rejected").await;
+        let project = db.create("foo").await;
+        project.create("bar").await;
+        project
+            .create_column("size", DataType::Integer)
+            .await
+            .unwrap();
+
+        let text = project
+            .get_data_range("bar", Some("a".to_string()), None, None, 10, 0)
+            .await;
+        assert!(matches!(text, Err(Error::NotNumeric(name)) if name == "bar"));
+
+        let missing = project.get_data_range("baz", None, None, None, 10, 0).await;
+        assert!(matches!(missing, Err(Error::ColumnNotFound(_))));
+
+        match project
+            .get_data_range("size", Some("small".to_string()), None, None, 10, 0)
+            .await
+        {
+            Err(Error::Validation(errors)) => assert_eq!(errors.errors[0].rule, RULE_TYPE),
+            other => panic!("Expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn changes_since() {
+        let db = create_mem_db("changes_since").await;
+        let project = db.create("foo").await;
+
+        for i in 0..3 {
+            let mut data = HashMap::new();
+            data.insert("bar".to_string(), i.to_string());
+            project.add_datapoint(data).await.unwrap();
+        }
+
+        // Two datapoints before the cutoff at 102, one in the same second
+        sqlx::query("UPDATE foo SET __timestamp__ = 100 + bar")
+            .execute(&project.pool)
+            .await
+            .unwrap();
+
+        let time = |seconds: i64| DateTime::from_timestamp(seconds, 0).unwrap();
+
+        let changes = project.changes_since(time(102)).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].values["bar"], Some("2".to_string()));
+
+        assert!(project.changes_since(time(103)).await.unwrap().is_empty());
+        assert_eq!(project.changes_since(time(99)).await.unwrap().len(), 3);
+        assert_eq!(project.change_count_since(time(101)).await.unwrap(), 2);
+
+        let page = project.changes_since_paged(time(100), 1, 1).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].values["bar"], Some("1".to_string()));
+
+        // Changing an old datapoint makes it show up again, as the most recent change
+        let id = project.get_data_with_ids().await.unwrap()[0].0;
+        let data = HashMap::from([("bar".to_string(), "3".to_string())]);
+        project.update_datapoint(id, data).await.unwrap();
+
+        let changes = project.changes_since(time(102)).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].values["bar"], Some("2".to_string()));
+        assert_eq!(changes[1].id, id);
+    }
+
+    #[tokio::test]
+    async fn drop_empty_columns() {
+        let db = create_mem_db("drop_empty_columns").await;
+        let project = db.create("foo").await;
+
+        project.create("used").await;
+        project.create("typo").await;
+        project.create("also_used").await;
+        project.create("unused").await;
+
+        let mut data = HashMap::new();
+        data.insert("used".to_string(), "a".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("also_used".to_string(), "b".to_string());
         project.add_datapoint(data).await.unwrap();
+
+        let dropped = project.drop_empty_columns().await.unwrap();
+        assert_eq!(dropped, vec!["typo".to_string(), "unused".to_string()]);
+
+        let names: Vec<String> = project
+            .get_all()
+            .await
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["used".to_string(), "also_used".to_string()]);
+
         let data = project.get_data().await.unwrap();
+        assert_eq!(data.len(), 2);
+        assert!(data.iter().all(|d| !d.contains_key("typo")));
+    }
 
-        assert_eq!(data.len(), 1);
-        assert_eq!(data[0].get("boo"), Some(Some("bar".to_string())).as_ref());
+    #[tokio::test]
+    async fn collapse_sparse_columns() {
+        let db = create_mem_db("collapse_sparse_columns").await;
+        let project = db.create("foo").await;
+
+        let datapoint = |values: &[(&str, &str)]| {
+            values
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<String, String>>()
+        };
+        project
+            .add_datapoint(datapoint(&[
+                ("name", "a"),
+                ("count", "1"),
+                ("ratio", "2.5"),
+            ]))
+            .await
+            .unwrap();
+        project
+            .add_datapoint(datapoint(&[("name", "b"), ("note", "hello")]))
+            .await
+            .unwrap();
+        project
+            .add_datapoint(datapoint(&[("name", "c")]))
+            .await
+            .unwrap();
+
+        // Previewing changes nothing
+        let preview = project.sparse_columns(0.5).await.unwrap();
+        assert_eq!(preview, ["count", "note", "ratio"]);
+        assert_eq!(project.get_all().await.len(), 4);
+
+        let collapsed = project.collapse_sparse_columns(0.5).await.unwrap();
+        assert_eq!(collapsed, preview);
+
+        let names: Vec<String> = project
+            .get_all()
+            .await
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, ["name", EXTRA_COLUMN]);
+
+        let extra = |data: &serde_json::Value, row: usize| {
+            data[row][EXTRA_COLUMN]
+                .as_str()
+                .map(|s| serde_json::from_str::<serde_json::Value>(s).unwrap())
+        };
+        let data = project.get_data_json().await.unwrap();
+        assert_eq!(extra(&data, 0), Some(json!({"count": 1, "ratio": 2.5})));
+        assert_eq!(extra(&data, 1), Some(json!({"note": "hello"})));
+        assert_eq!(extra(&data, 2), None);
+        assert_eq!(data[2]["name"], "c");
+
+        // Collapsing again adds to the objects already in the extra column
+        project
+            .add_datapoint(datapoint(&[("name", "d"), ("count", "7")]))
+            .await
+            .unwrap();
+        project
+            .add_datapoint(datapoint(&[("name", "e")]))
+            .await
+            .unwrap();
+        let collapsed = project.collapse_sparse_columns(0.5).await.unwrap();
+        assert_eq!(collapsed, ["count"]);
+
+        let data = project.get_data_json().await.unwrap();
+        assert_eq!(extra(&data, 0), Some(json!({"count": 1, "ratio": 2.5})));
+        assert_eq!(extra(&data, 3), Some(json!({"count": 7})));
+    }
+
+    #[tokio::test]
+    async fn parse_values_rejects_mismatches() {
+        let db = create_mem_db("parse_values_rejects_mismatches").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("count", DataType::Integer)
+            .await
+            .unwrap();
+        project.create_column("note", DataType::Text).await.unwrap();
+        let columns = project.get_columns().await.unwrap();
+
+        let values = project
+            .parse_values(&columns, &["42".to_string(), "x".to_string()])
+            .unwrap();
+        assert_eq!(values, [Value::Integer(42), Value::Text("x".to_string())]);
+
+        // A value that no longer fits a column fails the row instead of being left out
+        match project.parse_values(&columns, &["many".to_string(), "x".to_string()]) {
+            Err(Error::Validation(errors)) => {
+                assert_eq!(errors.errors.len(), 1);
+                assert_eq!(errors.errors[0].field, "count");
+                assert_eq!(errors.errors[0].rule, RULE_TYPE);
+            }
+            other => panic!("Expected a validation error, got {:?}", other),
+        }
     }
 
     impl Project {
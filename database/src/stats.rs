@@ -0,0 +1,337 @@
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+use crate::{
+    project::{ceil_timestamp, timestamp_placeholder, typed_column, Project, UPDATED_AT_COLUMN},
+    value::Value,
+    Database, Error,
+};
+
+/// Totals over every project in a database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub projects: usize,
+    pub columns: usize,
+    pub datapoints: i64,
+
+    /// Breakdown per project, sorted by name
+    pub per_project: Vec<ProjectStats>,
+}
+
+/// Size of a single project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectStats {
+    pub name: String,
+    pub columns: usize,
+    pub datapoints: i64,
+}
+
+/// Name and size of the schema of a project, see [Database::project_summaries]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSummary {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub column_count: i64,
+}
+
+impl Database {
+    /// Name, creation time and number of columns of every active project, sorted by name
+    ///
+    /// Counts the columns of all projects in a single query, instead of fetching the columns
+    /// of every project.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:project_summaries?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    /// project.create_column("bar", DataType::Text).await?;
+    ///
+    /// let summaries = db.project_summaries().await?;
+    /// assert_eq!(summaries[0].name, "foo");
+    /// assert_eq!(summaries[0].column_count, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn project_summaries(&self) -> Result<Vec<ProjectSummary>, Error> {
+        let rows = sqlx::query(
+            r#"
+            SELECT projects.name, projects.created_at, COUNT(columns.project_id)
+            FROM projects
+            LEFT JOIN columns ON columns.project_id = projects.id
+            WHERE projects.deleted_at IS NULL
+            GROUP BY projects.id, projects.name, projects.created_at
+            ORDER BY projects.name
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let created_at: i64 = row.try_get(1)?;
+                Ok(ProjectSummary {
+                    name: row.try_get(0)?,
+                    created_at: DateTime::from_timestamp(created_at, 0).unwrap_or_default(),
+                    column_count: row.try_get(2)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Count the projects, columns and datapoints in the database
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:stats?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("bar".to_string(), "baz".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let stats = db.stats().await?;
+    /// assert_eq!(stats.projects, 1);
+    /// assert_eq!(stats.columns, 1);
+    /// assert_eq!(stats.datapoints, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stats(&self) -> Result<Stats, Error> {
+        let mut projects = self.get_projects().await?;
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut stats = Stats::default();
+        for project in projects {
+            let project = ProjectStats {
+                columns: project.get_columns().await?.len(),
+                datapoints: project.datapoint_count().await?,
+                name: project.name,
+            };
+
+            stats.projects += 1;
+            stats.columns += project.columns;
+            stats.datapoints += project.datapoints;
+            stats.per_project.push(project);
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Project {
+    /// Number of datapoints in the project
+    pub async fn datapoint_count(&self) -> Result<i64, Error> {
+        let count = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", self.table()))
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Number of datapoints added within a time range, the same range as in
+    /// [Project::get_data_between]
+    pub async fn datapoint_count_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<i64, Error> {
+        let count = sqlx::query_scalar(&self.backend().sql(&format!(
+            "SELECT COUNT(*) FROM {} WHERE __timestamp__ >= {1} AND __timestamp__ < {1}",
+            self.table(),
+            timestamp_placeholder(self.backend())
+        )))
+        .bind(ceil_timestamp(from))
+        .bind(ceil_timestamp(to))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Number of datapoints added or changed at or after a time, the same datapoints as in
+    /// [Project::changes_since]
+    pub async fn change_count_since(&self, since: DateTime<Utc>) -> Result<i64, Error> {
+        let count = sqlx::query_scalar(&self.backend().sql(&format!(
+            "SELECT COUNT(*) FROM {} WHERE __timestamp__ >= {} OR {} >= ?",
+            self.table(),
+            timestamp_placeholder(self.backend()),
+            UPDATED_AT_COLUMN
+        )))
+        .bind(since.timestamp())
+        .bind(since.timestamp())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Number of datapoints in the ranges of [Project::get_data_range]
+    ///
+    /// # Errors
+    /// The same as [Project::get_data_range]
+    pub async fn datapoint_count_range(
+        &self,
+        column: &str,
+        min: Option<String>,
+        max: Option<String>,
+        between: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<i64, Error> {
+        let columns = self.get_columns().await?;
+        let (conditions, values) = self.range_filter(&columns, column, min, max, between)?;
+
+        let query = self.backend().sql(&format!(
+            "SELECT COUNT(*) FROM {} WHERE {}",
+            self.table(),
+            conditions
+        ));
+        let mut query = sqlx::query(&query);
+        for value in values {
+            query = value.bind(query);
+        }
+
+        Ok(query.fetch_one(&self.pool).await?.try_get(0)?)
+    }
+
+    /// Number of datapoints per distinct value of a column, most common value first
+    ///
+    /// Values are written like in [Project::get_data], NULL values are left out. Values with the
+    /// same count are sorted by value.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:value_histogram?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// for color in ["red", "blue", "red"] {
+    ///     let mut data = HashMap::new();
+    ///     data.insert("color".to_string(), color.to_string());
+    ///     project.add_datapoint(data).await?;
+    /// }
+    ///
+    /// let histogram = project.value_histogram("color").await?;
+    /// assert_eq!(histogram[0], ("red".to_string(), 2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// [Error::ColumnNotFound] if the project has no column with the name
+    pub async fn value_histogram(&self, column: &str) -> Result<Vec<(String, i64)>, Error> {
+        let column = self
+            .get_columns()
+            .await?
+            .into_iter()
+            .find(|c| c.name == column)
+            .ok_or_else(|| Error::ColumnNotFound(column.to_string()))?;
+
+        let query = format!(
+            r#"
+            SELECT {}, COUNT(*) FROM {}
+            WHERE {} IS NOT NULL
+            GROUP BY {}
+            ORDER BY COUNT(*) DESC, {}
+            "#,
+            typed_column(&column, self.backend()),
+            self.table(),
+            column.quoted(),
+            column.quoted(),
+            column.quoted()
+        );
+
+        let mut histogram = Vec::new();
+        for row in sqlx::query(&query).fetch_all(&self.pool).await? {
+            let value = Value::read(&row, 0, column.column_type)?;
+            let count: i64 = row.try_get(1)?;
+
+            if let Some(value) = value {
+                histogram.push((value.to_string(), count));
+            }
+        }
+
+        Ok(histogram)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{database::methods::create_mem_db, project::DataType, Error};
+
+    #[tokio::test]
+    async fn project_summaries() {
+        let db = create_mem_db("project_summaries").await;
+        let foo = db.create("foo").await;
+        foo.create("a").await;
+        foo.create("b").await;
+        let bar = db.create("bar").await;
+        bar.create("a").await;
+        db.create("empty").await;
+        db.create("archived").await.create("a").await;
+        db.archive_project("archived").await.unwrap();
+
+        let counts: Vec<(String, i64)> = db
+            .project_summaries()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.name, s.column_count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("bar".to_string(), 1),
+                ("empty".to_string(), 0),
+                ("foo".to_string(), 2),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn value_histogram() {
+        let db = create_mem_db("value_histogram").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("size", DataType::Integer)
+            .await
+            .unwrap();
+
+        for (color, size) in [("red", "1"), ("blue", "2"), ("red", "1"), ("green", "2")] {
+            let data = HashMap::from([
+                ("color".to_string(), color.to_string()),
+                ("size".to_string(), size.to_string()),
+            ]);
+            project.add_datapoint(data).await.unwrap();
+        }
+        let data = HashMap::from([("color".to_string(), "red".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+
+        let colors = project.value_histogram("color").await.unwrap();
+        assert_eq!(
+            colors,
+            vec![
+                ("red".to_string(), 3),
+                ("blue".to_string(), 1),
+                ("green".to_string(), 1),
+            ]
+        );
+
+        // The datapoint without a size is left out
+        let sizes = project.value_histogram("size").await.unwrap();
+        assert_eq!(sizes, vec![("1".to_string(), 2), ("2".to_string(), 2)]);
+
+        let missing = project.value_histogram("weight").await;
+        assert!(matches!(missing, Err(Error::ColumnNotFound(_))));
+    }
+}
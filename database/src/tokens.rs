@@ -0,0 +1,101 @@
+use chrono::Utc;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::{database::encode_project_name, Database, Error};
+
+/// Random bytes in a token, written out as twice as many hex digits
+const TOKEN_BYTES: usize = 32;
+
+/// Lower case hex digits of some bytes
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hash a token is stored under, so a leaked database does not leak usable tokens
+fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+impl Database {
+    /// Create a new token that is only valid for writing to a single project
+    ///
+    /// Only a hash of the token is stored, so it can not be shown again later. The project does
+    /// not have to exist yet, a token can be used to create it. Deleting the project revokes
+    /// all of its tokens.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::Database;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:create_project_token?mode=memory").await?;
+    ///
+    /// let token = db.create_project_token("foo").await?;
+    /// assert!(db.verify_token(&token, "foo").await?);
+    /// assert!(!db.verify_token(&token, "bar").await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    /// [Error::Reserved] or [Error::EmptyEncodedName] if no project can have the name
+    pub async fn create_project_token(&self, project: &str) -> Result<String, Error> {
+        encode_project_name(project)?;
+
+        let mut bytes = [0u8; TOKEN_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = to_hex(&bytes);
+
+        sqlx::query(&self.backend().sql(
+            "INSERT INTO project_tokens (token_hash, project_name, created_at) VALUES (?, ?, ?)",
+        ))
+        .bind(hash_token(&token))
+        .bind(project)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Whether a token was created for the project with [Database::create_project_token]
+    pub async fn verify_token(&self, token: &str, project: &str) -> Result<bool, Error> {
+        let count: i64 =
+            sqlx::query_scalar(&self.backend().sql(
+                "SELECT COUNT(*) FROM project_tokens WHERE token_hash = ? AND project_name = ?",
+            ))
+            .bind(hash_token(token))
+            .bind(project)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::methods::create_mem_db;
+
+    #[tokio::test]
+    async fn tokens_revoked_with_project() {
+        let db = create_mem_db("tokens_revoked_with_project").await;
+        db.create_project("foo").await.unwrap();
+
+        let token = db.create_project_token("foo").await.unwrap();
+        let other = db.create_project_token("foo").await.unwrap();
+        assert_ne!(token, other);
+        assert!(db.verify_token(&token, "foo").await.unwrap());
+        assert!(!db.verify_token("not a token", "foo").await.unwrap());
+
+        db.delete_project("foo").await.unwrap();
+        assert!(!db.verify_token(&token, "foo").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn token_for_invalid_name() {
+        let db = create_mem_db("token_for_invalid_name").await;
+        assert!(db.create_project_token("@@@").await.is_err());
+    }
+}
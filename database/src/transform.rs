@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::{project::Project, value::Value, Error};
+
+/// Transformation applied to the values of a column when reading, without changing what is
+/// stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Uppercase text
+    Upper,
+    /// Lowercase text
+    Lower,
+    /// Round floats and decimals to the given number of decimal places
+    Round(u32),
+}
+
+impl Transform {
+    /// Apply the transformation to a value, values it does not apply to are left as they are
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::{transform::Transform, value::Value};
+    /// assert_eq!(Transform::Round(1).apply(Value::Float(2.345)), Value::Float(2.3));
+    /// assert_eq!(Transform::Upper.apply(Value::Integer(5)), Value::Integer(5));
+    /// ```
+    pub fn apply(&self, value: Value) -> Value {
+        match (self, value) {
+            (Transform::Upper, Value::Text(text)) => Value::Text(text.to_uppercase()),
+            (Transform::Lower, Value::Text(text)) => Value::Text(text.to_lowercase()),
+            (Transform::Round(places), Value::Float(float)) => {
+                Value::Float(round_float(float, *places))
+            }
+            (Transform::Round(places), Value::Decimal(decimal)) => {
+                Value::Decimal(decimal.round_dp(*places))
+            }
+            (_, value) => value,
+        }
+    }
+}
+
+/// Round a float to a number of decimal places, floats too large to scale are left as they are
+fn round_float(float: f64, places: u32) -> f64 {
+    let scale = 10f64.powi(places.min(i32::MAX as u32) as i32);
+    let scaled = float * scale;
+
+    match scaled.is_finite() {
+        true => scaled.round() / scale,
+        false => float,
+    }
+}
+
+impl Project {
+    /// All datapoints from the project like [Project::get_data_typed], with the values of some
+    /// columns transformed
+    ///
+    /// The transformations are applied after reading, the stored data is not changed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, transform::Transform, value::Value};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:get_data_transformed?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let mut data = HashMap::new();
+    /// data.insert("name".to_string(), "fkit".to_string());
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let transforms = HashMap::from([("name".to_string(), Transform::Upper)]);
+    /// let data = project.get_data_transformed(&transforms).await?;
+    /// assert_eq!(data[0]["name"], Some(Value::Text("FKIT".to_string())));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Arguments
+    /// * `transforms` Transformation to apply, keyed by column name
+    pub async fn get_data_transformed(
+        &self,
+        transforms: &HashMap<String, Transform>,
+    ) -> Result<Vec<HashMap<String, Option<Value>>>, Error> {
+        let mut data = self.get_data_typed().await?;
+
+        for datapoint in &mut data {
+            for (column, transform) in transforms {
+                if let Some(value) = datapoint.get_mut(column) {
+                    *value = value.take().map(|value| transform.apply(value));
+                }
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rust_decimal::Decimal;
+
+    use crate::{database::methods::create_mem_db, value::Value};
+
+    use super::Transform;
+
+    #[test]
+    fn round() {
+        assert_eq!(
+            Transform::Round(2).apply(Value::Float(1.23456)),
+            Value::Float(1.23)
+        );
+        assert_eq!(
+            Transform::Round(0).apply(Value::Float(2.5)),
+            Value::Float(3.0)
+        );
+        assert_eq!(
+            Transform::Round(1).apply(Value::Decimal(Decimal::new(1234, 2))),
+            Value::Decimal(Decimal::new(123, 1))
+        );
+        assert_eq!(
+            Transform::Round(400).apply(Value::Float(1e300)),
+            Value::Float(1e300)
+        );
+    }
+
+    #[tokio::test]
+    async fn get_data_transformed() {
+        let db = create_mem_db("get_data_transformed").await;
+        let project = db.create_project("foo").await.unwrap();
+
+        let mut data = HashMap::new();
+        data.insert("ratio".to_string(), "0.666".to_string());
+        data.insert("name".to_string(), "Fkit".to_string());
+        data.insert("other".to_string(), "Fkit".to_string());
+        project.add_datapoint(data).await.unwrap();
+
+        let transforms = HashMap::from([
+            ("ratio".to_string(), Transform::Round(1)),
+            ("name".to_string(), Transform::Upper),
+            ("missing".to_string(), Transform::Lower),
+        ]);
+        let data = project.get_data_transformed(&transforms).await.unwrap();
+
+        assert_eq!(data[0]["ratio"], Some(Value::Float(0.7)));
+        assert_eq!(data[0]["name"], Some(Value::Text("FKIT".to_string())));
+        assert_eq!(data[0]["other"], Some(Value::Text("Fkit".to_string())));
+        assert!(!data[0].contains_key("missing"));
+
+        // The stored data is unchanged
+        let data = project.get_data_typed().await.unwrap();
+        assert_eq!(data[0]["name"], Some(Value::Text("Fkit".to_string())));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::Error;
 
 /// Characters that can be safley used to create projects
 const ALLOWED_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_";
@@ -5,14 +6,16 @@ const ALLOWED_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxy
 /// Encodes a string to be safe for use in a SQL query
 /// 
 /// Only characters in the set [A-Za-z0-9_] are allowed
-/// any other characters are excluded from the human-readable part
+/// any other characters are excluded from the human-readable part.
+/// When anything is excluded, a hash of the full input is appended, so
+/// different inputs like `a!b` and `ab` never encode to the same identifier
 ///
 /// # Example
 ///
 /// ```
 /// # use database::utils::sql_encode;
 /// let output = sql_encode("Hello, world!");
-/// assert_eq!(output, Err("Helloworld".to_string()));
+/// assert_eq!(output, Err("Helloworld_ed90f094".to_string()));
 /// ```
 ///
 /// ```
@@ -34,10 +37,52 @@ pub fn sql_encode(input: &str) -> Result<String,String> {
 
     match safe {
         true => Ok(output),
-        false => Err(output)
+        false => Err(format!("{}_{:08x}", output, fnv1a(input)))
     }
 }
 
+/// Encodes a name for use as a table or column identifier
+///
+/// Like [sql_encode], but a name without a single allowed character is rejected,
+/// since the identifier would be nothing but the hash, or nothing at all for an empty name
+///
+/// # Example
+///
+/// ```
+/// # use database::utils::encode_identifier;
+/// assert_eq!(encode_identifier("foo bar").unwrap(), "foobar_45c122aa");
+/// assert!(encode_identifier("@@@").is_err());
+/// ```
+pub fn encode_identifier(name: &str) -> Result<String, Error> {
+    if !name.chars().any(|c| ALLOWED_CHARS.contains(c)) {
+        return Err(Error::EmptyEncodedName(name.to_string()));
+    }
+
+    Ok(sql_encode(name).unwrap_or_else(|e| e))
+}
+
+/// Quotes an encoded identifier for use in a query
+///
+/// Quoting keeps names like `order` from being read as keywords, and keeps the case of the name
+/// on Postgres, which folds unquoted identifiers to lower case.
+///
+/// # Example
+///
+/// ```
+/// # use database::utils::quote_identifier;
+/// assert_eq!(quote_identifier("Foo"), "\"Foo\"");
+/// ```
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// 32 bit FNV-1a hash, stable across platforms and releases unlike the std hasher
+fn fnv1a(input: &str) -> u32 {
+    input.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
 #[cfg(test)]
 mod encode_name {
     use super::*;
@@ -47,7 +92,33 @@ mod encode_name {
         let output = sql_encode("Hello, world!");
         assert!(output.is_err());
 
-        assert_eq!(output.unwrap_err(), "Helloworld".to_string());
+        assert_eq!(output.unwrap_err(), "Helloworld_ed90f094".to_string());
+    }
+
+    #[test]
+    fn no_collisions() {
+        let stripped = sql_encode("a!b").unwrap_err();
+        let safe = sql_encode("ab").unwrap();
+        assert_ne!(stripped, safe);
+        assert_eq!(stripped, "ab_32a66e0f");
+
+        // Different characters stripped from the same place
+        assert_ne!(sql_encode("a!b"), sql_encode("a?b"));
+        assert_ne!(sql_encode("foo bar"), sql_encode("foo-bar"));
+    }
+
+    #[test]
+    fn empty_identifier() {
+        let error = encode_identifier("@@@").unwrap_err();
+        assert!(error.to_string().contains("@@@"));
+
+        assert!(encode_identifier("").is_err());
+        assert!(encode_identifier("_").is_ok());
+    }
+
+    #[test]
+    fn deterministic() {
+        assert_eq!(sql_encode("foo bar"), sql_encode("foo bar"));
     }
 
     #[test]
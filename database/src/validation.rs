@@ -0,0 +1,221 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use sqlx::{AnyConnection, Row};
+
+use crate::{
+    project::{id_column, timestamp_seconds, typed_column, DataType, Project},
+    value::Value,
+    Error,
+};
+
+/// Rule violated by a value of the wrong type for its column
+pub const RULE_TYPE: &str = "type";
+
+/// Rule violated by a number that is too large for the type of its column
+pub const RULE_OUT_OF_RANGE: &str = "out_of_range";
+
+/// Rule violated by a new column on a project with a frozen schema
+pub const RULE_SCHEMA_FROZEN: &str = "schema_frozen";
+
+/// A single field of a datapoint that failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Name of the field, the column the value was meant for
+    pub field: String,
+
+    /// Rule that was violated, like [RULE_TYPE]
+    pub rule: String,
+
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+/// Every validation failure of a datapoint, so all problems can be reported at once
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+/// Every validation failure of a single row in a batch of datapoints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    /// Index of the row in the batch, starting at 0
+    pub row: usize,
+
+    /// What is wrong with the row
+    pub errors: ValidationErrors,
+}
+
+/// A stored value that is not valid for a data type, see [Project::find_type_violations]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeViolation {
+    /// Row id of the datapoint holding the value
+    pub id: i64,
+
+    /// Time the datapoint was added
+    pub timestamp: DateTime<Utc>,
+
+    /// The value in its text form
+    pub value: String,
+}
+
+impl ValidationErrors {
+    /// Record a violation of a rule for a field
+    pub fn push(&mut self, field: &str, rule: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            rule: rule.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Ok if nothing was recorded, otherwise all errors sorted by field
+    pub fn into_result(mut self) -> Result<(), ValidationErrors> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        self.errors.sort_by(|a, b| a.field.cmp(&b.field));
+        Err(self)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<&str> = self.errors.iter().map(|e| e.field.as_str()).collect();
+        write!(f, "Invalid values for: {}", fields.join(", "))
+    }
+}
+
+impl Project {
+    /// Find the values of a column that are not valid for a data type
+    ///
+    /// Useful before changing the type of a column, every value that is reported would not
+    /// survive the change. NULL values are never violations.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use database::{Database, project::DataType};
+    /// # use std::collections::HashMap;
+    /// # tokio_test::block_on(test()).unwrap();
+    /// # async fn test() -> Result<(), database::Error>{
+    /// let db = Database::new("sqlite:file:find_type_violations?mode=memory").await?;
+    /// let project = db.create_project("foo").await?;
+    ///
+    /// let data = HashMap::from([("bar".to_string(), "baz".to_string())]);
+    /// project.add_datapoint(data).await?;
+    ///
+    /// let violations = project.find_type_violations("bar", DataType::Integer).await?;
+    /// assert_eq!(violations[0].value, "baz");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// The violations, oldest first. [Error::ColumnNotFound] if the project has no column with
+    /// the name
+    pub async fn find_type_violations(
+        &self,
+        column: &str,
+        as_type: DataType,
+    ) -> Result<Vec<TypeViolation>, Error> {
+        let mut conn = self.pool.acquire().await?;
+        self.find_type_violations_on(&mut conn, column, as_type)
+            .await
+    }
+
+    /// Like [Project::find_type_violations], using the given connection
+    pub(crate) async fn find_type_violations_on(
+        &self,
+        conn: &mut AnyConnection,
+        column: &str,
+        as_type: DataType,
+    ) -> Result<Vec<TypeViolation>, Error> {
+        let column = self
+            .fetch_columns_on(&mut *conn)
+            .await?
+            .into_iter()
+            .find(|c| c.name == column)
+            .ok_or_else(|| Error::ColumnNotFound(column.to_string()))?;
+
+        let query = format!(
+            r#"
+            SELECT {}, {}, {} FROM {}
+            WHERE {} IS NOT NULL
+            ORDER BY __timestamp__, {}
+            "#,
+            id_column(self.backend()),
+            timestamp_seconds(self.backend()),
+            typed_column(&column, self.backend()),
+            self.table(),
+            column.quoted(),
+            id_column(self.backend())
+        );
+
+        let mut violations = Vec::new();
+        for row in sqlx::query(&query).fetch_all(conn).await? {
+            let Some(value) = Value::read(&row, 2, column.column_type)? else {
+                continue;
+            };
+
+            let value = value.to_string();
+            if Value::parse(&value, as_type).is_none() {
+                let timestamp: i64 = row.try_get(1)?;
+                violations.push(TypeViolation {
+                    id: row.try_get(0)?,
+                    timestamp: DateTime::from_timestamp(timestamp, 0).unwrap_or_default(),
+                    value,
+                });
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{database::methods::create_mem_db, project::DataType, Error};
+
+    #[tokio::test]
+    async fn find_type_violations() {
+        let db = create_mem_db("find_type_violations").await;
+        let project = db.create("foo").await;
+        project
+            .create_column("count", DataType::Text)
+            .await
+            .unwrap();
+
+        for count in ["42", "x"] {
+            let data = HashMap::from([("count".to_string(), count.to_string())]);
+            project.add_datapoint(data).await.unwrap();
+        }
+        let data = HashMap::from([("other".to_string(), "1".to_string())]);
+        project.add_datapoint(data).await.unwrap();
+
+        let violations = project
+            .find_type_violations("count", DataType::Integer)
+            .await
+            .unwrap();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].value, "x");
+
+        let violations = project
+            .find_type_violations("count", DataType::Text)
+            .await
+            .unwrap();
+        assert!(violations.is_empty());
+
+        let missing = project
+            .find_type_violations("weight", DataType::Integer)
+            .await;
+        assert!(matches!(missing, Err(Error::ColumnNotFound(_))));
+    }
+}
@@ -0,0 +1,184 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rust_decimal::Decimal;
+use sqlx::{
+    any::{AnyArguments, AnyRow},
+    query::Query,
+    Any, Row, TypeInfo, ValueRef,
+};
+
+use crate::project::DataType;
+
+/// A single value read from a project table
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Integer(i64),
+    Float(f64),
+    Decimal(Decimal),
+    Raw(Vec<u8>),
+}
+
+impl Value {
+    /// Parse a value from its text form as the given data type
+    ///
+    /// Decimals are parsed exactly, without going through floating point. Floats have to be
+    /// finite, so `inf`, `NaN` and numbers too large for a float are not valid.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::{project::DataType, value::Value};
+    /// assert_eq!(Value::parse("42", DataType::Integer), Some(Value::Integer(42)));
+    /// assert_eq!(Value::parse("forty two", DataType::Integer), None);
+    ///
+    /// let decimal = Value::parse("3.14159", DataType::Decimal).unwrap();
+    /// assert_eq!(decimal.to_string(), "3.14159");
+    /// ```
+    ///
+    /// # Returns
+    /// The value or None if the text is not valid for the data type
+    pub fn parse(text: &str, data_type: DataType) -> Option<Value> {
+        match data_type {
+            DataType::Text => Some(Value::Text(text.to_string())),
+            DataType::Integer | DataType::BigInteger => text.parse().ok().map(Value::Integer),
+            DataType::Float => text
+                .parse()
+                .ok()
+                .filter(|float: &f64| float.is_finite())
+                .map(Value::Float),
+            DataType::Decimal => Decimal::from_str_exact(text).ok().map(Value::Decimal),
+            DataType::Raw => Some(Value::Raw(text.as_bytes().to_vec())),
+        }
+    }
+
+    /// Bind the value to a query
+    ///
+    /// Decimals are bound as text, since the generic driver has no decimal type. The insert
+    /// query is responsible for casting them where the backend needs it.
+    pub(crate) fn bind<'q>(
+        self,
+        query: Query<'q, Any, AnyArguments<'q>>,
+    ) -> Query<'q, Any, AnyArguments<'q>> {
+        match self {
+            Value::Text(text) => query.bind(text),
+            Value::Integer(integer) => query.bind(integer),
+            Value::Float(float) => query.bind(float),
+            Value::Decimal(decimal) => query.bind(decimal.to_string()),
+            Value::Raw(raw) => query.bind(raw),
+        }
+    }
+
+    /// Read a value from a row, decoding it as the given data type
+    ///
+    /// SQLite will store whatever it is given regardless of the column type, values that can not
+    /// be decoded as the declared type are read as whatever type they were stored as.
+    ///
+    /// # Returns
+    /// Some(Value) or None if the value is NULL
+    /// Error if the value could not be decoded as any type
+    pub fn read(
+        row: &AnyRow,
+        index: usize,
+        data_type: DataType,
+    ) -> Result<Option<Value>, sqlx::Error> {
+        if is_null(row, index)? {
+            return Ok(None);
+        }
+
+        let typed = match data_type {
+            DataType::Text => Value::read_text(row, index),
+            DataType::Integer | DataType::BigInteger => Value::read_integer(row, index),
+            DataType::Float => Value::read_float(row, index),
+            DataType::Decimal => Value::read_decimal(row, index),
+            DataType::Raw => Value::read_raw(row, index),
+        };
+
+        typed.or_else(|e| {
+            Value::read_text(row, index)
+                .or_else(|_| Value::read_integer(row, index))
+                .or_else(|_| Value::read_float(row, index))
+                .or_else(|_| Value::read_raw(row, index))
+                .map_err(|_| e)
+        })
+    }
+
+    fn read_text(row: &AnyRow, index: usize) -> Result<Option<Value>, sqlx::Error> {
+        Ok(row.try_get::<Option<String>, _>(index)?.map(Value::Text))
+    }
+
+    fn read_integer(row: &AnyRow, index: usize) -> Result<Option<Value>, sqlx::Error> {
+        // SQLite integers are selected as text, see [crate::project::typed_column_list]
+        if let Ok(text) = row.try_get::<String, _>(index) {
+            return text
+                .parse()
+                .map(|integer| Some(Value::Integer(integer)))
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)));
+        }
+
+        Ok(row.try_get::<Option<i64>, _>(index)?.map(Value::Integer))
+    }
+
+    fn read_float(row: &AnyRow, index: usize) -> Result<Option<Value>, sqlx::Error> {
+        Ok(row.try_get::<Option<f64>, _>(index)?.map(Value::Float))
+    }
+
+    fn read_decimal(row: &AnyRow, index: usize) -> Result<Option<Value>, sqlx::Error> {
+        match row.try_get::<Option<String>, _>(index)? {
+            Some(text) => Decimal::from_str_exact(&text)
+                .map(|decimal| Some(Value::Decimal(decimal)))
+                .map_err(|e| sqlx::Error::Decode(Box::new(e))),
+            None => Ok(None),
+        }
+    }
+
+    fn read_raw(row: &AnyRow, index: usize) -> Result<Option<Value>, sqlx::Error> {
+        Ok(row.try_get::<Option<Vec<u8>>, _>(index)?.map(Value::Raw))
+    }
+}
+
+/// Whether the value at the index of the row is NULL
+///
+/// The Any driver never reports a value as NULL, so decoding NULL into an Option fails. NULL
+/// values do keep the NULL type name, which is checked for instead.
+pub(crate) fn is_null(row: &AnyRow, index: usize) -> Result<bool, sqlx::Error> {
+    Ok(row.try_get_raw(index)?.type_info().name() == "NULL")
+}
+
+impl Value {
+    /// Json form of the value
+    ///
+    /// Integers and floats become numbers, raw bytes a base64 string. Decimals are kept as
+    /// strings, since json numbers are usually read as floating point and would lose digits.
+    /// Floats that json can not hold, like NaN, become null.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use database::value::Value;
+    /// # use serde_json::json;
+    /// assert_eq!(Value::Integer(42).to_json(), json!(42));
+    /// assert_eq!(Value::Raw(b"hi".to_vec()).to_json(), json!("aGk="));
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Text(text) => serde_json::Value::String(text.clone()),
+            Value::Integer(integer) => serde_json::Value::from(*integer),
+            Value::Float(float) => serde_json::Number::from_f64(*float)
+                .map(serde_json::Value::Number)
+                .unwrap_or(serde_json::Value::Null),
+            Value::Decimal(decimal) => serde_json::Value::String(decimal.to_string()),
+            Value::Raw(raw) => serde_json::Value::String(STANDARD.encode(raw)),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    /// Text form of the value, raw bytes are shown as lossy UTF-8
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Integer(integer) => write!(f, "{}", integer),
+            Value::Float(float) => write!(f, "{}", float),
+            Value::Decimal(decimal) => write!(f, "{}", decimal),
+            Value::Raw(raw) => write!(f, "{}", String::from_utf8_lossy(raw)),
+        }
+    }
+}